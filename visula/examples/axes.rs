@@ -0,0 +1,116 @@
+use bytemuck::{Pod, Zeroable};
+use clap::Parser;
+use glam::Vec3;
+use std::path::PathBuf;
+use visula::{
+    colormap, Axes3D, Colormap, InstanceBuffer, InstanceDeviceExt, RenderData, Renderable,
+    ShadowRenderData, SphereGeometry, SphereMaterial, Spheres,
+};
+use visula_derive::Instance;
+
+#[derive(Parser, Debug)]
+#[command(about = "Plot the Lorenz attractor inside axes using Visula")]
+struct Args {
+    #[arg(
+        value_name = "FONT_FILE",
+        default_value = "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf"
+    )]
+    font_path: PathBuf,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Instance, Pod, Zeroable)]
+struct PointData {
+    position: [f32; 3],
+    time: f32,
+}
+
+struct Simulation {
+    spheres: Spheres,
+    axes: Axes3D,
+    _point_buffer: InstanceBuffer<PointData>,
+}
+
+impl Simulation {
+    fn new(application: &mut visula::Application) -> Simulation {
+        let args = Args::parse();
+        let font_data = std::fs::read(&args.font_path).expect("Could not read font file");
+
+        let (sigma, rho, beta) = (10.0, 28.0, 8.0 / 3.0);
+        let dt = 0.005;
+        let count = 8000;
+        let mut position = Vec3::new(1.0, 1.0, 1.0);
+        let mut points = Vec::with_capacity(count);
+        for i in 0..count {
+            let velocity = Vec3::new(
+                sigma * (position.y - position.x),
+                position.x * (rho - position.z) - position.y,
+                position.x * position.y - beta * position.z,
+            );
+            position += velocity * dt;
+            points.push(PointData {
+                position: position.to_array(),
+                time: i as f32 / count as f32,
+            });
+        }
+        let (min, max) = points.iter().fold(
+            (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+            |(min, max), point| {
+                let position = Vec3::from(point.position);
+                (min.min(position), max.max(position))
+            },
+        );
+
+        let point_buffer: InstanceBuffer<PointData> = application.device.create_instance_buffer();
+        point_buffer.update(&application.device, &application.queue, &points);
+        let point = point_buffer.instance();
+        let spheres = Spheres::new(
+            &application.rendering_descriptor(),
+            &SphereGeometry {
+                position: point.position,
+                radius: 0.2.into(),
+                color: colormap(point.time, Colormap::Viridis),
+            },
+            &SphereMaterial::default(),
+        )
+        .unwrap();
+
+        let axes = Axes3D::new(
+            &application.rendering_descriptor(),
+            &font_data,
+            min.floor(),
+            max.ceil(),
+        )
+        .unwrap();
+        application.camera_controller.target_transform.center = 0.5 * (min + max);
+
+        Simulation {
+            spheres,
+            axes,
+            _point_buffer: point_buffer,
+        }
+    }
+}
+
+impl visula::Simulation for Simulation {
+    fn update(&mut self, application: &mut visula::Application) {
+        self.axes.update(
+            &application.device,
+            &application.queue,
+            &application.camera_controller,
+        );
+    }
+
+    fn render(&mut self, data: &mut RenderData) {
+        self.spheres.render(data);
+        self.axes.render(data);
+    }
+
+    fn render_shadow(&mut self, data: &mut ShadowRenderData) {
+        self.spheres.render_shadow(data);
+    }
+}
+
+fn main() {
+    visula::run(Simulation::new);
+}
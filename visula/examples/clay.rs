@@ -345,6 +345,7 @@ impl Simulation {
             },
             &SphereMaterial {
                 color: Expression::from(glam::Vec3::new(0.2, 0.8, 0.6)).lit(),
+                ..Default::default()
             },
         )
         .unwrap();
@@ -0,0 +1,149 @@
+use bytemuck::{Pod, Zeroable};
+use glam::Vec3;
+use visula::{
+    ComputePipeline, ComputePipelineDescriptor, Expression, InstanceBuffer, RenderData, Renderable,
+    SphereGeometry, SphereMaterial, Spheres, UniformBuffer,
+};
+use visula_derive::{Delegate, Instance, Uniform};
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Instance, Pod, Zeroable)]
+struct Particle {
+    position: Vec3,
+    velocity: Vec3,
+}
+
+#[repr(C, align(16))]
+#[derive(Clone, Copy, Debug, Pod, Uniform, Zeroable)]
+struct Settings {
+    time_step: f32,
+    gravity: f32,
+    _padding: [f32; 2],
+}
+
+#[derive(Delegate)]
+struct Integration {
+    position: Expression,
+    velocity: Expression,
+    time_step: Expression,
+    gravity: Expression,
+}
+
+const KERNEL: &str = r#"
+struct Integration {
+    position: vec3<f32>,
+    velocity: vec3<f32>,
+    time_step: f32,
+    gravity: f32,
+}
+
+@compute @workgroup_size(64)
+fn cs_main() {
+    var integration: Integration;
+    integration.velocity.y -= integration.gravity * integration.time_step;
+    integration.position += integration.velocity * integration.time_step;
+    if integration.position.y < 0.0 {
+        integration.position.y = -integration.position.y;
+        integration.velocity.y = -0.9 * integration.velocity.y;
+    }
+}
+"#;
+
+struct Simulation {
+    spheres: Spheres,
+    integration: ComputePipeline,
+    settings: Settings,
+    settings_buffer: UniformBuffer<Settings>,
+}
+
+impl Simulation {
+    fn new(application: &mut visula::Application) -> Simulation {
+        let side = 100;
+        let particles: Vec<Particle> = (0..side * side)
+            .map(|i| {
+                let x = (i % side) as f32 - side as f32 / 2.0;
+                let z = (i / side) as f32 - side as f32 / 2.0;
+                Particle {
+                    position: Vec3::new(x, 20.0 + 0.1 * (x * x + z * z).sqrt(), z),
+                    velocity: Vec3::ZERO,
+                }
+            })
+            .collect();
+        let particle_buffer = InstanceBuffer::new_with_init(&application.device, &particles);
+        let particle = particle_buffer.instance();
+
+        let settings = Settings {
+            time_step: 0.0,
+            gravity: 9.81,
+            _padding: [0.0; 2],
+        };
+        let settings_buffer = UniformBuffer::new_with_init(&application.device, &settings);
+        let settings_uniform = settings_buffer.uniform();
+
+        let integration = ComputePipeline::new(
+            &application.device,
+            &ComputePipelineDescriptor {
+                label: "integration",
+                shader_source: KERNEL,
+                shader_variable_name: "integration",
+            },
+            &Integration {
+                position: particle.position.clone(),
+                velocity: particle.velocity,
+                time_step: settings_uniform.time_step,
+                gravity: settings_uniform.gravity,
+            },
+        )
+        .unwrap();
+
+        // The spheres read the same buffer that the kernel writes to.
+        let spheres = Spheres::new(
+            &application.rendering_descriptor(),
+            &SphereGeometry {
+                position: particle.position,
+                radius: 0.4.into(),
+                color: Vec3::new(0.9, 0.5, 0.2).into(),
+            },
+            &SphereMaterial::default(),
+        )
+        .unwrap();
+
+        Simulation {
+            spheres,
+            integration,
+            settings,
+            settings_buffer,
+        }
+    }
+}
+
+impl visula::Simulation for Simulation {
+    fn update(&mut self, application: &mut visula::Application) {
+        self.settings.time_step = application.time_step();
+        self.settings_buffer
+            .update(&application.queue, &self.settings);
+        let mut encoder =
+            application
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("integration encoder"),
+                });
+        self.integration.dispatch(&application.device, &mut encoder);
+        application.queue.submit(Some(encoder.finish()));
+    }
+
+    fn render(&mut self, data: &mut RenderData) {
+        self.spheres.render(data);
+    }
+
+    fn gui(&mut self, _application: &visula::Application, context: &egui::Context) {
+        egui::Window::new("Settings").show(context, |ui| {
+            ui.label("Gravity");
+            ui.add(egui::Slider::new(&mut self.settings.gravity, 0.0..=30.0));
+        });
+    }
+}
+
+fn main() {
+    visula::run(Simulation::new);
+}
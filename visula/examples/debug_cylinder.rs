@@ -55,6 +55,7 @@ impl Simulation {
             },
             &CylinderMaterial {
                 color: Expression::InputColor.toon_lit(),
+                ..Default::default()
             },
         )
         .unwrap();
@@ -99,6 +100,7 @@ impl Simulation {
             },
             &SphereMaterial {
                 color: Expression::InputColor.toon_lit(),
+                ..Default::default()
             },
         )
         .unwrap();
@@ -134,6 +136,7 @@ impl visula::Simulation for Simulation {
 
     fn render_shadow(&mut self, data: &mut ShadowRenderData) {
         self.cylinder.render_shadow(data);
+        self.torus_mesh.render_shadow(data);
         self.soma_spheres.render_shadow(data);
     }
 
@@ -0,0 +1,56 @@
+use bytemuck::{Pod, Zeroable};
+use glam::Vec3;
+use visula::{Environment, Expression, Pbr, SphereGeometry, SphereMaterial, Spheres};
+use visula_derive::Instance;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Instance, Pod, Zeroable)]
+struct Sample {
+    position: Vec3,
+    metallic: f32,
+    roughness: f32,
+}
+
+fn main() {
+    let path = std::env::args()
+        .nth(1)
+        .expect("usage: environment <equirectangular .hdr file>");
+    visula::run(move |application| {
+        let environment =
+            Environment::from_hdr_file(&application.device, &application.queue, &path).unwrap();
+        application.set_environment(environment);
+
+        let side = 5;
+        let samples: Vec<Sample> = (0..side * side)
+            .map(|i| {
+                let x = i % side;
+                let y = i / side;
+                Sample {
+                    position: 2.5 * Vec3::new(x as f32 - 2.0, y as f32 - 2.0, 0.0),
+                    metallic: y as f32 / (side - 1) as f32,
+                    roughness: x as f32 / (side - 1) as f32,
+                }
+            })
+            .collect();
+        let sample = application.instances(&samples);
+        Spheres::new(
+            &application.rendering_descriptor(),
+            &SphereGeometry {
+                position: sample.position,
+                radius: 1.0.into(),
+                color: Vec3::new(0.95, 0.95, 0.95).into(),
+            },
+            &SphereMaterial {
+                color: Pbr {
+                    color: Expression::InputColor,
+                    metallic: sample.metallic,
+                    roughness: sample.roughness,
+                    ..Default::default()
+                }
+                .into(),
+                ..Default::default()
+            },
+        )
+        .unwrap()
+    });
+}
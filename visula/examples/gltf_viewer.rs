@@ -4,8 +4,8 @@ use std::io::BufReader;
 
 use clap::Parser;
 use visula::{
-    io::gltf::{parse_gltf, GltfMesh},
-    Expression, MeshGeometry, MeshMaterial, MeshPipeline, RenderData,
+    io::gltf::parse_gltf, Expression, MeshGeometry, MeshMaterial, MeshPipeline, RenderData,
+    Renderable, ShadowRenderData,
 };
 
 #[derive(Parser)]
@@ -55,19 +55,11 @@ impl Simulation {
                     &MeshGeometry::default(),
                     &MeshMaterial {
                         color: color_expression,
+                        ..Default::default()
                     },
                 )
                 .unwrap();
-                let GltfMesh {
-                    vertex_buffer,
-                    index_buffer,
-                    index_count,
-                    ..
-                } = mesh;
-
-                mesh_pipeline.vertex_count = index_count;
-                mesh_pipeline.vertex_buffer = vertex_buffer;
-                mesh_pipeline.index_buffer = index_buffer;
+                mesh_pipeline.set_gltf_mesh(&mesh);
                 mesh_pipeline
             })
             .collect();
@@ -85,6 +77,12 @@ impl visula::Simulation for Simulation {
             pipeline.render(data);
         }
     }
+
+    fn render_shadow(&mut self, data: &mut ShadowRenderData) {
+        for pipeline in &self.mesh_pipelines {
+            pipeline.render_shadow(data);
+        }
+    }
 }
 
 fn main() {
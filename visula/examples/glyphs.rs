@@ -0,0 +1,139 @@
+use bytemuck::{Pod, Zeroable};
+use glam::{Quat, Vec3};
+use visula::{
+    colormap, ArrowGeometry, ArrowMaterial, Arrows, BoxGeometry, BoxMaterial, Boxes,
+    CapsuleGeometry, CapsuleMaterial, Capsules, Colormap, ConeGeometry, ConeMaterial, Cones,
+    EllipsoidGeometry, EllipsoidMaterial, Ellipsoids, InstanceBuffer, InstanceDeviceExt,
+    RenderData, Renderable, ShadowRenderData,
+};
+use visula_derive::Instance;
+
+#[repr(C)]
+#[derive(Clone, Copy, Instance, Pod, Zeroable)]
+struct ArrowData {
+    position: [f32; 3],
+    direction: [f32; 3],
+}
+
+struct Simulation {
+    arrows: Arrows,
+    _arrow_buffer: InstanceBuffer<ArrowData>,
+    cones: Cones,
+    capsules: Capsules,
+    ellipsoids: Ellipsoids,
+    boxes: Boxes,
+}
+
+impl Simulation {
+    fn new(application: &mut visula::Application) -> Simulation {
+        let side = 12;
+        let arrow_data: Vec<ArrowData> = (0..side * side)
+            .map(|i| {
+                let x = (i % side) as f32 - side as f32 / 2.0;
+                let z = (i / side) as f32 - side as f32 / 2.0;
+                let direction = Vec3::new(-z, 0.0, x) * 0.15;
+                ArrowData {
+                    position: [x, -2.0, z],
+                    direction: direction.to_array(),
+                }
+            })
+            .collect();
+        let arrow_buffer: InstanceBuffer<ArrowData> = application.device.create_instance_buffer();
+        arrow_buffer.update(&application.device, &application.queue, &arrow_data);
+        let arrow = arrow_buffer.instance();
+        let arrows = Arrows::new(
+            &application.rendering_descriptor(),
+            &ArrowGeometry {
+                start: arrow.position.clone(),
+                end: &arrow.position + &arrow.direction,
+                color: colormap(arrow.direction.length() / 1.2, Colormap::Viridis),
+                ..Default::default()
+            },
+            &ArrowMaterial::default(),
+        )
+        .unwrap();
+
+        let cones = Cones::new(
+            &application.rendering_descriptor(),
+            &ConeGeometry {
+                start: Vec3::new(-4.0, 0.0, 0.0).into(),
+                end: Vec3::new(-4.0, 1.5, 0.0).into(),
+                radius: 0.5.into(),
+                color: Vec3::new(0.9, 0.4, 0.2).into(),
+            },
+            &ConeMaterial::default(),
+        )
+        .unwrap();
+        let capsules = Capsules::new(
+            &application.rendering_descriptor(),
+            &CapsuleGeometry {
+                start: Vec3::new(-1.5, 0.5, -0.5).into(),
+                end: Vec3::new(-0.5, 1.0, 0.5).into(),
+                radius: 0.4.into(),
+                color: Vec3::new(0.3, 0.8, 0.4).into(),
+            },
+            &CapsuleMaterial::default(),
+        )
+        .unwrap();
+        let ellipsoids = Ellipsoids::new(
+            &application.rendering_descriptor(),
+            &EllipsoidGeometry {
+                position: Vec3::new(1.5, 0.75, 0.0).into(),
+                rotation: Quat::from_rotation_z(0.5).into(),
+                radii: Vec3::new(1.0, 0.5, 0.3).into(),
+                color: Vec3::new(0.3, 0.5, 0.9).into(),
+            },
+            &EllipsoidMaterial::default(),
+        )
+        .unwrap();
+        let boxes = Boxes::new(
+            &application.rendering_descriptor(),
+            &BoxGeometry {
+                position: Vec3::new(4.0, 0.75, 0.0).into(),
+                rotation: Quat::from_rotation_y(0.6).into(),
+                size: Vec3::new(1.5, 1.5, 1.0).into(),
+                corner_radius: 0.2.into(),
+                color: Vec3::new(0.9, 0.8, 0.3).into(),
+            },
+            &BoxMaterial::default(),
+        )
+        .unwrap();
+
+        Simulation {
+            arrows,
+            _arrow_buffer: arrow_buffer,
+            cones,
+            capsules,
+            ellipsoids,
+            boxes,
+        }
+    }
+
+    fn renderables(&self) -> [&dyn Renderable; 5] {
+        [
+            &self.arrows,
+            &self.cones,
+            &self.capsules,
+            &self.ellipsoids,
+            &self.boxes,
+        ]
+    }
+}
+
+impl visula::Simulation for Simulation {
+    fn render(&mut self, data: &mut RenderData) {
+        for renderable in self.renderables() {
+            renderable.render(data);
+        }
+    }
+
+    fn render_shadow(&mut self, data: &mut ShadowRenderData) {
+        for renderable in self.renderables() {
+            renderable.render_shadow(data);
+        }
+    }
+}
+
+fn main() {
+    visula::run(Simulation::new);
+}
@@ -0,0 +1,26 @@
+use visula::{
+    colormap, vec3, Colormap, HeadlessApplication, SphereGeometry, SphereMaterial, Spheres,
+};
+
+fn main() -> Result<(), visula::error::Error> {
+    env_logger::init();
+    let mut application = pollster::block_on(HeadlessApplication::new(1280, 720))?;
+    application.camera_controller.target_transform.distance = 40.0;
+
+    let data: Vec<f32> = (0..10_000).map(|i| i as f32 * 0.01).collect();
+    let t = application.instances(&data);
+    let mut spheres = Spheres::new(
+        &application.rendering_descriptor(),
+        &SphereGeometry {
+            position: 10.0 * vec3(t.cos(), t.sin(), &t / 50.0 - 1.0),
+            radius: 0.2.into(),
+            color: colormap(&t / 100.0, Colormap::Viridis),
+        },
+        &SphereMaterial::default(),
+    )?;
+
+    application.update();
+    application.render(&mut spheres);
+    application.save_png("headless.png")?;
+    Ok(())
+}
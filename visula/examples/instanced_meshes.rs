@@ -0,0 +1,127 @@
+use bytemuck::{Pod, Zeroable};
+use glam::{Quat, Vec3, Vec4};
+use visula::{
+    primitives::generate_torus, CustomEvent, Expression, InstanceBuffer, MeshGeometry,
+    MeshMaterial, MeshPipeline, PickingRenderData, RenderData, Renderable, ShadowRenderData,
+};
+use visula_derive::Instance;
+use winit::{
+    dpi::PhysicalPosition,
+    event::{ElementState, Event, MouseButton, WindowEvent},
+};
+
+#[repr(C, align(16))]
+#[derive(Clone, Copy, Instance, Pod, Zeroable)]
+struct MeshInstanceData {
+    position: Vec3,
+    _padding: f32,
+    rotation: Quat,
+    color: Vec4,
+}
+
+struct Simulation {
+    mesh: MeshPipeline,
+    instances: Vec<MeshInstanceData>,
+    instance_buffer: InstanceBuffer<MeshInstanceData>,
+    cursor_position: PhysicalPosition<f64>,
+    time: f32,
+}
+
+impl Simulation {
+    fn new(application: &mut visula::Application) -> Simulation {
+        let side = 20;
+        let instances: Vec<MeshInstanceData> = (0..side * side * side)
+            .map(|i| {
+                let index = Vec3::new(
+                    (i % side) as f32,
+                    ((i / side) % side) as f32,
+                    (i / (side * side)) as f32,
+                );
+                let relative = index / (side - 1) as f32;
+                MeshInstanceData {
+                    position: 2.0 * (index - Vec3::splat((side - 1) as f32 / 2.0)),
+                    _padding: Default::default(),
+                    rotation: Quat::IDENTITY,
+                    color: relative.extend(1.0),
+                }
+            })
+            .collect();
+        let instance_buffer = InstanceBuffer::<MeshInstanceData>::new(&application.device);
+        let instance = instance_buffer.instance();
+
+        let mut mesh = MeshPipeline::new(
+            &application.rendering_descriptor(),
+            &MeshGeometry {
+                position: instance.position,
+                rotation: instance.rotation,
+                color: instance.color,
+                ..Default::default()
+            },
+            &MeshMaterial {
+                color: Expression::InputColor.lit(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let (vertices, indices) = generate_torus(0.6, 0.2, 24, 12, [255, 255, 255, 255]);
+        mesh.set_mesh_data(&application.device, &vertices, &indices);
+
+        Simulation {
+            mesh,
+            instances,
+            instance_buffer,
+            cursor_position: PhysicalPosition::default(),
+            time: 0.0,
+        }
+    }
+}
+
+impl visula::Simulation for Simulation {
+    fn handle_event(&mut self, application: &mut visula::Application, event: &Event<CustomEvent>) {
+        let Event::WindowEvent { event, .. } = event else {
+            return;
+        };
+        match event {
+            WindowEvent::CursorMoved { position, .. } => self.cursor_position = *position,
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } => {
+                let cursor_position = self.cursor_position;
+                if let Some(result) = application.pick(self, cursor_position) {
+                    if result.picking_id == self.mesh.picking_id() {
+                        self.instances[result.instance_index as usize].color = Vec4::ONE;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn update(&mut self, application: &mut visula::Application) {
+        self.time += application.time_step();
+        for (i, instance) in self.instances.iter_mut().enumerate() {
+            let axis = Vec3::new(1.0, i as f32 % 3.0, 1.0).normalize();
+            instance.rotation = Quat::from_axis_angle(axis, self.time + 0.1 * i as f32);
+        }
+        self.instance_buffer
+            .update(&application.device, &application.queue, &self.instances);
+    }
+
+    fn render(&mut self, data: &mut RenderData) {
+        self.mesh.render(data);
+    }
+
+    fn render_shadow(&mut self, data: &mut ShadowRenderData) {
+        self.mesh.render_shadow(data);
+    }
+
+    fn render_picking(&mut self, data: &mut PickingRenderData) {
+        self.mesh.render_picking(data);
+    }
+}
+
+fn main() {
+    visula::run(Simulation::new);
+}
@@ -0,0 +1,79 @@
+use glam::{Quat, Vec3};
+use ndarray::Array3;
+use visula::{
+    primitives::generate_isosurface, MeshGeometry, MeshMaterial, MeshPipeline, RenderData,
+    Renderable, ShadowRenderData,
+};
+
+struct Simulation {
+    mesh: MeshPipeline,
+    values: Array3<f32>,
+    iso_level: f32,
+}
+
+impl Simulation {
+    fn new(application: &mut visula::Application) -> Simulation {
+        let side = 48;
+        let centers = [
+            Vec3::new(0.3, 0.4, 0.5),
+            Vec3::new(0.7, 0.6, 0.4),
+            Vec3::new(0.5, 0.5, 0.8),
+        ];
+        let values = Array3::from_shape_fn((side, side, side), |(x, y, z)| {
+            let voxel = Vec3::new(x as f32, y as f32, z as f32) / side as f32;
+            centers
+                .iter()
+                .map(|center| (-voxel.distance_squared(*center) / 0.02).exp())
+                .sum()
+        });
+
+        let mut mesh = MeshPipeline::new(
+            &application.rendering_descriptor(),
+            &MeshGeometry {
+                rotation: Quat::IDENTITY.into(),
+                position: Vec3::splat(-5.0).into(),
+                scale: Vec3::splat(10.0 / side as f32).into(),
+            },
+            &MeshMaterial::default(),
+        )
+        .unwrap();
+        let iso_level = 0.5;
+        let (vertices, indices) = generate_isosurface(&values, iso_level, [230, 160, 60, 255]);
+        mesh.set_mesh_data(&application.device, &vertices, &indices);
+
+        Simulation {
+            mesh,
+            values,
+            iso_level,
+        }
+    }
+}
+
+impl visula::Simulation for Simulation {
+    fn render(&mut self, data: &mut RenderData) {
+        self.mesh.render(data);
+    }
+
+    fn render_shadow(&mut self, data: &mut ShadowRenderData) {
+        self.mesh.render_shadow(data);
+    }
+
+    fn gui(&mut self, application: &visula::Application, context: &egui::Context) {
+        egui::Window::new("Settings").show(context, |ui| {
+            ui.label("Iso level");
+            if ui
+                .add(egui::Slider::new(&mut self.iso_level, 0.05..=1.5))
+                .changed()
+            {
+                let (vertices, indices) =
+                    generate_isosurface(&self.values, self.iso_level, [230, 160, 60, 255]);
+                self.mesh
+                    .set_mesh_data(&application.device, &vertices, &indices);
+            }
+        });
+    }
+}
+
+fn main() {
+    visula::run(Simulation::new);
+}
@@ -0,0 +1,115 @@
+use bytemuck::{Pod, Zeroable};
+use clap::Parser;
+use glam::{Vec2, Vec3};
+use std::path::PathBuf;
+use visula::{
+    InstanceBuffer, InstanceDeviceExt, LabelGeometry, LabelOptions, Labels, RenderData, Renderable,
+    ShadowRenderData, SphereGeometry, SphereMaterial, Spheres,
+};
+use visula_derive::Instance;
+
+#[derive(Parser, Debug)]
+#[command(about = "Label spheres with text using Visula")]
+struct Args {
+    #[arg(
+        value_name = "FONT_FILE",
+        default_value = "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf"
+    )]
+    font_path: PathBuf,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Instance, Pod, Zeroable)]
+struct AtomData {
+    position: [f32; 3],
+    color: [f32; 3],
+}
+
+struct Simulation {
+    spheres: Spheres,
+    labels: Labels,
+    _atom_buffer: InstanceBuffer<AtomData>,
+}
+
+impl Simulation {
+    fn new(application: &mut visula::Application) -> Simulation {
+        let args = Args::parse();
+        let font_data = std::fs::read(&args.font_path).expect("Could not read font file");
+
+        let elements = [
+            ("C", Vec3::new(0.3, 0.3, 0.3)),
+            ("O", Vec3::new(0.9, 0.2, 0.2)),
+            ("N", Vec3::new(0.2, 0.3, 0.9)),
+            ("H", Vec3::new(0.9, 0.9, 0.9)),
+        ];
+        let side = 10;
+        let mut atoms = Vec::new();
+        let mut texts = Vec::new();
+        for i in 0..side * side * side {
+            let (element, color) = elements[(i * 7) % elements.len()];
+            let position = Vec3::new(
+                (i % side) as f32,
+                ((i / side) % side) as f32,
+                (i / (side * side)) as f32,
+            ) * 2.0
+                - Vec3::splat(side as f32);
+            atoms.push(AtomData {
+                position: position.to_array(),
+                color: color.to_array(),
+            });
+            texts.push(format!("{element}{i}"));
+        }
+        let atom_buffer: InstanceBuffer<AtomData> = application.device.create_instance_buffer();
+        atom_buffer.update(&application.device, &application.queue, &atoms);
+        let atom = atom_buffer.instance();
+
+        let spheres = Spheres::new(
+            &application.rendering_descriptor(),
+            &SphereGeometry {
+                position: atom.position.clone(),
+                radius: 0.5.into(),
+                color: atom.color.clone(),
+            },
+            &SphereMaterial::default(),
+        )
+        .unwrap();
+
+        // The labels are placed above the atoms, so that they are not hidden by the atoms they
+        // belong to.
+        let mut labels = Labels::new(
+            &application.rendering_descriptor(),
+            &LabelGeometry {
+                position: &atom.position + Vec3::new(0.0, 0.5, 0.0),
+                size: 18.0.into(),
+                offset: Vec2::new(0.0, 0.5).into(),
+                outline_width: 0.1.into(),
+                ..Default::default()
+            },
+            &font_data,
+            &LabelOptions::default(),
+        )
+        .unwrap();
+        labels.update(&application.queue, &texts);
+
+        Simulation {
+            spheres,
+            labels,
+            _atom_buffer: atom_buffer,
+        }
+    }
+}
+
+impl visula::Simulation for Simulation {
+    fn render(&mut self, data: &mut RenderData) {
+        self.spheres.render(data);
+        self.labels.render(data);
+    }
+
+    fn render_shadow(&mut self, data: &mut ShadowRenderData) {
+        self.spheres.render_shadow(data);
+    }
+}
+
+fn main() {
+    visula::run(Simulation::new);
+}
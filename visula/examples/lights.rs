@@ -0,0 +1,70 @@
+use glam::Vec3;
+use visula::{Light, RenderData, Renderable, SphereGeometry, SphereMaterial, Spheres};
+
+struct Simulation {
+    spheres: Spheres,
+    time: f32,
+}
+
+impl Simulation {
+    fn new(application: &mut visula::Application) -> Simulation {
+        let side = 20;
+        let positions: Vec<Vec3> = (0..side * side)
+            .map(|i| {
+                let x = (i % side) as f32 - side as f32 / 2.0;
+                let z = (i / side) as f32 - side as f32 / 2.0;
+                2.0 * Vec3::new(x, 0.0, z)
+            })
+            .collect();
+        let position = application.instances(&positions);
+
+        let spheres = Spheres::new(
+            &application.rendering_descriptor(),
+            &SphereGeometry {
+                position,
+                radius: 0.8.into(),
+                color: Vec3::splat(0.8).into(),
+            },
+            &SphereMaterial::default(),
+        )
+        .unwrap();
+
+        let light = &mut application.light;
+        light.intensity = 0.2;
+        light.ambient = 0.05;
+        light.fill_intensity = 0.0;
+        light.lights = vec![
+            Light::point(Vec3::ZERO, 15.0)
+                .with_color(Vec3::new(1.0, 0.3, 0.2))
+                .with_intensity(20.0),
+            Light::point(Vec3::ZERO, 15.0)
+                .with_color(Vec3::new(0.2, 0.4, 1.0))
+                .with_intensity(20.0),
+            Light::spot(Vec3::new(0.0, 15.0, 0.0), Vec3::NEG_Y, 40.0, 0.3, 0.4)
+                .with_intensity(100.0),
+        ];
+
+        Simulation { spheres, time: 0.0 }
+    }
+}
+
+impl visula::Simulation for Simulation {
+    fn update(&mut self, application: &mut visula::Application) {
+        self.time += application.time_step();
+        for (i, light) in application.light.lights.iter_mut().take(2).enumerate() {
+            let angle = self.time + i as f32 * std::f32::consts::PI;
+            light.kind = visula::LightKind::Point {
+                position: Vec3::new(8.0 * angle.cos(), 2.0, 8.0 * angle.sin()),
+                range: 15.0,
+            };
+        }
+    }
+
+    fn render(&mut self, data: &mut RenderData) {
+        self.spheres.render(data);
+    }
+}
+
+fn main() {
+    visula::run(Simulation::new);
+}
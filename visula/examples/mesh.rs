@@ -1,6 +1,7 @@
 use glam::Vec3;
 use visula::{
     primitives::mesh_primitive::MeshVertexAttributes, MeshGeometry, MeshMaterial, MeshPipeline,
+    Renderable,
 };
 
 const PI: f32 = std::f32::consts::PI;
@@ -1,5 +1,4 @@
 use bytemuck::{Pod, Zeroable};
-use chrono::{DateTime, TimeDelta, Utc};
 use clap::Parser;
 use glam::Vec3;
 use itertools::Itertools;
@@ -200,7 +199,7 @@ struct Simulation {
     bounding_box: BoundingBox,
     count: usize,
     target_temperature: f32,
-    last_update: DateTime<Utc>,
+    elapsed_time: f32,
 }
 
 impl Simulation {
@@ -270,7 +269,7 @@ impl Simulation {
             },
             count,
             target_temperature: 10.0,
-            last_update: Utc::now(),
+            elapsed_time: 0.0,
         }
     }
 
@@ -282,14 +281,14 @@ impl Simulation {
 impl visula::Simulation for Simulation {
     fn update(&mut self, application: &mut visula::Application) {
         let mut bond_data = Vec::new();
-        let current_time = Utc::now();
-        let time_diff = current_time - self.last_update;
+        // Use the application's time step rather than the wall clock so that recordings
+        // advance the simulation by the same amount every frame.
+        self.elapsed_time += application.time_step();
         let target_fps = self.settings.speed as f32 * 60.0;
-        if time_diff < TimeDelta::milliseconds((1000.0 / target_fps) as i64) {
+        if self.elapsed_time < 1.0 / target_fps {
             return;
         }
-        let steps = ((target_fps * time_diff.num_milliseconds() as f32 / 1000.0) as i32)
-            .min(self.settings.speed);
+        let steps = ((target_fps * self.elapsed_time) as i32).min(self.settings.speed);
         if steps == 0 {
             return;
         }
@@ -317,7 +316,7 @@ impl visula::Simulation for Simulation {
             .update(&application.device, &application.queue, &self.particles);
         self.settings_buffer
             .update(&application.queue, &self.settings);
-        self.last_update = current_time;
+        self.elapsed_time = 0.0;
     }
 
     fn render(&mut self, data: &mut RenderData) {
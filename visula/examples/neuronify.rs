@@ -273,6 +273,7 @@ impl Simulation {
             },
             &SphereMaterial {
                 color: Expression::InputColor.lit(),
+                ..Default::default()
             },
         )
         .unwrap();
@@ -286,6 +287,7 @@ impl Simulation {
             },
             &LineMaterial {
                 color: Expression::from(glam::Vec3::new(1.0, 0.8, 1.0)).lit(),
+                ..Default::default()
             },
         )
         .unwrap();
@@ -302,6 +304,7 @@ impl Simulation {
             },
             &LineMaterial {
                 color: Expression::from(glam::Vec3::new(1.0, 0.8, 1.0)).lit(),
+                ..Default::default()
             },
         )
         .unwrap();
@@ -335,10 +338,11 @@ impl Simulation {
             &MeshGeometry {
                 position: mesh_instance.position,
                 rotation: mesh_instance.rotation,
-                scale: Vec3::ONE.into(),
+                ..Default::default()
             },
             &MeshMaterial {
                 color: Expression::from(Vec4::new(1.0, 1.0, 1.0, 1.0)).lit(),
+                ..Default::default()
             },
         )
         .unwrap();
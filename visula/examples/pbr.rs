@@ -0,0 +1,50 @@
+use bytemuck::{Pod, Zeroable};
+use glam::Vec3;
+use visula::{Expression, Pbr, SphereGeometry, SphereMaterial, Spheres};
+use visula_derive::Instance;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Instance, Pod, Zeroable)]
+struct Sample {
+    position: Vec3,
+    metallic: f32,
+    roughness: f32,
+}
+
+fn main() {
+    visula::run(|application| {
+        // Metallic increases along x and roughness along z.
+        let side = 7;
+        let samples: Vec<Sample> = (0..side * side)
+            .map(|i| {
+                let x = i % side;
+                let z = i / side;
+                Sample {
+                    position: 2.5 * Vec3::new(x as f32 - 3.0, 0.0, z as f32 - 3.0),
+                    metallic: x as f32 / (side - 1) as f32,
+                    roughness: z as f32 / (side - 1) as f32,
+                }
+            })
+            .collect();
+        let sample = application.instances(&samples);
+        Spheres::new(
+            &application.rendering_descriptor(),
+            &SphereGeometry {
+                position: sample.position,
+                radius: 1.0.into(),
+                color: Vec3::new(0.9, 0.6, 0.3).into(),
+            },
+            &SphereMaterial {
+                color: Pbr {
+                    color: Expression::InputColor,
+                    metallic: sample.metallic,
+                    roughness: sample.roughness,
+                    ..Default::default()
+                }
+                .into(),
+                ..Default::default()
+            },
+        )
+        .unwrap()
+    });
+}
@@ -1,7 +1,7 @@
 use glam::Vec3;
 use visula::{
     primitives::mesh_primitive::MeshVertexAttributes, Expression, MeshGeometry, MeshMaterial,
-    MeshPipeline,
+    MeshPipeline, Renderable,
 };
 use visula_core::TextureBuffer;
 
@@ -28,6 +28,7 @@ impl PlaneExample {
             },
             &MeshMaterial {
                 color: texture.sample(&Expression::UV),
+                ..Default::default()
             },
         )
         .unwrap();
@@ -0,0 +1,35 @@
+use glam::Vec3;
+use visula::{SphereGeometry, SphereMaterial, Spheres};
+
+fn main() {
+    visula::run(|application| {
+        let side = 1000;
+        let count = side * side;
+        // Positions, radii and colors are kept in separate storage buffers rather than one
+        // interleaved vertex buffer.
+        let positions: Vec<Vec3> = (0..count)
+            .map(|i| {
+                let x = (i % side) as f32 / side as f32 - 0.5;
+                let z = (i / side) as f32 / side as f32 - 0.5;
+                let y = 0.1 * (20.0 * x).sin() * (20.0 * z).cos();
+                100.0 * Vec3::new(x, y, z)
+            })
+            .collect();
+        let radii: Vec<f32> = positions.iter().map(|p| 0.04 + 0.002 * p.y).collect();
+        let colors: Vec<Vec3> = positions
+            .iter()
+            .map(|p| Vec3::new(0.5 + 0.05 * p.y, 0.4, 0.6 - 0.05 * p.y))
+            .collect();
+
+        Spheres::new(
+            &application.rendering_descriptor(),
+            &SphereGeometry {
+                position: application.storage_instances(&positions),
+                radius: application.storage_instances(&radii),
+                color: application.storage_instances(&colors),
+            },
+            &SphereMaterial::default(),
+        )
+        .unwrap()
+    });
+}
@@ -77,7 +77,10 @@ fn create_sphere_variant(
             radius: sphere.radius,
             color: sphere.color,
         },
-        &SphereMaterial { color: color_expr },
+        &SphereMaterial {
+            color: color_expr,
+            ..Default::default()
+        },
     )
 }
 
@@ -126,7 +129,10 @@ impl Simulation {
                     width: 0.3.into(),
                     color: vec3(0.8, 0.8, 0.8),
                 },
-                &LineMaterial { color: color_expr },
+                &LineMaterial {
+                    color: color_expr,
+                    ..Default::default()
+                },
             )
             .unwrap()
         };
@@ -170,6 +176,7 @@ impl Simulation {
             },
             &MeshMaterial {
                 color: Expression::from(Vec4::new(0.8, 0.3, 0.2, 1.0)).lit(),
+                ..Default::default()
             },
         )
         .unwrap();
@@ -182,6 +189,7 @@ impl Simulation {
             },
             &MeshMaterial {
                 color: Expression::from(Vec4::new(0.9, 0.9, 0.9, 1.0)).lit(),
+                ..Default::default()
             },
         )
         .unwrap();
@@ -273,6 +281,7 @@ impl visula::Simulation for Simulation {
     fn render_shadow(&mut self, data: &mut ShadowRenderData) {
         self.sphere_variants.flat.render_shadow(data);
         self.line_variants.flat.render_shadow(data);
+        self.mesh.render_shadow(data);
     }
 
     fn gui(&mut self, application: &visula::Application, context: &egui::Context) {
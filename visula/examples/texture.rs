@@ -1,7 +1,7 @@
 use glam::Vec3;
 use visula::{
     primitives::mesh_primitive::MeshVertexAttributes, Expression, MeshGeometry, MeshMaterial,
-    MeshPipeline,
+    MeshPipeline, Renderable,
 };
 use visula_core::TextureBuffer;
 
@@ -108,6 +108,7 @@ impl TextureExample {
             &MeshGeometry::default(),
             &MeshMaterial {
                 color: texture.sample(&Expression::UV).lit(),
+                ..Default::default()
             },
         )
         .unwrap();
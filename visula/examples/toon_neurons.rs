@@ -510,6 +510,7 @@ impl ToonNeurons {
             },
             &CylinderMaterial {
                 color: Expression::InputColor.toon_lit(),
+                ..Default::default()
             },
         )
         .unwrap();
@@ -527,6 +528,7 @@ impl ToonNeurons {
             },
             &SphereMaterial {
                 color: Expression::InputColor.toon_lit(),
+                ..Default::default()
             },
         )
         .unwrap();
@@ -546,6 +548,7 @@ impl ToonNeurons {
             },
             &CylinderMaterial {
                 color: Expression::InputColor.toon_lit(),
+                ..Default::default()
             },
         )
         .unwrap();
@@ -563,6 +566,7 @@ impl ToonNeurons {
             },
             &SphereMaterial {
                 color: Expression::InputColor.toon_lit(),
+                ..Default::default()
             },
         )
         .unwrap();
@@ -590,6 +594,7 @@ impl ToonNeurons {
                 },
                 &MeshMaterial {
                     color: Expression::InputColor.toon_lit(),
+                    ..Default::default()
                 },
             )
             .unwrap();
@@ -608,6 +613,7 @@ impl ToonNeurons {
             },
             &MeshMaterial {
                 color: Expression::InputColor.toon_lit(),
+                ..Default::default()
             },
         )
         .unwrap();
@@ -624,6 +630,7 @@ impl ToonNeurons {
             },
             &MeshMaterial {
                 color: Expression::InputColor.toon_lit(),
+                ..Default::default()
             },
         )
         .unwrap();
@@ -696,6 +703,9 @@ impl visula::Simulation for ToonNeurons {
         self.joint_spheres.render_shadow(data);
         self.axon_cylinders.render_shadow(data);
         self.soma_spheres.render_shadow(data);
+        for mesh in &self.synapse_meshes {
+            mesh.render_shadow(data);
+        }
     }
 
     fn gui(&mut self, application: &visula::Application, context: &egui::Context) {
@@ -0,0 +1,83 @@
+use glam::Vec3;
+use visula::{
+    Expression, RenderData, Renderable, ShadowRenderData, SphereGeometry, SphereMaterial, Spheres,
+    TransparentRenderData,
+};
+
+struct Simulation {
+    organelles: Spheres,
+    membranes: Spheres,
+}
+
+impl Simulation {
+    fn new(application: &mut visula::Application) -> Simulation {
+        let side = 4;
+        let cells: Vec<Vec3> = (0..side * side)
+            .map(|i| {
+                let x = (i % side) as f32 - side as f32 / 2.0;
+                let z = (i / side) as f32 - side as f32 / 2.0;
+                8.0 * Vec3::new(x, 0.0, z)
+            })
+            .collect();
+
+        let organelle_positions: Vec<Vec3> = cells
+            .iter()
+            .flat_map(|cell| {
+                (0..8).map(move |i| {
+                    let angle = i as f32 * 2.4;
+                    let height = (i as f32 / 7.0) * 2.0 - 1.0;
+                    *cell + 1.8 * Vec3::new(angle.cos(), height, angle.sin())
+                })
+            })
+            .collect();
+        let organelle = application.instances(&organelle_positions);
+        let organelles = Spheres::new(
+            &application.rendering_descriptor(),
+            &SphereGeometry {
+                position: organelle,
+                radius: 0.5.into(),
+                color: Vec3::new(0.9, 0.4, 0.2).into(),
+            },
+            &SphereMaterial::default(),
+        )
+        .unwrap();
+
+        let membrane = application.instances(&cells);
+        let membranes = Spheres::new(
+            &application.rendering_descriptor(),
+            &SphereGeometry {
+                position: membrane,
+                radius: 3.0.into(),
+                color: Vec3::new(0.3, 0.6, 0.9).into(),
+            },
+            &SphereMaterial {
+                color: Expression::InputColor.lit(),
+                alpha: 0.3.into(),
+            },
+        )
+        .unwrap();
+
+        Simulation {
+            organelles,
+            membranes,
+        }
+    }
+}
+
+impl visula::Simulation for Simulation {
+    fn render(&mut self, data: &mut RenderData) {
+        self.organelles.render(data);
+    }
+
+    fn render_transparent(&mut self, data: &mut TransparentRenderData) {
+        self.membranes.render_transparent(data);
+    }
+
+    fn render_shadow(&mut self, data: &mut ShadowRenderData) {
+        self.organelles.render_shadow(data);
+    }
+}
+
+fn main() {
+    visula::run(Simulation::new);
+}
@@ -0,0 +1,63 @@
+use glam::Vec3;
+use visula::{
+    MeshGeometry, MeshMaterial, RenderData, Renderable, ShadowRenderData, TubePoint, Tubes,
+};
+
+struct Simulation {
+    tubes: Tubes,
+}
+
+impl Simulation {
+    fn new(application: &mut visula::Application) -> Simulation {
+        let mut tubes = Tubes::new(
+            &application.rendering_descriptor(),
+            &MeshGeometry::default(),
+            &MeshMaterial::default(),
+        )
+        .unwrap();
+
+        // A coarse dendrite with branches, with few points to show the interpolation.
+        let trunk: Vec<TubePoint> = (0..8)
+            .map(|i| TubePoint {
+                position: Vec3::new(0.3 * (i as f32).sin(), i as f32, 0.3 * (i as f32).cos()),
+                radius: 0.5 - 0.05 * i as f32,
+                color: Vec3::new(0.9, 0.5, 0.2),
+            })
+            .collect();
+        let branches = (0..4).map(|branch| {
+            let start = trunk[2 * branch + 1];
+            let angle = branch as f32 * 2.1;
+            let direction = Vec3::new(angle.cos(), 0.6, angle.sin());
+            (0..6)
+                .map(|i| {
+                    let t = i as f32;
+                    TubePoint {
+                        position: start.position
+                            + t * direction
+                            + Vec3::new(0.0, 0.3 * (2.0 * t).sin(), 0.0),
+                        radius: (start.radius * 0.6 - 0.03 * t).max(0.05),
+                        color: Vec3::new(0.2, 0.5 + 0.08 * t, 0.9),
+                    }
+                })
+                .collect::<Vec<_>>()
+        });
+        let paths: Vec<Vec<TubePoint>> = std::iter::once(trunk.clone()).chain(branches).collect();
+        tubes.update(&application.device, &paths);
+
+        Simulation { tubes }
+    }
+}
+
+impl visula::Simulation for Simulation {
+    fn render(&mut self, data: &mut RenderData) {
+        self.tubes.render(data);
+    }
+
+    fn render_shadow(&mut self, data: &mut ShadowRenderData) {
+        self.tubes.render_shadow(data);
+    }
+}
+
+fn main() {
+    visula::run(Simulation::new);
+}
@@ -101,6 +101,7 @@ impl Simulation {
             },
             &SphereMaterial {
                 color: Expression::InputColor.lit(),
+                ..Default::default()
             },
         )
         .unwrap();
@@ -109,6 +110,7 @@ impl Simulation {
             &MeshGeometry::default(),
             &MeshMaterial {
                 color: Expression::from(Vec4::splat(1.0)).lit(),
+                ..Default::default()
             },
         )
         .unwrap();
@@ -0,0 +1,82 @@
+use glam::Vec3;
+use visula::{
+    colormap, Colormap, Expression, RenderData, Renderable, ShadowRenderData, SphereGeometry,
+    SphereMaterial, Spheres, TransparentRenderData, Volume, VolumeGeometry, VolumeMaterial,
+};
+
+struct Simulation {
+    volume: Volume,
+    probe: Spheres,
+}
+
+impl Simulation {
+    fn new(application: &mut visula::Application) -> Simulation {
+        let side = 64;
+        let centers = [
+            Vec3::new(0.3, 0.4, 0.5),
+            Vec3::new(0.7, 0.6, 0.4),
+            Vec3::new(0.5, 0.5, 0.8),
+        ];
+        let values: Vec<f32> = (0..side * side * side)
+            .map(|i| {
+                let voxel = Vec3::new(
+                    (i % side) as f32,
+                    ((i / side) % side) as f32,
+                    (i / (side * side)) as f32,
+                ) / side as f32;
+                centers
+                    .iter()
+                    .map(|center| (-voxel.distance_squared(*center) / 0.02).exp())
+                    .sum()
+            })
+            .collect();
+
+        let value = Expression::VolumeValue;
+        let volume = Volume::new(
+            &application.rendering_descriptor(),
+            &VolumeGeometry {
+                position: Vec3::splat(-5.0).into(),
+                size: Vec3::splat(10.0).into(),
+            },
+            &VolumeMaterial {
+                color: colormap(&value, Colormap::Inferno),
+                opacity: 0.2 * value.smoothstep(0.2, 1.0),
+            },
+            [side as u32; 3],
+        )
+        .unwrap();
+        volume.update(&application.queue, &values);
+
+        let probe_position = application.instances(&[Vec3::new(2.0, 1.0, -1.0)]);
+        let probe = Spheres::new(
+            &application.rendering_descriptor(),
+            &SphereGeometry {
+                position: probe_position,
+                radius: 1.0.into(),
+                color: Vec3::new(0.2, 0.7, 0.9).into(),
+            },
+            &SphereMaterial::default(),
+        )
+        .unwrap();
+
+        Simulation { volume, probe }
+    }
+}
+
+impl visula::Simulation for Simulation {
+    fn render(&mut self, data: &mut RenderData) {
+        self.probe.render(data);
+    }
+
+    fn render_transparent(&mut self, data: &mut TransparentRenderData) {
+        self.volume.render_transparent(data);
+    }
+
+    fn render_shadow(&mut self, data: &mut ShadowRenderData) {
+        self.probe.render_shadow(data);
+    }
+}
+
+fn main() {
+    visula::run(Simulation::new);
+}
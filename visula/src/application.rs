@@ -1,8 +1,11 @@
 use crate::camera::Camera;
+use crate::environment::Environment;
 use crate::light::DirectionalLight;
+use crate::picking::{PickResult, Picker};
+use crate::post_process::config::SkyMode;
 use crate::post_process::PostProcessor;
 use crate::rendering_descriptor::RenderingDescriptor;
-use crate::simulation::ShadowRenderData;
+use crate::simulation::{PickingRenderData, ShadowRenderData, TransparentRenderData};
 use crate::{camera::controller::CameraController, simulation::RenderData};
 use crate::{CameraControllerResponse, Simulation};
 use chrono::{DateTime, Utc};
@@ -15,11 +18,13 @@ use winit::window::WindowId;
 use std::fmt::Debug;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use web_time::Instant;
 use wgpu::{
     BackendOptions, Color, CommandEncoder, CurrentSurfaceTexture, Device, Dx12BackendOptions,
     GlBackendOptions, InstanceDescriptor, SurfaceTexture, TextureFormat, TextureView,
     TextureViewDescriptor,
 };
+use winit::dpi::PhysicalPosition;
 use winit::{event::WindowEvent, window::Window};
 
 #[derive(Debug)]
@@ -41,6 +46,55 @@ pub struct Application {
     pub sample_count: u32,
     pending_screenshot: Option<PathBuf>,
     surface_supports_copy_src: bool,
+    recording: Option<Recording>,
+    last_update: Instant,
+    time_step: f32,
+    picker: Option<Picker>,
+}
+
+/// Settings for recording a numbered PNG sequence with [`Application::start_recording`].
+#[derive(Clone, Debug)]
+pub struct RecordingConfig {
+    /// Directory that the frames are written to. Created if it does not exist.
+    pub directory: PathBuf,
+    /// Capture every `frame_interval`-th rendered frame.
+    pub frame_interval: u32,
+    /// Simulated time in seconds that passes per rendered frame while recording, as reported
+    /// by [`Application::time_step`].
+    pub time_step: f32,
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        RecordingConfig {
+            directory: PathBuf::from("recording"),
+            frame_interval: 1,
+            time_step: 1.0 / 60.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Recording {
+    config: RecordingConfig,
+    frames_rendered: u32,
+    frames_written: u32,
+}
+
+impl Recording {
+    fn next_frame_path(&mut self) -> Option<PathBuf> {
+        let capture = self.frames_rendered % self.config.frame_interval.max(1) == 0;
+        self.frames_rendered = self.frames_rendered.saturating_add(1);
+        if !capture {
+            return None;
+        }
+        let path = self
+            .config
+            .directory
+            .join(format!("frame_{:05}.png", self.frames_written));
+        self.frames_written += 1;
+        Some(path)
+    }
 }
 
 fn create_egui_context() -> egui::Context {
@@ -106,32 +160,7 @@ impl Application {
     pub async fn new(window: Arc<Window>) -> Result<Application, crate::error::Error> {
         let size = window.inner_size();
 
-        #[cfg(target_arch = "wasm32")]
-        let backends = wgpu::Backends::BROWSER_WEBGPU | wgpu::Backends::GL;
-        #[cfg(not(target_arch = "wasm32"))]
-        let backends = wgpu::Backends::from_env().unwrap_or_else(wgpu::Backends::all);
-
-        let dx12_shader_compiler = wgpu::Dx12Compiler::from_env().unwrap_or_default();
-        let gles_minor_version = wgpu::Gles3MinorVersion::from_env().unwrap_or_default();
-
-        let instance = wgpu::Instance::new(InstanceDescriptor {
-            backends,
-            backend_options: BackendOptions {
-                gl: GlBackendOptions {
-                    gles_minor_version,
-                    fence_behavior: wgpu::GlFenceBehavior::default(),
-                    debug_fns: wgpu::GlDebugFns::default(),
-                },
-                dx12: Dx12BackendOptions {
-                    shader_compiler: dx12_shader_compiler,
-                    ..Default::default()
-                },
-                noop: wgpu::NoopBackendOptions::default(),
-            },
-            flags: wgpu::InstanceFlags::from_build_config().with_env(),
-            memory_budget_thresholds: wgpu::MemoryBudgetThresholds::default(),
-            display: None,
-        });
+        let instance = create_instance();
         let surface = instance.create_surface(window.clone())?;
         let adapter = instance
             .request_adapter(&wgpu::RequestAdapterOptions {
@@ -142,16 +171,7 @@ impl Application {
             .await
             .map_err(|_| crate::error::Error::NoAdapter)?;
 
-        let (device, queue) = adapter
-            .request_device(&wgpu::DeviceDescriptor {
-                label: None,
-                required_features: wgpu::Features::empty(),
-                required_limits: adapter.limits(),
-                memory_hints: wgpu::MemoryHints::Performance,
-                experimental_features: Default::default(),
-                trace: Default::default(),
-            })
-            .await?;
+        let (device, queue) = request_device(&adapter).await?;
 
         let surface_caps = surface.get_capabilities(&adapter);
         let surface_supports_copy_src = surface_caps.usages.contains(wgpu::TextureUsages::COPY_SRC);
@@ -172,28 +192,11 @@ impl Application {
         let sample_count = 1;
         #[cfg(not(target_arch = "wasm32"))]
         let sample_count = 4;
-        let depth_texture_in = device.create_texture(&wgpu::TextureDescriptor {
-            size: wgpu::Extent3d {
-                width: config.width,
-                height: config.height,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Depth32Float,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-            label: None,
-            view_formats: &[],
-        });
-        let depth_texture = depth_texture_in.create_view(&wgpu::TextureViewDescriptor::default());
+        let depth_texture =
+            create_depth_texture(&device, config.width, config.height, sample_count);
 
-        let multisampled_framebuffer = Self::create_multisampled_framebuffer(
-            &device,
-            config.width,
-            config.height,
-            sample_count,
-        );
+        let multisampled_framebuffer =
+            create_multisampled_framebuffer(&device, config.width, config.height, sample_count);
 
         let start_time = Utc::now();
 
@@ -231,6 +234,10 @@ impl Application {
             sample_count,
             pending_screenshot: None,
             surface_supports_copy_src,
+            recording: None,
+            last_update: Instant::now(),
+            time_step: 0.0,
+            picker: None,
         })
     }
 
@@ -253,31 +260,54 @@ impl Application {
         self.surface_supports_copy_src
     }
 
-    fn create_multisampled_framebuffer(
-        device: &wgpu::Device,
-        width: u32,
-        height: u32,
-        sample_count: u32,
-    ) -> wgpu::TextureView {
-        let multisampled_texture_extent = wgpu::Extent3d {
-            width,
-            height,
-            depth_or_array_layers: 1,
-        };
-        let multisampled_frame_descriptor = &wgpu::TextureDescriptor {
-            size: multisampled_texture_extent,
-            mip_level_count: 1,
-            sample_count,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba16Float,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            label: None,
-            view_formats: &[],
-        };
+    /// Start writing every `config.frame_interval`-th rendered frame to
+    /// `config.directory/frame_NNNNN.png`, without the GUI overlay.
+    ///
+    /// While recording, [`Application::time_step`] reports the fixed `config.time_step` instead
+    /// of the wall-clock time between frames, so that simulations using it produce the same
+    /// sequence regardless of how long each frame takes to render and save.
+    pub fn start_recording(&mut self, config: RecordingConfig) -> Result<(), crate::error::Error> {
+        if !self.surface_supports_copy_src {
+            return Err(crate::error::Error::ScreenshotUnsupported);
+        }
+        std::fs::create_dir_all(&config.directory)?;
+        log::info!("recording frames to {}", config.directory.display());
+        self.recording = Some(Recording {
+            config,
+            frames_rendered: 0,
+            frames_written: 0,
+        });
+        Ok(())
+    }
 
-        device
-            .create_texture(multisampled_frame_descriptor)
-            .create_view(&wgpu::TextureViewDescriptor::default())
+    /// Stop a recording started with [`Application::start_recording`].
+    pub fn stop_recording(&mut self) {
+        if let Some(recording) = self.recording.take() {
+            log::info!(
+                "recorded {} frames to {}",
+                recording.frames_written,
+                recording.config.directory.display()
+            );
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Number of frames written by the current recording, or `None` if not recording.
+    pub fn recorded_frames(&self) -> Option<u32> {
+        self.recording
+            .as_ref()
+            .map(|recording| recording.frames_written)
+    }
+
+    /// Time in seconds to advance simulations by this frame.
+    ///
+    /// This is the wall-clock time since the previous [`Application::update`], or the fixed
+    /// [`RecordingConfig::time_step`] while recording.
+    pub fn time_step(&self) -> f32 {
+        self.time_step
     }
 
     pub fn window_event(&mut self, window_id: WindowId, event: &WindowEvent) -> bool {
@@ -328,26 +358,11 @@ impl Application {
         match event {
             WindowEvent::CloseRequested => println!("{}", crude_profiler::report()),
             WindowEvent::Resized(size) => {
-                let depth_texture = self.device.create_texture(&wgpu::TextureDescriptor {
-                    size: wgpu::Extent3d {
-                        width: size.width,
-                        height: size.height,
-                        depth_or_array_layers: 1,
-                    },
-                    mip_level_count: 1,
-                    sample_count: self.sample_count,
-                    dimension: wgpu::TextureDimension::D2,
-                    format: wgpu::TextureFormat::Depth32Float,
-                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
-                        | wgpu::TextureUsages::TEXTURE_BINDING,
-                    label: None,
-                    view_formats: &[],
-                });
                 self.depth_texture =
-                    depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
+                    create_depth_texture(&self.device, size.width, size.height, self.sample_count);
                 self.config.width = size.width;
                 self.config.height = size.height;
-                self.multisampled_framebuffer = Self::create_multisampled_framebuffer(
+                self.multisampled_framebuffer = create_multisampled_framebuffer(
                     &self.device,
                     size.width,
                     size.height,
@@ -377,12 +392,63 @@ impl Application {
     }
 
     pub fn update(&mut self) {
+        let now = Instant::now();
+        self.time_step = match self.recording {
+            Some(ref recording) => recording.config.time_step,
+            None => (now - self.last_update).as_secs_f32(),
+        };
+        self.last_update = now;
         self.camera_controller.update();
         let camera_uniforms = self
             .camera_controller
             .uniforms(self.config.width as f32, self.config.height as f32);
         self.camera.update(&camera_uniforms, &self.queue);
-        self.light.update(&self.queue);
+        self.light.update(&self.queue, &camera_uniforms);
+    }
+
+    /// Find the instance drawn at `screen_position`, given in physical pixels.
+    ///
+    /// Renders the picking passes of `simulation` for that pixel, using the camera from the
+    /// last [`Application::update`], and blocks until the result has been read back. Returns
+    /// `None` if no picking-aware primitive covers the pixel.
+    pub fn pick(
+        &mut self,
+        simulation: &mut impl Simulation,
+        screen_position: PhysicalPosition<f64>,
+    ) -> Option<PickResult> {
+        let width = self.config.width;
+        let height = self.config.height;
+        if screen_position.x < 0.0 || screen_position.y < 0.0 {
+            return None;
+        }
+        let pixel = [screen_position.x as u32, screen_position.y as u32];
+        if pixel[0] >= width || pixel[1] >= height {
+            return None;
+        }
+        if self
+            .picker
+            .as_ref()
+            .is_none_or(|picker| picker.size() != (width, height))
+        {
+            self.picker = Some(Picker::new(&self.device, width, height));
+        }
+        let picker = self.picker.as_ref()?;
+
+        let mut encoder = self.encoder();
+        picker.clear(&mut encoder);
+        simulation.render_picking(&mut PickingRenderData {
+            encoder: &mut encoder,
+            id_texture: &picker.id_view,
+            depth_texture: &picker.depth_view,
+            camera: &self.camera,
+            light: &self.light,
+            pixel,
+        });
+        picker.copy_pixel(&mut encoder, pixel);
+        self.queue.submit(Some(encoder.finish()));
+
+        let camera_uniforms = self.camera_controller.uniforms(width as f32, height as f32);
+        picker.read(&self.device, pixel, &camera_uniforms)
     }
 
     pub fn next_frame(&self) -> Result<SurfaceTexture, crate::error::Error> {
@@ -450,122 +516,32 @@ impl Application {
             ..wgpu::TextureViewDescriptor::default()
         });
 
-        {
-            encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("shadow clear"),
-                color_attachments: &[],
-                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &self.light.shadow_texture_view,
-                    depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(1.0),
-                        store: wgpu::StoreOp::Store,
-                    }),
-                    stencil_ops: None,
-                }),
-                occlusion_query_set: None,
-                timestamp_writes: None,
-                multiview_mask: None,
-            });
-        }
-        simulation.render_shadow(&mut ShadowRenderData {
-            encoder: &mut encoder,
-            shadow_texture: &self.light.shadow_texture_view,
-            light: &self.light,
-        });
-
-        let msaa = self.sample_count > 1;
-        {
-            let hdr_view = &self.post_processor.hdr_view;
-            let normal_msaa_view = &self.post_processor.normal_msaa_view;
-            let normal_resolve_view = &self.post_processor.normal_resolve_view;
-            encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("clear"),
-                color_attachments: &[
-                    Some(wgpu::RenderPassColorAttachment {
-                        view: if msaa {
-                            &self.multisampled_framebuffer
-                        } else {
-                            hdr_view
-                        },
-                        resolve_target: if msaa { Some(hdr_view) } else { None },
-                        depth_slice: None,
-                        ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(simulation.clear_color()),
-                            store: wgpu::StoreOp::Store,
-                        },
-                    }),
-                    Some(wgpu::RenderPassColorAttachment {
-                        view: if msaa {
-                            normal_msaa_view
-                        } else {
-                            normal_resolve_view
-                        },
-                        resolve_target: if msaa {
-                            Some(normal_resolve_view)
-                        } else {
-                            None
-                        },
-                        depth_slice: None,
-                        ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(Color::TRANSPARENT),
-                            store: wgpu::StoreOp::Store,
-                        },
-                    }),
-                ],
-                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &self.depth_texture,
-                    depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(1.0),
-                        store: wgpu::StoreOp::Store,
-                    }),
-                    stencil_ops: None,
-                }),
-                occlusion_query_set: None,
-                timestamp_writes: None,
-                multiview_mask: None,
-            });
-        }
-
-        self.post_processor.render_sky(
+        render_scene(
             &mut encoder,
             &self.queue,
-            if msaa {
-                &self.multisampled_framebuffer
-            } else {
-                &self.post_processor.hdr_view
+            simulation,
+            SceneTargets {
+                output_view: &view,
+                multisampled_framebuffer: &self.multisampled_framebuffer,
+                depth_texture: &self.depth_texture,
+                sample_count: self.sample_count,
             },
-            &self.depth_texture,
             &self.camera,
+            &self.light,
+            &self.post_processor,
         );
 
-        simulation.render(&mut RenderData {
-            view: &self.post_processor.hdr_view,
-            multisampled_framebuffer: if msaa {
-                &self.multisampled_framebuffer
-            } else {
-                &self.post_processor.hdr_view
-            },
-            depth_texture: &self.depth_texture,
-            normal_msaa: if msaa {
-                &self.post_processor.normal_msaa_view
-            } else {
-                &self.post_processor.normal_resolve_view
-            },
-            normal_resolve: &self.post_processor.normal_resolve_view,
-            encoder: &mut encoder,
-            camera: &self.camera,
-            light: &self.light,
-        });
-
-        self.post_processor.render_ssao(&mut encoder, &self.queue);
-
-        self.post_processor
-            .render_outline(&mut encoder, &self.queue);
-
-        self.post_processor.render_bloom(&mut encoder, &self.queue);
-
-        self.post_processor
-            .render_tonemap(&mut encoder, &self.queue, &view);
+        // Recorded frames are copied before the GUI is drawn on top.
+        let recording_frame_path = self
+            .recording
+            .as_mut()
+            .and_then(|recording| recording.next_frame_path());
+        let recording_in_flight = match recording_frame_path {
+            Some(path) if self.surface_supports_copy_src => {
+                Some(self.encode_screenshot_copy(&mut encoder, &frame.texture, path))
+            }
+            _ => None,
+        };
 
         let raw_input = self.egui_renderer.state.take_egui_input(&self.window);
         #[allow(deprecated)]
@@ -629,6 +605,9 @@ impl Application {
         self.queue.submit(Some(encoder.finish()));
         frame.present();
 
+        if let Some(pending) = recording_in_flight {
+            pending.write(&self.device);
+        }
         if let Some(pending) = screenshot_in_flight {
             pending.write(&self.device);
         }
@@ -645,6 +624,15 @@ impl Application {
         target: &wgpu::Texture,
     ) -> Option<PendingScreenshot> {
         let path = self.pending_screenshot.take()?;
+        Some(self.encode_screenshot_copy(encoder, target, path))
+    }
+
+    fn encode_screenshot_copy(
+        &self,
+        encoder: &mut CommandEncoder,
+        target: &wgpu::Texture,
+        path: PathBuf,
+    ) -> PendingScreenshot {
         let width = self.config.width;
         let height = self.config.height;
         let bytes_per_pixel = 4u32;
@@ -679,14 +667,22 @@ impl Application {
                 depth_or_array_layers: 1,
             },
         );
-        Some(PendingScreenshot {
+        PendingScreenshot {
             buffer,
             path,
             padded_bytes_per_row,
             width,
             height,
             format: self.config.format,
-        })
+        }
+    }
+
+    /// Light the scene with `environment` and show it as the sky.
+    pub fn set_environment(&mut self, environment: Environment) {
+        self.post_processor
+            .set_environment(&self.device, &environment);
+        self.post_processor.config.sky.mode = SkyMode::Environment;
+        self.light.set_environment(&self.device, environment);
     }
 
     pub fn rendering_descriptor(&self) -> RenderingDescriptor<'_> {
@@ -705,6 +701,265 @@ impl Application {
     {
         visula_core::InstanceBuffer::new_with_init(&self.device, data).instance()
     }
+
+    /// Like [`Self::instances`], but the data is read from a storage buffer by
+    /// `instance_index`, see [`visula_core::InstanceBindingMode::Storage`].
+    pub fn storage_instances<T>(&self, data: &[T]) -> T::Type
+    where
+        T: visula_core::Instance + bytemuck::Pod,
+    {
+        visula_core::InstanceBuffer::new_with_init(&self.device, data)
+            .with_binding_mode(visula_core::InstanceBindingMode::Storage)
+            .instance()
+    }
+}
+
+pub(crate) fn create_instance() -> wgpu::Instance {
+    #[cfg(target_arch = "wasm32")]
+    let backends = wgpu::Backends::BROWSER_WEBGPU | wgpu::Backends::GL;
+    #[cfg(not(target_arch = "wasm32"))]
+    let backends = wgpu::Backends::from_env().unwrap_or_else(wgpu::Backends::all);
+
+    let dx12_shader_compiler = wgpu::Dx12Compiler::from_env().unwrap_or_default();
+    let gles_minor_version = wgpu::Gles3MinorVersion::from_env().unwrap_or_default();
+
+    wgpu::Instance::new(InstanceDescriptor {
+        backends,
+        backend_options: BackendOptions {
+            gl: GlBackendOptions {
+                gles_minor_version,
+                fence_behavior: wgpu::GlFenceBehavior::default(),
+                debug_fns: wgpu::GlDebugFns::default(),
+            },
+            dx12: Dx12BackendOptions {
+                shader_compiler: dx12_shader_compiler,
+                ..Default::default()
+            },
+            noop: wgpu::NoopBackendOptions::default(),
+        },
+        flags: wgpu::InstanceFlags::from_build_config().with_env(),
+        memory_budget_thresholds: wgpu::MemoryBudgetThresholds::default(),
+        display: None,
+    })
+}
+
+pub(crate) async fn request_device(
+    adapter: &wgpu::Adapter,
+) -> Result<(wgpu::Device, wgpu::Queue), crate::error::Error> {
+    Ok(adapter
+        .request_device(&wgpu::DeviceDescriptor {
+            label: None,
+            required_features: wgpu::Features::empty(),
+            required_limits: adapter.limits(),
+            memory_hints: wgpu::MemoryHints::Performance,
+            experimental_features: Default::default(),
+            trace: Default::default(),
+        })
+        .await?)
+}
+
+pub(crate) fn create_depth_texture(
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+    sample_count: u32,
+) -> wgpu::TextureView {
+    device
+        .create_texture(&wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Depth32Float,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            label: None,
+            view_formats: &[],
+        })
+        .create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+pub(crate) fn create_multisampled_framebuffer(
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+    sample_count: u32,
+) -> wgpu::TextureView {
+    let multisampled_texture_extent = wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    };
+    let multisampled_frame_descriptor = &wgpu::TextureDescriptor {
+        size: multisampled_texture_extent,
+        mip_level_count: 1,
+        sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba16Float,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        label: None,
+        view_formats: &[],
+    };
+
+    device
+        .create_texture(multisampled_frame_descriptor)
+        .create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+/// The per-frame render targets that [`render_scene`] draws into.
+pub(crate) struct SceneTargets<'a> {
+    pub output_view: &'a TextureView,
+    pub multisampled_framebuffer: &'a TextureView,
+    pub depth_texture: &'a TextureView,
+    pub sample_count: u32,
+}
+
+/// Encodes the shadow pass, the scene and all post-processing passes, ending with the
+/// tonemapped result in `targets.output_view`. Shared by the windowed and headless
+/// applications so that both produce identical frames.
+pub(crate) fn render_scene(
+    encoder: &mut CommandEncoder,
+    queue: &wgpu::Queue,
+    simulation: &mut impl Simulation,
+    targets: SceneTargets,
+    camera: &Camera,
+    light: &DirectionalLight,
+    post_processor: &PostProcessor,
+) {
+    for (cascade_view, cascade_bind_group) in
+        light.cascade_views.iter().zip(&light.cascade_bind_groups)
+    {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("shadow clear"),
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: cascade_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+            multiview_mask: None,
+        });
+        simulation.render_shadow(&mut ShadowRenderData {
+            encoder,
+            shadow_texture: cascade_view,
+            shadow_bind_group: cascade_bind_group,
+            light,
+        });
+    }
+
+    let msaa = targets.sample_count > 1;
+    {
+        let hdr_view = &post_processor.hdr_view;
+        let normal_msaa_view = &post_processor.normal_msaa_view;
+        let normal_resolve_view = &post_processor.normal_resolve_view;
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("clear"),
+            color_attachments: &[
+                Some(wgpu::RenderPassColorAttachment {
+                    view: if msaa {
+                        targets.multisampled_framebuffer
+                    } else {
+                        hdr_view
+                    },
+                    resolve_target: if msaa { Some(hdr_view) } else { None },
+                    depth_slice: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(simulation.clear_color()),
+                        store: wgpu::StoreOp::Store,
+                    },
+                }),
+                Some(wgpu::RenderPassColorAttachment {
+                    view: if msaa {
+                        normal_msaa_view
+                    } else {
+                        normal_resolve_view
+                    },
+                    resolve_target: if msaa {
+                        Some(normal_resolve_view)
+                    } else {
+                        None
+                    },
+                    depth_slice: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                }),
+            ],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: targets.depth_texture,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+            multiview_mask: None,
+        });
+    }
+
+    post_processor.render_sky(
+        encoder,
+        queue,
+        if msaa {
+            targets.multisampled_framebuffer
+        } else {
+            &post_processor.hdr_view
+        },
+        targets.depth_texture,
+        camera,
+    );
+
+    simulation.render(&mut RenderData {
+        view: &post_processor.hdr_view,
+        multisampled_framebuffer: if msaa {
+            targets.multisampled_framebuffer
+        } else {
+            &post_processor.hdr_view
+        },
+        depth_texture: targets.depth_texture,
+        normal_msaa: if msaa {
+            &post_processor.normal_msaa_view
+        } else {
+            &post_processor.normal_resolve_view
+        },
+        normal_resolve: &post_processor.normal_resolve_view,
+        encoder,
+        camera,
+        light,
+    });
+
+    let transparency = &post_processor.transparency;
+    post_processor.clear_transparency(encoder);
+    simulation.render_transparent(&mut TransparentRenderData {
+        accumulation: &transparency.accumulation_msaa_view,
+        accumulation_resolve: msaa.then_some(&transparency.accumulation_view),
+        revealage: &transparency.revealage_msaa_view,
+        revealage_resolve: msaa.then_some(&transparency.revealage_view),
+        depth_texture: targets.depth_texture,
+        encoder,
+        camera,
+        light,
+    });
+    post_processor.render_transparency(encoder);
+
+    post_processor.render_ssao(encoder, queue);
+
+    post_processor.render_outline(encoder, queue);
+
+    post_processor.render_bloom(encoder, queue);
+
+    post_processor.render_tonemap(encoder, queue, targets.output_view);
 }
 
 /// Handle to a screenshot whose texture-to-buffer copy has been encoded but not yet finalized.
@@ -759,6 +1014,18 @@ fn save_png(
     format: TextureFormat,
     path: &Path,
 ) -> Result<(), image::ImageError> {
+    let rgba = unpad_rgba(data, padded_bytes_per_row, width, height, format);
+    image::save_buffer(path, &rgba, width, height, image::ColorType::Rgba8)
+}
+
+/// Strip the row padding required by texture-to-buffer copies and convert BGRA data to RGBA.
+pub(crate) fn unpad_rgba(
+    data: &[u8],
+    padded_bytes_per_row: u32,
+    width: u32,
+    height: u32,
+    format: TextureFormat,
+) -> Vec<u8> {
     let swap_rb = matches!(
         format,
         TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb
@@ -778,5 +1045,5 @@ fn save_png(
             }
         }
     }
-    image::save_buffer(path, &rgba, width, height, image::ColorType::Rgba8)
+    rgba
 }
@@ -53,7 +53,7 @@ pub struct CameraController {
     pub rotational_speed: f32,
     pub roll_speed: f32,
     state: State,
-    window_id: WindowId,
+    window_id: Option<WindowId>,
     previous_time: Instant,
     pub current_transform: CameraTransform,
     pub target_transform: CameraTransform,
@@ -79,7 +79,28 @@ fn lerpf(current: f32, target: f32, rate: f32) -> f32 {
 }
 
 impl CameraController {
+    /// The vertical field of view of [`CameraController::projection_matrix`] in radians.
+    pub const FIELD_OF_VIEW: f32 = 40.0 / 180.0 * PI;
+
     pub fn new(window: &Window) -> CameraController {
+        Self::with_window_properties(
+            Some(window.id()),
+            window.scale_factor() as f32,
+            window.inner_size(),
+        )
+    }
+
+    /// Creates a controller that is not attached to any window, for use with offscreen
+    /// rendering. Window events are ignored, but the transforms can still be set directly.
+    pub fn new_headless(width: u32, height: u32) -> CameraController {
+        Self::with_window_properties(None, 1.0, PhysicalSize::new(width, height))
+    }
+
+    fn with_window_properties(
+        window_id: Option<WindowId>,
+        scale_factor: f32,
+        window_size: PhysicalSize<u32>,
+    ) -> CameraController {
         let up = Vec3::Y;
         let forward = Vec3::Z;
         let right = Vec3::cross(forward, up).normalize();
@@ -89,8 +110,6 @@ impl CameraController {
         let axis = Vec3::cross(offset, forward).normalize();
         let rotation = Quat::from_axis_angle(axis, 1.0);
         let new_forward = (rotation * forward).normalize();
-        let scale_factor = window.scale_factor() as f32;
-        let window_id = window.id();
         let transform = CameraTransform {
             forward: new_forward,
             true_up: up,
@@ -114,7 +133,7 @@ impl CameraController {
             previous_time: Instant::now(),
             smoothing: 0.8,
             last_screen_position: None,
-            window_size: window.inner_size(),
+            window_size,
             first_intersection: None,
             drag_plane: DragPlane::Camera,
         }
@@ -225,7 +244,7 @@ impl CameraController {
             return response;
         }
 
-        if window_id != self.window_id {
+        if Some(window_id) != self.window_id {
             return response;
         }
         match event {
@@ -387,7 +406,7 @@ impl CameraController {
         let dist = self.current_transform.distance.max(1.0);
         let near = (dist * 0.001).max(0.001);
         let far = (dist * 1000.0).max(100.0);
-        Mat4::perspective_rh(40f32 / 180.0 * PI, aspect_ratio, near, far)
+        Mat4::perspective_rh(Self::FIELD_OF_VIEW, aspect_ratio, near, far)
     }
 
     pub fn active(&self) -> bool {
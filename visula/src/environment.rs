@@ -0,0 +1,432 @@
+use std::f32::consts::PI;
+use std::path::Path;
+
+use glam::{Vec2, Vec3};
+
+use crate::error::Error;
+
+/// Width of the largest level of the specular mip chain. The height is half of this.
+const SPECULAR_SIZE: usize = 256;
+/// Number of levels in the specular mip chain, from roughness 0 to 1.
+const SPECULAR_MIP_COUNT: u32 = 6;
+const IRRADIANCE_SIZE: usize = 32;
+/// The source is downsampled to at most this width before prefiltering.
+const SOURCE_SIZE: usize = 512;
+const SPECULAR_SAMPLE_COUNT: u32 = 64;
+
+/// An equirectangular HDR environment, prefiltered for image-based lighting.
+///
+/// Holds a diffuse irradiance map and a specular mip chain where each level is convolved
+/// with a GGX lobe of increasing roughness. Both are stored in the equirectangular layout
+/// of the source, so they can be sampled without cube maps, next to the unfiltered source
+/// itself for drawing the sky. Pass it to
+/// [`crate::Application::set_environment`] to light the scene and draw it as the sky.
+#[derive(Clone, Debug)]
+pub struct Environment {
+    pub irradiance_view: wgpu::TextureView,
+    pub specular_view: wgpu::TextureView,
+    /// The source at full resolution, or as large as the device supports, for drawing the
+    /// sky without the blur of the prefiltered maps.
+    pub sky_view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+    /// Number of mip levels in the specular texture, or zero for an empty environment.
+    pub specular_mip_count: u32,
+}
+
+impl Environment {
+    /// An environment without any light, used until one is loaded.
+    pub fn empty(device: &wgpu::Device) -> Environment {
+        let irradiance = create_texture(device, "empty environment irradiance", 1, 1, 1);
+        let specular = create_texture(device, "empty environment specular", 1, 1, 1);
+        let sky = create_texture(device, "empty environment sky", 1, 1, 1);
+        Environment {
+            irradiance_view: irradiance.create_view(&wgpu::TextureViewDescriptor::default()),
+            specular_view: specular.create_view(&wgpu::TextureViewDescriptor::default()),
+            sky_view: sky.create_view(&wgpu::TextureViewDescriptor::default()),
+            sampler: create_sampler(device),
+            specular_mip_count: 0,
+        }
+    }
+
+    /// Load and prefilter a Radiance `.hdr` file with an equirectangular projection.
+    pub fn from_hdr_file(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: impl AsRef<Path>,
+    ) -> Result<Environment, Error> {
+        let bytes = std::fs::read(path)?;
+        Self::from_hdr_bytes(device, queue, &bytes)
+    }
+
+    /// Like [`Self::from_hdr_file`], but reads the `.hdr` file from memory.
+    pub fn from_hdr_bytes(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bytes: &[u8],
+    ) -> Result<Environment, Error> {
+        let decoder = image::codecs::hdr::HdrDecoder::new(bytes)?;
+        let metadata = decoder.metadata();
+        let pixels: Vec<Vec3> = decoder
+            .read_image_hdr()?
+            .into_iter()
+            .map(|pixel| Vec3::from(pixel.0))
+            .collect();
+        Ok(Self::from_equirectangular(
+            device,
+            queue,
+            metadata.width as usize,
+            metadata.height as usize,
+            &pixels,
+        ))
+    }
+
+    /// Prefilter linear RGB radiance given in row-major equirectangular layout, with the
+    /// top row looking along +Y.
+    pub fn from_equirectangular(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        width: usize,
+        height: usize,
+        pixels: &[Vec3],
+    ) -> Environment {
+        assert_eq!(pixels.len(), width * height, "pixel count must match size");
+        let mut source = EquirectImage {
+            width,
+            height,
+            pixels: pixels.to_vec(),
+        };
+        let max_size = device.limits().max_texture_dimension_2d as usize;
+        while source.width > max_size || source.height > max_size {
+            source = source.downsample();
+        }
+        let sky_texture = create_texture(
+            device,
+            "environment sky",
+            source.width as u32,
+            source.height as u32,
+            1,
+        );
+        write_level(queue, &sky_texture, 0, &source);
+
+        while source.width > SOURCE_SIZE {
+            source = source.downsample();
+        }
+        let pyramid = Pyramid::new(source);
+
+        let irradiance = pyramid.irradiance(IRRADIANCE_SIZE, IRRADIANCE_SIZE / 2);
+        let irradiance_texture = create_texture(
+            device,
+            "environment irradiance",
+            irradiance.width as u32,
+            irradiance.height as u32,
+            1,
+        );
+        write_level(queue, &irradiance_texture, 0, &irradiance);
+
+        let specular_texture = create_texture(
+            device,
+            "environment specular",
+            SPECULAR_SIZE as u32,
+            SPECULAR_SIZE as u32 / 2,
+            SPECULAR_MIP_COUNT,
+        );
+        for level in 0..SPECULAR_MIP_COUNT {
+            let width = SPECULAR_SIZE >> level;
+            let roughness = level as f32 / (SPECULAR_MIP_COUNT - 1) as f32;
+            let prefiltered = pyramid.prefilter_specular(width, width / 2, roughness);
+            write_level(queue, &specular_texture, level, &prefiltered);
+        }
+
+        Environment {
+            irradiance_view: irradiance_texture
+                .create_view(&wgpu::TextureViewDescriptor::default()),
+            specular_view: specular_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            sky_view: sky_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            sampler: create_sampler(device),
+            specular_mip_count: SPECULAR_MIP_COUNT,
+        }
+    }
+}
+
+fn create_texture(
+    device: &wgpu::Device,
+    label: &str,
+    width: u32,
+    height: u32,
+    mip_level_count: u32,
+) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba16Float,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    })
+}
+
+fn create_sampler(device: &wgpu::Device) -> wgpu::Sampler {
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("environment sampler"),
+        address_mode_u: wgpu::AddressMode::Repeat,
+        address_mode_v: wgpu::AddressMode::ClampToEdge,
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        mipmap_filter: wgpu::MipmapFilterMode::Linear,
+        ..Default::default()
+    })
+}
+
+fn write_level(queue: &wgpu::Queue, texture: &wgpu::Texture, level: u32, image: &EquirectImage) {
+    let data: Vec<u16> = image
+        .pixels
+        .iter()
+        .flat_map(|pixel| [pixel.x, pixel.y, pixel.z, 1.0])
+        .map(|value| half::f16::from_f32(value).to_bits())
+        .collect();
+    queue.write_texture(
+        wgpu::TexelCopyTextureInfo {
+            texture,
+            mip_level: level,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        bytemuck::cast_slice(&data),
+        wgpu::TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(image.width as u32 * 8),
+            rows_per_image: Some(image.height as u32),
+        },
+        wgpu::Extent3d {
+            width: image.width as u32,
+            height: image.height as u32,
+            depth_or_array_layers: 1,
+        },
+    );
+}
+
+/// Maps a direction to equirectangular texture coordinates. Must match
+/// `visula_equirect_uv` in `lighting.wgsl`.
+fn direction_to_uv(direction: Vec3) -> Vec2 {
+    Vec2::new(
+        direction.z.atan2(direction.x) / (2.0 * PI) + 0.5,
+        direction.y.clamp(-1.0, 1.0).acos() / PI,
+    )
+}
+
+fn uv_to_direction(uv: Vec2) -> Vec3 {
+    let phi = (uv.x - 0.5) * 2.0 * PI;
+    let theta = uv.y * PI;
+    Vec3::new(
+        theta.sin() * phi.cos(),
+        theta.cos(),
+        theta.sin() * phi.sin(),
+    )
+}
+
+struct EquirectImage {
+    width: usize,
+    height: usize,
+    pixels: Vec<Vec3>,
+}
+
+impl EquirectImage {
+    fn downsample(&self) -> EquirectImage {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = Vec3::ZERO;
+                for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    sum += self.texel(2 * x + dx, 2 * y + dy);
+                }
+                pixels.push(sum / 4.0);
+            }
+        }
+        EquirectImage {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Wraps around horizontally and clamps vertically.
+    fn texel(&self, x: usize, y: usize) -> Vec3 {
+        self.pixels[y.min(self.height - 1) * self.width + x % self.width]
+    }
+
+    fn sample(&self, uv: Vec2) -> Vec3 {
+        let x = uv.x * self.width as f32 - 0.5;
+        let y = (uv.y * self.height as f32 - 0.5).max(0.0);
+        let x0 = x.floor();
+        let y0 = y.floor();
+        let fx = x - x0;
+        let fy = y - y0;
+        let x0 = x0.rem_euclid(self.width as f32) as usize;
+        let y0 = y0 as usize;
+        let top = self.texel(x0, y0).lerp(self.texel(x0 + 1, y0), fx);
+        let bottom = self.texel(x0, y0 + 1).lerp(self.texel(x0 + 1, y0 + 1), fx);
+        top.lerp(bottom, fy)
+    }
+
+    fn texel_direction(&self, x: usize, y: usize) -> Vec3 {
+        uv_to_direction(Vec2::new(
+            (x as f32 + 0.5) / self.width as f32,
+            (y as f32 + 0.5) / self.height as f32,
+        ))
+    }
+}
+
+/// Box-filtered levels of the source, used to sample it with a footprint that matches the
+/// lobe being integrated.
+struct Pyramid {
+    levels: Vec<EquirectImage>,
+}
+
+impl Pyramid {
+    fn new(source: EquirectImage) -> Pyramid {
+        let mut levels = vec![source];
+        while levels.last().unwrap().width > 1 {
+            let next = levels.last().unwrap().downsample();
+            levels.push(next);
+        }
+        Pyramid { levels }
+    }
+
+    fn sample(&self, direction: Vec3, lod: f32) -> Vec3 {
+        let uv = direction_to_uv(direction);
+        let lod = lod.clamp(0.0, (self.levels.len() - 1) as f32);
+        let lower = lod.floor() as usize;
+        let upper = (lower + 1).min(self.levels.len() - 1);
+        self.levels[lower]
+            .sample(uv)
+            .lerp(self.levels[upper].sample(uv), lod - lower as f32)
+    }
+
+    /// The smallest level that is at least `width` wide.
+    fn level_for_width(&self, width: usize) -> &EquirectImage {
+        self.levels
+            .iter()
+            .rev()
+            .find(|level| level.width >= width)
+            .unwrap_or(&self.levels[0])
+    }
+
+    /// Cosine-weighted irradiance, divided by pi so that a uniform environment of radiance 1
+    /// gives 1.
+    fn irradiance(&self, width: usize, height: usize) -> EquirectImage {
+        let input = self.level_for_width(width);
+        let texel_angle = (2.0 * PI / input.width as f32) * (PI / input.height as f32);
+        let samples: Vec<(Vec3, Vec3)> = (0..input.height)
+            .flat_map(|y| (0..input.width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let direction = input.texel_direction(x, y);
+                let solid_angle = texel_angle * (1.0 - direction.y * direction.y).sqrt();
+                (direction, input.texel(x, y) * solid_angle)
+            })
+            .collect();
+
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let normal = uv_to_direction(Vec2::new(
+                    (x as f32 + 0.5) / width as f32,
+                    (y as f32 + 0.5) / height as f32,
+                ));
+                let irradiance: Vec3 = samples
+                    .iter()
+                    .map(|(direction, radiance)| *radiance * normal.dot(*direction).max(0.0))
+                    .sum();
+                pixels.push(irradiance / PI);
+            }
+        }
+        EquirectImage {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Radiance convolved with a GGX lobe of the given roughness, assuming that the view
+    /// and reflection directions equal the normal.
+    ///
+    /// Uses importance sampling, reading each sample from a pyramid level that matches the
+    /// solid angle it represents to avoid noise with few samples.
+    fn prefilter_specular(&self, width: usize, height: usize, roughness: f32) -> EquirectImage {
+        let source = &self.levels[0];
+        let texel_solid_angle = 4.0 * PI / (source.width * source.height) as f32;
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let normal = uv_to_direction(Vec2::new(
+                    (x as f32 + 0.5) / width as f32,
+                    (y as f32 + 0.5) / height as f32,
+                ));
+                if roughness == 0.0 {
+                    let lod = (source.width as f32 / width as f32).log2();
+                    pixels.push(self.sample(normal, lod));
+                    continue;
+                }
+                let mut sum = Vec3::ZERO;
+                let mut weight = 0.0;
+                for i in 0..SPECULAR_SAMPLE_COUNT {
+                    let half = importance_sample_ggx(hammersley(i), normal, roughness);
+                    let light = 2.0 * normal.dot(half) * half - normal;
+                    let n_dot_l = normal.dot(light);
+                    if n_dot_l <= 0.0 {
+                        continue;
+                    }
+                    // With the view along the normal, n·h equals h·v and the pdf is D / 4.
+                    let n_dot_h = normal.dot(half).max(0.0);
+                    let pdf = distribution_ggx(n_dot_h, roughness) / 4.0 + 1e-4;
+                    let sample_solid_angle = 1.0 / (SPECULAR_SAMPLE_COUNT as f32 * pdf);
+                    let lod = 0.5 * (sample_solid_angle / texel_solid_angle).log2() + 1.0;
+                    sum += self.sample(light, lod) * n_dot_l;
+                    weight += n_dot_l;
+                }
+                pixels.push(sum / weight.max(1e-4));
+            }
+        }
+        EquirectImage {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+fn hammersley(i: u32) -> Vec2 {
+    Vec2::new(
+        i as f32 / SPECULAR_SAMPLE_COUNT as f32,
+        i.reverse_bits() as f32 * 2.328_306_4e-10,
+    )
+}
+
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+    let alpha2 = roughness.powi(4);
+    let denominator = n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0;
+    alpha2 / (PI * denominator * denominator)
+}
+
+fn importance_sample_ggx(xi: Vec2, normal: Vec3, roughness: f32) -> Vec3 {
+    let alpha = roughness * roughness;
+    let phi = 2.0 * PI * xi.x;
+    let cos_theta = ((1.0 - xi.y) / (1.0 + (alpha * alpha - 1.0) * xi.y)).sqrt();
+    let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+    let up = if normal.z.abs() < 0.999 {
+        Vec3::Z
+    } else {
+        Vec3::X
+    };
+    let tangent = up.cross(normal).normalize();
+    let bitangent = normal.cross(tangent);
+    (tangent * sin_theta * phi.cos() + bitangent * sin_theta * phi.sin() + normal * cos_theta)
+        .normalize()
+}
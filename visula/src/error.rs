@@ -30,4 +30,12 @@ pub enum Error {
     EventLoop(#[from] winit::error::EventLoopError),
     #[error("missing binary data in glTF buffer")]
     GltfMissingBlobData,
+    #[error("failed to map buffer: {0}")]
+    BufferAsync(#[from] wgpu::BufferAsyncError),
+    #[error("device poll failed: {0}")]
+    Poll(#[from] wgpu::PollError),
+    #[error("surface does not support copying frames for screenshots")]
+    ScreenshotUnsupported,
+    #[error("image error: {0}")]
+    Image(#[from] image::ImageError),
 }
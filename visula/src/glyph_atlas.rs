@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+
+use bytemuck::{Pod, Zeroable};
+use glam::Vec2;
+use ttf_parser::{Face, GlyphId};
+
+use crate::error::Error;
+use crate::text::{FontChain, TextAlign, TextLayoutOptions};
+
+/// Width and height of the atlas in pixels.
+pub const ATLAS_SIZE: u32 = 1024;
+/// Resolution of the glyphs rasterized into the atlas.
+pub const PIXELS_PER_EM: f32 = 40.0;
+/// Distance in em from the outline where the signed distance field saturates, which limits
+/// the width of outlines drawn from it.
+pub const SPREAD: f32 = 0.15;
+/// Empty pixels between glyphs, so that linear filtering does not bleed into neighbours.
+const PADDING: u32 = 1;
+/// Line segments used to flatten each quadratic and cubic curve of an outline.
+const QUADRATIC_STEPS: usize = 6;
+const CUBIC_STEPS: usize = 10;
+
+/// A glyph quad, with its corners in em and the matching texture coordinates in the atlas.
+///
+/// The coordinates are given for the bottom-left and top-right corners.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct AtlasGlyph {
+    pub min: Vec2,
+    pub max: Vec2,
+    pub uv_min: Vec2,
+    pub uv_max: Vec2,
+}
+
+/// A single-channel atlas of signed distance fields of glyphs from a TrueType or OpenType
+/// font.
+///
+/// Each pixel stores the distance to the glyph outline, mapped from `[-SPREAD, SPREAD]` em to
+/// `[0, 1]` and positive inside the glyph, so the outline is at `0.5`. Glyphs are rasterized
+/// on the CPU the first time they are laid out and packed into rows of the atlas. When the
+/// atlas is full, further glyphs are left out.
+pub struct GlyphAtlas {
+    font_data: Vec<u8>,
+    glyphs: GlyphCache,
+}
+
+/// The rasterized glyphs and where they are in the atlas, kept apart from the font data so
+/// that glyphs can be added while the font is borrowed for layout.
+struct GlyphCache {
+    quads: HashMap<u16, Option<AtlasGlyph>>,
+    pixels: Vec<u8>,
+    cursor: (u32, u32),
+    row_height: u32,
+    changed: bool,
+}
+
+impl GlyphAtlas {
+    pub fn new(font_data: &[u8]) -> Result<Self, Error> {
+        Face::parse(font_data, 0).map_err(|_| Error::FontParse)?;
+        Ok(GlyphAtlas {
+            font_data: font_data.to_vec(),
+            glyphs: GlyphCache {
+                quads: HashMap::new(),
+                pixels: vec![0; (ATLAS_SIZE * ATLAS_SIZE) as usize],
+                cursor: (0, 0),
+                row_height: 0,
+                changed: false,
+            },
+        })
+    }
+
+    /// The atlas pixels, row by row from the top.
+    pub fn pixels(&self) -> &[u8] {
+        &self.glyphs.pixels
+    }
+
+    /// Returns whether glyphs have been added since the last call.
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.glyphs.changed)
+    }
+
+    /// Lays out each of `texts` with [`FontChain::layout`], rasterizing glyphs that are not
+    /// yet in the atlas. The font is parsed once for all of them.
+    ///
+    /// The quads are given in em, with the lines centered horizontally on the origin and the
+    /// text centered vertically between the ascender of the first line and the descender of
+    /// the last.
+    pub fn layout<S: AsRef<str>>(&mut self, texts: &[S]) -> Vec<Vec<AtlasGlyph>> {
+        let fonts =
+            FontChain::new(&self.font_data, &[]).expect("font was parsed when creating the atlas");
+        let options = TextLayoutOptions {
+            align: TextAlign::Center,
+            ..Default::default()
+        };
+        let mut result = Vec::with_capacity(texts.len());
+        for text in texts {
+            let layout = fonts.layout(text.as_ref(), &options);
+            let center = Vec2::new(0.0, 0.5 * (layout.bounds.min.y + layout.bounds.max.y));
+            let mut quads = Vec::new();
+            for positioned in &layout.glyphs {
+                let face = fonts.face(positioned.font);
+                if let Some(quad) = self.glyphs.glyph(face, positioned.glyph) {
+                    let offset = positioned.position - center;
+                    quads.push(AtlasGlyph {
+                        min: quad.min + offset,
+                        max: quad.max + offset,
+                        ..quad
+                    });
+                }
+            }
+            result.push(quads);
+        }
+        result
+    }
+}
+
+impl GlyphCache {
+    /// The quad of a glyph relative to its origin, or `None` for glyphs without an outline.
+    fn glyph(&mut self, face: &Face, glyph: GlyphId) -> Option<AtlasGlyph> {
+        if let Some(quad) = self.quads.get(&glyph.0) {
+            return *quad;
+        }
+        let quad = self.rasterize(face, glyph);
+        self.quads.insert(glyph.0, quad);
+        quad
+    }
+
+    fn rasterize(&mut self, face: &Face, glyph: GlyphId) -> Option<AtlasGlyph> {
+        let mut outline = OutlineFlattener::new(1.0 / face.units_per_em() as f32);
+        let bounds = face.outline_glyph(glyph, &mut outline)?;
+        let min = Vec2::new(bounds.x_min as f32, bounds.y_min as f32) * outline.scale
+            - Vec2::splat(SPREAD);
+        let max = Vec2::new(bounds.x_max as f32, bounds.y_max as f32) * outline.scale
+            + Vec2::splat(SPREAD);
+        let width = ((max.x - min.x) * PIXELS_PER_EM).ceil() as u32;
+        let height = ((max.y - min.y) * PIXELS_PER_EM).ceil() as u32;
+        let Some((x, y)) = self.allocate(width, height) else {
+            log::warn!("Glyph atlas is full, skipping glyph {}", glyph.0);
+            return None;
+        };
+
+        let top = min.y + height as f32 / PIXELS_PER_EM;
+        for row in 0..height {
+            for column in 0..width {
+                let point = Vec2::new(
+                    min.x + (column as f32 + 0.5) / PIXELS_PER_EM,
+                    top - (row as f32 + 0.5) / PIXELS_PER_EM,
+                );
+                let distance = outline.signed_distance(point);
+                let value = (0.5 + 0.5 * distance / SPREAD).clamp(0.0, 1.0);
+                self.pixels[((y + row) * ATLAS_SIZE + x + column) as usize] =
+                    (value * 255.0).round() as u8;
+            }
+        }
+        self.changed = true;
+
+        let size = ATLAS_SIZE as f32;
+        Some(AtlasGlyph {
+            min,
+            max: Vec2::new(
+                min.x + width as f32 / PIXELS_PER_EM,
+                min.y + height as f32 / PIXELS_PER_EM,
+            ),
+            uv_min: Vec2::new(x as f32, (y + height) as f32) / size,
+            uv_max: Vec2::new((x + width) as f32, y as f32) / size,
+        })
+    }
+
+    /// Finds space for a glyph in the current row of the atlas, or starts a new row.
+    fn allocate(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        if self.cursor.0 + width > ATLAS_SIZE {
+            self.cursor = (0, self.cursor.1 + self.row_height + PADDING);
+            self.row_height = 0;
+        }
+        if width > ATLAS_SIZE || self.cursor.1 + height > ATLAS_SIZE {
+            return None;
+        }
+        let position = self.cursor;
+        self.cursor.0 += width + PADDING;
+        self.row_height = self.row_height.max(height);
+        Some(position)
+    }
+}
+
+/// Collects the outline of a glyph as closed contours of line segments, in em.
+struct OutlineFlattener {
+    scale: f32,
+    segments: Vec<(Vec2, Vec2)>,
+    start: Vec2,
+    current: Vec2,
+}
+
+impl OutlineFlattener {
+    fn new(scale: f32) -> Self {
+        OutlineFlattener {
+            scale,
+            segments: Vec::new(),
+            start: Vec2::ZERO,
+            current: Vec2::ZERO,
+        }
+    }
+
+    fn point(&self, x: f32, y: f32) -> Vec2 {
+        Vec2::new(x, y) * self.scale
+    }
+
+    fn push(&mut self, point: Vec2) {
+        if point != self.current {
+            self.segments.push((self.current, point));
+        }
+        self.current = point;
+    }
+
+    /// Distance from `point` to the outline, positive inside it by the non-zero winding rule.
+    fn signed_distance(&self, point: Vec2) -> f32 {
+        let mut distance = f32::MAX;
+        let mut winding = 0;
+        for &(a, b) in &self.segments {
+            let ab = b - a;
+            let t = ((point - a).dot(ab) / ab.length_squared()).clamp(0.0, 1.0);
+            distance = distance.min(point.distance(a + t * ab));
+            if (a.y <= point.y) != (b.y <= point.y) {
+                let crossing = a.x + (point.y - a.y) / (b.y - a.y) * ab.x;
+                if crossing > point.x {
+                    winding += if b.y > a.y { 1 } else { -1 };
+                }
+            }
+        }
+        if winding != 0 {
+            distance
+        } else {
+            -distance
+        }
+    }
+}
+
+impl ttf_parser::OutlineBuilder for OutlineFlattener {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.start = self.point(x, y);
+        self.current = self.start;
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.push(self.point(x, y));
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (p0, p1, p2) = (self.current, self.point(x1, y1), self.point(x, y));
+        for step in 1..=QUADRATIC_STEPS {
+            let t = step as f32 / QUADRATIC_STEPS as f32;
+            let s = 1.0 - t;
+            self.push(s * s * p0 + 2.0 * s * t * p1 + t * t * p2);
+        }
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (p0, p1, p2, p3) = (
+            self.current,
+            self.point(x1, y1),
+            self.point(x2, y2),
+            self.point(x, y),
+        );
+        for step in 1..=CUBIC_STEPS {
+            let t = step as f32 / CUBIC_STEPS as f32;
+            let s = 1.0 - t;
+            self.push(
+                s * s * s * p0 + 3.0 * s * s * t * p1 + 3.0 * s * t * t * p2 + t * t * t * p3,
+            );
+        }
+    }
+
+    fn close(&mut self) {
+        self.push(self.start);
+    }
+}
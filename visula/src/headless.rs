@@ -0,0 +1,286 @@
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use wgpu::TextureFormat;
+
+use crate::application::{
+    create_depth_texture, create_instance, create_multisampled_framebuffer, render_scene,
+    request_device, unpad_rgba, SceneTargets,
+};
+use crate::camera::controller::CameraController;
+use crate::camera::Camera;
+use crate::environment::Environment;
+use crate::error::Error;
+use crate::light::DirectionalLight;
+use crate::post_process::config::SkyMode;
+use crate::post_process::PostProcessor;
+use crate::rendering_descriptor::RenderingDescriptor;
+use crate::Simulation;
+
+const OUTPUT_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+/// An offscreen counterpart to [`crate::Application`] that renders into an owned texture
+/// instead of a window surface.
+///
+/// Uses the same camera, light and post-processing setup as the windowed application, so a
+/// [`Simulation`] renders identically in both. There is no egui overlay and no event handling;
+/// the simulation's `render_shadow` and `render` are called directly by
+/// [`HeadlessApplication::render`].
+#[derive(Debug)]
+pub struct HeadlessApplication {
+    pub device: wgpu::Device,
+    pub queue: wgpu::Queue,
+    pub width: u32,
+    pub height: u32,
+    pub camera_controller: CameraController,
+    pub depth_texture: wgpu::TextureView,
+    pub multisampled_framebuffer: wgpu::TextureView,
+    pub output_texture: wgpu::Texture,
+    pub output_view: wgpu::TextureView,
+    pub camera: Camera,
+    pub light: DirectionalLight,
+    pub post_processor: PostProcessor,
+    pub start_time: DateTime<Utc>,
+    pub sample_count: u32,
+}
+
+impl HeadlessApplication {
+    /// Create an offscreen application rendering `width` x `height` frames.
+    ///
+    /// Prefers a hardware adapter, but falls back to a software adapter (such as llvmpipe or
+    /// WARP) when none is available, so that frames can be produced on machines without a GPU.
+    pub async fn new(width: u32, height: u32) -> Result<HeadlessApplication, Error> {
+        let instance = create_instance();
+        let adapter = match instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: None,
+                force_fallback_adapter: false,
+            })
+            .await
+        {
+            Ok(adapter) => adapter,
+            Err(_) => instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::default(),
+                    compatible_surface: None,
+                    force_fallback_adapter: true,
+                })
+                .await
+                .map_err(|_| Error::NoAdapter)?,
+        };
+        log::info!("headless rendering with {:?}", adapter.get_info());
+
+        let (device, queue) = request_device(&adapter).await?;
+
+        // Software adapters do not necessarily support multisampling of the HDR target.
+        let sample_count = if adapter
+            .get_texture_format_features(TextureFormat::Rgba16Float)
+            .flags
+            .sample_count_supported(4)
+        {
+            4
+        } else {
+            1
+        };
+
+        let depth_texture = create_depth_texture(&device, width, height, sample_count);
+        let multisampled_framebuffer =
+            create_multisampled_framebuffer(&device, width, height, sample_count);
+
+        let output_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("headless output"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: OUTPUT_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let output_view = output_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let camera_controller = CameraController::new_headless(width, height);
+        let camera = Camera::new(&device);
+        let light = DirectionalLight::new(&device);
+        let post_processor = PostProcessor::new(
+            &device,
+            &queue,
+            width,
+            height,
+            OUTPUT_FORMAT,
+            &camera,
+            &depth_texture,
+            sample_count,
+        );
+
+        Ok(HeadlessApplication {
+            device,
+            queue,
+            width,
+            height,
+            camera_controller,
+            depth_texture,
+            multisampled_framebuffer,
+            output_texture,
+            output_view,
+            camera,
+            light,
+            post_processor,
+            start_time: Utc::now(),
+            sample_count,
+        })
+    }
+
+    /// Upload the camera and light uniforms.
+    ///
+    /// There is no user interaction to smooth out, so the camera jumps straight to
+    /// `camera_controller.target_transform`.
+    pub fn update(&mut self) {
+        self.camera_controller.current_transform = self.camera_controller.target_transform.clone();
+        let camera_uniforms = self
+            .camera_controller
+            .uniforms(self.width as f32, self.height as f32);
+        self.camera.update(&camera_uniforms, &self.queue);
+        self.light.update(&self.queue, &camera_uniforms);
+    }
+
+    /// Render one frame of `simulation` into [`HeadlessApplication::output_texture`].
+    pub fn render(&mut self, simulation: &mut impl Simulation) {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        render_scene(
+            &mut encoder,
+            &self.queue,
+            simulation,
+            SceneTargets {
+                output_view: &self.output_view,
+                multisampled_framebuffer: &self.multisampled_framebuffer,
+                depth_texture: &self.depth_texture,
+                sample_count: self.sample_count,
+            },
+            &self.camera,
+            &self.light,
+            &self.post_processor,
+        );
+        self.queue.submit(Some(encoder.finish()));
+    }
+
+    /// Read back the last rendered frame as tightly packed, row-major RGBA8 pixels.
+    ///
+    /// Blocks until the GPU has finished rendering.
+    pub fn read_pixels(&self) -> Result<Vec<u8>, Error> {
+        let bytes_per_pixel = 4u32;
+        let unpadded_bytes_per_row = self.width * bytes_per_pixel;
+        let padded_bytes_per_row = unpadded_bytes_per_row
+            .div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("headless readback buffer"),
+            size: (padded_bytes_per_row as u64) * (self.height as u64),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture: &self.output_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(self.height),
+                },
+            },
+            wgpu::Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+        );
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device.poll(wgpu::PollType::wait_indefinitely())?;
+        receiver
+            .recv()
+            .expect("map_async callback dropped without being called")?;
+
+        let data = slice.get_mapped_range();
+        let pixels = unpad_rgba(
+            &data,
+            padded_bytes_per_row,
+            self.width,
+            self.height,
+            OUTPUT_FORMAT,
+        );
+        drop(data);
+        buffer.unmap();
+        Ok(pixels)
+    }
+
+    /// Read back the last rendered frame and write it as a PNG to `path`.
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let pixels = self.read_pixels()?;
+        image::save_buffer(
+            path,
+            &pixels,
+            self.width,
+            self.height,
+            image::ColorType::Rgba8,
+        )?;
+        Ok(())
+    }
+
+    /// Light the scene with `environment` and show it as the sky.
+    pub fn set_environment(&mut self, environment: Environment) {
+        self.post_processor
+            .set_environment(&self.device, &environment);
+        self.post_processor.config.sky.mode = SkyMode::Environment;
+        self.light.set_environment(&self.device, environment);
+    }
+
+    pub fn rendering_descriptor(&self) -> RenderingDescriptor<'_> {
+        RenderingDescriptor {
+            device: &self.device,
+            format: wgpu::TextureFormat::Rgba16Float,
+            camera: &self.camera,
+            light: &self.light,
+            sample_count: self.sample_count,
+        }
+    }
+
+    pub fn instances<T>(&self, data: &[T]) -> T::Type
+    where
+        T: visula_core::Instance + bytemuck::Pod,
+    {
+        visula_core::InstanceBuffer::new_with_init(&self.device, data).instance()
+    }
+
+    /// Like [`Self::instances`], but the data is read from a storage buffer by
+    /// `instance_index`, see [`visula_core::InstanceBindingMode::Storage`].
+    pub fn storage_instances<T>(&self, data: &[T]) -> T::Type
+    where
+        T: visula_core::Instance + bytemuck::Pod,
+    {
+        visula_core::InstanceBuffer::new_with_init(&self.device, data)
+            .with_binding_mode(visula_core::InstanceBindingMode::Storage)
+            .instance()
+    }
+}
@@ -34,18 +34,25 @@ pub use cgmath;
 pub use egui_wgpu;
 pub use visula_core;
 pub use visula_core::{
-    clamp, colormap, glam, max, min, mix, naga, smoothstep, step, uuid, vec2, vec3, vec4, wgpu,
-    Colormap, Expression, InstanceBuffer, InstanceDeviceExt, TextureInput, UniformBuffer,
+    clamp, colormap, glam, look_at, mat2, mat3, mat4, max, min, mix, naga, quat_from_axis_angle,
+    quat_from_to, quat_mul, quat_rotate, select, smoothstep, step, uuid, vec2, vec3, vec4, wgpu,
+    Colormap, Expression, InstanceBindingMode, InstanceBuffer, InstanceDeviceExt, NormalMap, Pbr,
+    ShaderFunctions, TextureInput, UniformBuffer,
 };
 
 pub mod application;
 pub mod camera;
 pub mod custom_event;
 pub mod drop_event;
+pub mod environment;
 pub mod error;
+pub mod glyph_atlas;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod headless;
 pub mod io;
 pub mod light;
 pub mod painter;
+pub mod picking;
 pub mod pipelines;
 pub mod post_process;
 pub mod primitives;
@@ -56,12 +63,16 @@ pub mod simulation;
 pub mod text;
 pub mod vec_to_buffer;
 
-pub use application::{Application, PendingScreenshot};
+pub use application::{Application, PendingScreenshot, RecordingConfig};
 pub use camera::controller::{CameraController, CameraControllerResponse, CameraTransform};
 pub use camera::Camera;
 pub use custom_event::CustomEvent;
 pub use drop_event::DropEvent;
-pub use light::DirectionalLight;
+pub use environment::Environment;
+#[cfg(not(target_arch = "wasm32"))]
+pub use headless::HeadlessApplication;
+pub use light::{DirectionalLight, Light, LightKind};
+pub use picking::PickResult;
 pub use pipelines::*;
 pub use primitives::*;
 pub use render_pass::*;
@@ -93,6 +104,8 @@ struct App<F, S> {
     init_simulation: F,
     #[cfg(not(target_arch = "wasm32"))]
     auto_screenshot: Option<AutoScreenshot>,
+    #[cfg(not(target_arch = "wasm32"))]
+    auto_recording: Option<AutoRecording>,
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -120,6 +133,45 @@ impl AutoScreenshot {
     }
 }
 
+/// Records an image sequence from startup when `VISULA_RECORDING` is set to an output
+/// directory. `VISULA_RECORDING_INTERVAL` and `VISULA_RECORDING_TIME_STEP` configure the
+/// capture interval and simulated time step, and the application exits after
+/// `VISULA_RECORDING_FRAMES` frames have been written, if set.
+#[cfg(not(target_arch = "wasm32"))]
+struct AutoRecording {
+    config: RecordingConfig,
+    frame_count: Option<u32>,
+    started: bool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl AutoRecording {
+    fn from_env() -> Option<Self> {
+        let directory = std::env::var_os("VISULA_RECORDING")?;
+        let defaults = RecordingConfig::default();
+        let frame_interval = std::env::var("VISULA_RECORDING_INTERVAL")
+            .ok()
+            .and_then(|s| s.parse::<u32>().ok())
+            .unwrap_or(defaults.frame_interval);
+        let time_step = std::env::var("VISULA_RECORDING_TIME_STEP")
+            .ok()
+            .and_then(|s| s.parse::<f32>().ok())
+            .unwrap_or(defaults.time_step);
+        let frame_count = std::env::var("VISULA_RECORDING_FRAMES")
+            .ok()
+            .and_then(|s| s.parse::<u32>().ok());
+        Some(AutoRecording {
+            config: RecordingConfig {
+                directory: PathBuf::from(directory),
+                frame_interval,
+                time_step,
+            },
+            frame_count,
+            started: false,
+        })
+    }
+}
+
 impl<F, S> ApplicationHandler<CustomEvent> for App<F, S>
 where
     F: FnMut(&mut Application) -> S + 'static,
@@ -162,6 +214,15 @@ where
         }
         match event {
             WindowEvent::RedrawRequested => {
+                #[cfg(not(target_arch = "wasm32"))]
+                if let Some(auto) = self.auto_recording.as_mut() {
+                    if !auto.started {
+                        if let Err(e) = application.start_recording(auto.config.clone()) {
+                            log::error!("Failed to start recording: {e}");
+                        }
+                        auto.started = true;
+                    }
+                }
                 application.update();
                 simulation.update(application);
                 #[cfg(not(target_arch = "wasm32"))]
@@ -180,6 +241,15 @@ where
                         return;
                     }
                 }
+                #[cfg(not(target_arch = "wasm32"))]
+                if let Some(auto) = self.auto_recording.as_ref() {
+                    let written = application.recorded_frames().unwrap_or(0);
+                    if auto.frame_count.is_some_and(|count| written >= count) {
+                        application.stop_recording();
+                        event_loop.exit();
+                        return;
+                    }
+                }
                 application.window.request_redraw();
             }
             WindowEvent::CloseRequested => event_loop.exit(),
@@ -365,6 +435,8 @@ where
         main_window_id: None,
         #[cfg(not(target_arch = "wasm32"))]
         auto_screenshot: AutoScreenshot::from_env(),
+        #[cfg(not(target_arch = "wasm32"))]
+        auto_recording: AutoRecording::from_env(),
     };
 
     event_loop
@@ -2,8 +2,13 @@ use bytemuck::{Pod, Zeroable};
 use glam::{Mat4, Vec3};
 use std::mem::size_of;
 
+use crate::camera::uniforms::CameraUniforms;
+use crate::environment::Environment;
 use crate::vec_to_buffer::vec_to_buffer;
 
+/// Maximum number of shadow cascades supported by the lighting shader.
+pub const MAX_CASCADES: usize = 4;
+
 #[repr(C)]
 #[derive(Clone, Copy, Debug)]
 pub struct LightUniforms {
@@ -12,6 +17,10 @@ pub struct LightUniforms {
     pub color: Vec3,
     pub intensity: f32,
     pub light_view_proj: Mat4,
+    pub cascade_view_proj: [Mat4; MAX_CASCADES],
+    pub cascade_count: u32,
+    pub shadow_map_size: f32,
+    pub _pad1: [f32; 2],
 }
 
 unsafe impl Pod for LightUniforms {}
@@ -27,33 +36,213 @@ impl AsRef<[f32; size_of::<LightUniforms>() / size_of::<f32>()]> for LightUnifor
     }
 }
 
-pub const SHADOW_MAP_SIZE: u32 = 4096;
+/// Default resolution of each shadow cascade.
+pub const SHADOW_MAP_SIZE: u32 = 2048;
+
+/// Default number of shadow cascades.
+pub const CASCADE_COUNT: u32 = 4;
+
+/// Maximum number of entries in [`DirectionalLight::lights`] that are uploaded to the GPU.
+pub const MAX_LIGHTS: usize = 16;
+
+/// The shape of a [`Light`] and its shape-specific parameters.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LightKind {
+    /// Light from infinitely far away, travelling along `direction`.
+    Directional { direction: Vec3 },
+    /// Light emitted in all directions from `position`.
+    ///
+    /// The inverse-square falloff is smoothly brought to zero at `range`. A `range` of zero
+    /// or less disables the cutoff.
+    Point { position: Vec3, range: f32 },
+    /// Light emitted from `position` in a cone around `direction`.
+    ///
+    /// The intensity is full inside `inner_angle` and fades to zero at `outer_angle`, both
+    /// measured in radians from the cone axis.
+    Spot {
+        position: Vec3,
+        direction: Vec3,
+        range: f32,
+        inner_angle: f32,
+        outer_angle: f32,
+    },
+}
+
+/// An additional light source that is evaluated by the lit shading functions.
+///
+/// Unlike the main [`DirectionalLight`], these lights do not cast shadows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Light {
+    pub kind: LightKind,
+    pub color: Vec3,
+    pub intensity: f32,
+}
+
+impl Light {
+    pub fn directional(direction: Vec3) -> Self {
+        Light {
+            kind: LightKind::Directional { direction },
+            color: Vec3::ONE,
+            intensity: 1.0,
+        }
+    }
+
+    pub fn point(position: Vec3, range: f32) -> Self {
+        Light {
+            kind: LightKind::Point { position, range },
+            color: Vec3::ONE,
+            intensity: 1.0,
+        }
+    }
+
+    pub fn spot(
+        position: Vec3,
+        direction: Vec3,
+        range: f32,
+        inner_angle: f32,
+        outer_angle: f32,
+    ) -> Self {
+        Light {
+            kind: LightKind::Spot {
+                position,
+                direction,
+                range,
+                inner_angle,
+                outer_angle,
+            },
+            color: Vec3::ONE,
+            intensity: 1.0,
+        }
+    }
+
+    pub fn with_color(mut self, color: Vec3) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    fn uniforms(&self) -> LightSourceUniforms {
+        let mut uniforms = LightSourceUniforms {
+            color: self.color,
+            intensity: self.intensity,
+            ..Default::default()
+        };
+        match self.kind {
+            LightKind::Directional { direction } => {
+                uniforms.kind = 0;
+                uniforms.direction = direction;
+            }
+            LightKind::Point { position, range } => {
+                uniforms.kind = 1;
+                uniforms.position = position;
+                uniforms.range = range;
+            }
+            LightKind::Spot {
+                position,
+                direction,
+                range,
+                inner_angle,
+                outer_angle,
+            } => {
+                uniforms.kind = 2;
+                uniforms.position = position;
+                uniforms.direction = direction;
+                uniforms.range = range;
+                uniforms.inner_cos = inner_angle.cos();
+                uniforms.outer_cos = outer_angle.cos();
+            }
+        }
+        uniforms
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod, Zeroable)]
+pub struct LightSourceUniforms {
+    pub position: Vec3,
+    pub kind: u32,
+    pub direction: Vec3,
+    pub range: f32,
+    pub color: Vec3,
+    pub intensity: f32,
+    pub inner_cos: f32,
+    pub outer_cos: f32,
+    pub _pad0: [f32; 2],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod, Zeroable)]
+pub struct LightListUniforms {
+    pub fill_direction: Vec3,
+    pub fill_intensity: f32,
+    pub ambient: f32,
+    pub count: u32,
+    pub environment_intensity: f32,
+    pub environment_mip_count: f32,
+    pub lights: [LightSourceUniforms; MAX_LIGHTS],
+}
 
 #[derive(Debug)]
 pub struct DirectionalLight {
     pub bind_group_layout: wgpu::BindGroupLayout,
     pub bind_group: wgpu::BindGroup,
     pub uniform_buffer: wgpu::Buffer,
+    /// Array texture with one layer per cascade.
     pub shadow_texture: wgpu::Texture,
+    /// View of all cascades, sampled by the lighting shader.
     pub shadow_texture_view: wgpu::TextureView,
+    /// Views of the individual cascades, rendered to by the shadow passes.
+    pub cascade_views: Vec<wgpu::TextureView>,
     pub shadow_sampler: wgpu::Sampler,
     pub shadow_bind_group_layout: wgpu::BindGroupLayout,
-    pub shadow_bind_group: wgpu::BindGroup,
+    /// Uniforms for the shadow pass of each cascade, with `light_view_proj` set to the
+    /// cascade's projection.
+    pub cascade_buffers: Vec<wgpu::Buffer>,
+    pub cascade_bind_groups: Vec<wgpu::BindGroup>,
+    pub light_list_buffer: wgpu::Buffer,
     pub direction: Vec3,
     pub color: Vec3,
     pub intensity: f32,
-    /// Center of the shadow frustum in world space.
-    /// Set this to the camera look-at point each frame.
-    pub shadow_center: Vec3,
-    /// Half-extent of the orthographic shadow frustum.
-    /// Controls how large an area the shadow map covers.
-    pub shadow_extent: f32,
-    /// Distance from shadow_center to the light source position.
+    /// Distance from the center of each cascade to the light source position.
+    /// Shadow casters further towards the light than this are clipped.
     pub shadow_distance: f32,
+    /// Distance from the camera covered by the cascades. When `None`, four times the
+    /// distance from the camera to its center is used.
+    pub shadow_far: Option<f32>,
+    /// Blend between uniform (0.0) and logarithmic (1.0) cascade split distances.
+    pub cascade_split_lambda: f32,
+    cascade_count: u32,
+    shadow_map_size: u32,
+    cascade_view_proj: [Mat4; MAX_CASCADES],
+    /// Additional point, spot and directional lights. Only the first [`MAX_LIGHTS`] are used.
+    pub lights: Vec<Light>,
+    /// Direction of the unshadowed fill light, tinted by `color` like the main light.
+    pub fill_direction: Vec3,
+    pub fill_intensity: f32,
+    /// Constant light added regardless of the surface orientation, tinted by `color`.
+    /// Replaced by the environment's irradiance once one is set.
+    pub ambient: f32,
+    /// Image-based ambient light, see [`Self::set_environment`].
+    pub environment: Environment,
+    pub environment_intensity: f32,
 }
 
 impl DirectionalLight {
     pub fn new(device: &wgpu::Device) -> Self {
+        Self::with_cascades(device, CASCADE_COUNT, SHADOW_MAP_SIZE)
+    }
+
+    /// Create a light whose shadows are split into `cascade_count` cascades of
+    /// `shadow_map_size` x `shadow_map_size` texels each.
+    pub fn with_cascades(device: &wgpu::Device, cascade_count: u32, shadow_map_size: u32) -> Self {
+        assert!(
+            (1..=MAX_CASCADES as u32).contains(&cascade_count),
+            "cascade count must be between 1 and {MAX_CASCADES}"
+        );
         let direction = Vec3::new(-1.0, -1.0, -1.0).normalize();
         let color = Vec3::ONE;
         let intensity = 1.0;
@@ -76,7 +265,7 @@ impl DirectionalLight {
                     visibility: wgpu::ShaderStages::FRAGMENT,
                     ty: wgpu::BindingType::Texture {
                         sample_type: wgpu::TextureSampleType::Depth,
-                        view_dimension: wgpu::TextureViewDimension::D2,
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
                         multisampled: false,
                     },
                     count: None,
@@ -87,15 +276,53 @@ impl DirectionalLight {
                     ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
                     count: None,
                 },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            size_of::<LightListUniforms>() as u64
+                        ),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 6,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
             ],
         });
 
         let shadow_texture = device.create_texture(&wgpu::TextureDescriptor {
             label: Some("shadow map"),
             size: wgpu::Extent3d {
-                width: SHADOW_MAP_SIZE,
-                height: SHADOW_MAP_SIZE,
-                depth_or_array_layers: 1,
+                width: shadow_map_size,
+                height: shadow_map_size,
+                depth_or_array_layers: cascade_count,
             },
             mip_level_count: 1,
             sample_count: 1,
@@ -105,8 +332,22 @@ impl DirectionalLight {
             view_formats: &[],
         });
 
-        let shadow_texture_view =
-            shadow_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let shadow_texture_view = shadow_texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("shadow map cascades"),
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        let cascade_views = (0..cascade_count)
+            .map(|cascade| {
+                shadow_texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some(&format!("shadow map cascade {cascade}")),
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    base_array_layer: cascade,
+                    array_layer_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
 
         let shadow_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
             label: Some("shadow sampler"),
@@ -123,24 +364,22 @@ impl DirectionalLight {
             wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         );
 
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("light bind group"),
-            layout: &bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: uniform_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::TextureView(&shadow_texture_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: wgpu::BindingResource::Sampler(&shadow_sampler),
-                },
-            ],
-        });
+        let light_list_buffer = vec_to_buffer(
+            device,
+            &[LightListUniforms::default()],
+            wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        );
+
+        let environment = Environment::empty(device);
+        let bind_group = create_bind_group(
+            device,
+            &bind_group_layout,
+            &uniform_buffer,
+            &shadow_texture_view,
+            &shadow_sampler,
+            &light_list_buffer,
+            &environment,
+        );
 
         let shadow_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -157,14 +396,28 @@ impl DirectionalLight {
                 }],
             });
 
-        let shadow_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("shadow pass bind group"),
-            layout: &shadow_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: uniform_buffer.as_entire_binding(),
-            }],
-        });
+        let cascade_buffers: Vec<wgpu::Buffer> = (0..cascade_count)
+            .map(|_| {
+                vec_to_buffer(
+                    device,
+                    light_uniforms.as_ref(),
+                    wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                )
+            })
+            .collect();
+        let cascade_bind_groups = cascade_buffers
+            .iter()
+            .map(|buffer| {
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("shadow pass bind group"),
+                    layout: &shadow_bind_group_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: buffer.as_entire_binding(),
+                    }],
+                })
+            })
+            .collect();
 
         DirectionalLight {
             bind_group_layout,
@@ -172,35 +425,231 @@ impl DirectionalLight {
             uniform_buffer,
             shadow_texture,
             shadow_texture_view,
+            cascade_views,
             shadow_sampler,
             shadow_bind_group_layout,
-            shadow_bind_group,
+            cascade_buffers,
+            cascade_bind_groups,
+            light_list_buffer,
             direction,
             color,
             intensity,
-            shadow_center: Vec3::ZERO,
-            shadow_extent: 50.0,
             shadow_distance: 200.0,
+            shadow_far: None,
+            cascade_split_lambda: 0.75,
+            cascade_count,
+            shadow_map_size,
+            cascade_view_proj: [Mat4::IDENTITY; MAX_CASCADES],
+            lights: Vec::new(),
+            fill_direction: -Vec3::new(1.0, 0.4, -0.8).normalize(),
+            fill_intensity: 0.3,
+            ambient: 0.1,
+            environment,
+            environment_intensity: 1.0,
         }
     }
 
-    pub fn compute_light_view_proj(&self) -> Mat4 {
-        let light_pos = self.shadow_center - self.direction * self.shadow_distance;
-        let view = Mat4::look_at_rh(light_pos, self.shadow_center, Vec3::Y);
-        let e = self.shadow_extent;
-        let proj = Mat4::orthographic_rh(-e, e, -e, e, 1.0, self.shadow_distance * 2.0);
+    /// Use `environment` for the ambient light instead of the constant `ambient`.
+    pub fn set_environment(&mut self, device: &wgpu::Device, environment: Environment) {
+        self.bind_group = create_bind_group(
+            device,
+            &self.bind_group_layout,
+            &self.uniform_buffer,
+            &self.shadow_texture_view,
+            &self.shadow_sampler,
+            &self.light_list_buffer,
+            &environment,
+        );
+        self.environment = environment;
+    }
+
+    pub fn cascade_count(&self) -> u32 {
+        self.cascade_count
+    }
+
+    pub fn shadow_map_size(&self) -> u32 {
+        self.shadow_map_size
+    }
+
+    /// The light-space projections of the cascades from the last [`Self::update`], ordered
+    /// from the camera outwards.
+    pub fn cascade_view_proj(&self) -> &[Mat4] {
+        &self.cascade_view_proj[..self.cascade_count as usize]
+    }
+
+    /// Distances from the camera at which the cascades end, using the practical split scheme
+    /// that blends uniform and logarithmic splits by `cascade_split_lambda`.
+    pub fn cascade_splits(&self, near: f32, far: f32) -> Vec<f32> {
+        let count = self.cascade_count as f32;
+        let lambda = self.cascade_split_lambda.clamp(0.0, 1.0);
+        (1..=self.cascade_count)
+            .map(|i| {
+                let fraction = i as f32 / count;
+                let logarithmic = near * (far / near).powf(fraction);
+                let uniform = near + (far - near) * fraction;
+                lambda * logarithmic + (1.0 - lambda) * uniform
+            })
+            .collect()
+    }
+
+    /// Fit an orthographic projection around the slice of the camera frustum between the
+    /// view distances `near` and `far`.
+    ///
+    /// The slice is enclosed in a sphere so that the projection keeps its size as the camera
+    /// rotates, and the center is snapped to whole texels to avoid shimmering edges as the
+    /// camera moves.
+    fn fit_cascade(&self, camera_uniforms: &CameraUniforms, near: f32, far: f32) -> Mat4 {
+        let camera_position = camera_uniforms.position;
+        let forward = camera_uniforms.view_vector.normalize();
+        let mut corners = Vec::with_capacity(8);
+        for x in [-1.0, 1.0] {
+            for y in [-1.0, 1.0] {
+                let far_corner = camera_uniforms
+                    .inverse_view_projection_matrix
+                    .project_point3(Vec3::new(x, y, 1.0));
+                // Ray through the corner, scaled to unit length along the view direction.
+                let ray = far_corner - camera_position;
+                let ray = ray / ray.dot(forward);
+                corners.push(camera_position + ray * near);
+                corners.push(camera_position + ray * far);
+            }
+        }
+        let center = corners.iter().sum::<Vec3>() / corners.len() as f32;
+        let radius = corners
+            .iter()
+            .map(|corner| corner.distance(center))
+            .fold(0.0, f32::max);
+        let radius = (radius * 16.0).ceil() / 16.0;
+
+        let up = if self.direction.normalize().dot(Vec3::Y).abs() > 0.99 {
+            Vec3::Z
+        } else {
+            Vec3::Y
+        };
+        let light_rotation = Mat4::look_at_rh(Vec3::ZERO, self.direction, up);
+        let texel_size = 2.0 * radius / self.shadow_map_size as f32;
+        let light_center = light_rotation.transform_point3(center);
+        let snapped = Vec3::new(
+            (light_center.x / texel_size).floor() * texel_size,
+            (light_center.y / texel_size).floor() * texel_size,
+            light_center.z,
+        );
+        let center = light_rotation.inverse().transform_point3(snapped);
+
+        let light_position = center - self.direction.normalize() * self.shadow_distance;
+        let view = Mat4::look_at_rh(light_position, center, up);
+        let proj = Mat4::orthographic_rh(
+            -radius,
+            radius,
+            -radius,
+            radius,
+            0.0,
+            self.shadow_distance + radius,
+        );
         proj * view
     }
 
-    pub fn update(&self, queue: &wgpu::Queue) {
-        let light_view_proj = self.compute_light_view_proj();
-        let uniforms = LightUniforms {
+    /// Fit the cascades to the camera frustum and upload the light uniforms.
+    pub fn update(&mut self, queue: &wgpu::Queue, camera_uniforms: &CameraUniforms) {
+        // perspective_rh stores near * far / (near - far) and far / (near - far) in the
+        // projection, so their ratio is the near plane distance.
+        let projection = camera_uniforms.projection_matrix;
+        let camera_near = projection.w_axis.z / projection.z_axis.z;
+        let camera_distance = camera_uniforms.view_vector.length();
+        let far = self
+            .shadow_far
+            .unwrap_or(4.0 * camera_distance)
+            .max(camera_near * 2.0);
+
+        let mut near = camera_near;
+        for (cascade, split) in self
+            .cascade_splits(camera_near, far)
+            .into_iter()
+            .enumerate()
+        {
+            self.cascade_view_proj[cascade] = self.fit_cascade(camera_uniforms, near, split);
+            near = split;
+        }
+
+        let mut uniforms = LightUniforms {
             direction: self.direction,
             _pad0: 0.0,
             color: self.color,
             intensity: self.intensity,
-            light_view_proj,
+            light_view_proj: self.cascade_view_proj[0],
+            cascade_view_proj: self.cascade_view_proj,
+            cascade_count: self.cascade_count,
+            shadow_map_size: self.shadow_map_size as f32,
+            _pad1: [0.0; 2],
         };
+        for (buffer, view_proj) in self.cascade_buffers.iter().zip(self.cascade_view_proj) {
+            uniforms.light_view_proj = view_proj;
+            queue.write_buffer(buffer, 0, bytemuck::cast_slice(&[uniforms]));
+        }
+        uniforms.light_view_proj = self.cascade_view_proj[0];
         queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+
+        let mut light_list = LightListUniforms {
+            fill_direction: self.fill_direction,
+            fill_intensity: self.fill_intensity,
+            ambient: self.ambient,
+            count: self.lights.len().min(MAX_LIGHTS) as u32,
+            environment_intensity: self.environment_intensity,
+            environment_mip_count: self.environment.specular_mip_count as f32,
+            ..Default::default()
+        };
+        for (target, light) in light_list.lights.iter_mut().zip(&self.lights) {
+            *target = light.uniforms();
+        }
+        queue.write_buffer(
+            &self.light_list_buffer,
+            0,
+            bytemuck::cast_slice(&[light_list]),
+        );
     }
 }
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    uniform_buffer: &wgpu::Buffer,
+    shadow_texture_view: &wgpu::TextureView,
+    shadow_sampler: &wgpu::Sampler,
+    light_list_buffer: &wgpu::Buffer,
+    environment: &Environment,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("light bind group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(shadow_texture_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Sampler(shadow_sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: light_list_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::TextureView(&environment.irradiance_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 5,
+                resource: wgpu::BindingResource::TextureView(&environment.specular_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 6,
+                resource: wgpu::BindingResource::Sampler(&environment.sampler),
+            },
+        ],
+    })
+}
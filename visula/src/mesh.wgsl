@@ -27,10 +27,12 @@ struct MeshGeometry {
     rotation: vec4<f32>,
     position: vec3<f32>,
     scale: vec3<f32>,
+    color: vec4<f32>,
 };
 
 struct MeshMaterial {
     color: vec4<f32>,
+    alpha: f32,
 };
 
 fn calculate_transform_matrix(
@@ -76,7 +78,7 @@ fn vs_main(
     out.normal = normal_matrix * normal;
     out.uv = uv;
     out.world_position = (transform_matrix * vec4<f32>(position, 1.0)).xyz;
-    out.vertex_color = color;
+    out.vertex_color = color * geometry.color;
     return out;
 }
 
@@ -95,7 +97,7 @@ fn fs_main(vertex: VertexOutput) -> FragmentOutput {
     var material: MeshMaterial;
 
     var output: FragmentOutput;
-    output.color = vec4<f32>(material.color.xyz, 1.0);
+    output.color = vec4<f32>(material.color.xyz, material.alpha);
     output.normal = vec4<f32>(_visula_normal, 0.0);
     return output;
 }
@@ -60,6 +60,7 @@ impl Painter {
             },
             &SphereMaterial {
                 color: Expression::InputColor.lit(),
+                ..Default::default()
             },
         )?;
 
@@ -76,6 +77,7 @@ impl Painter {
             },
             &LineMaterial {
                 color: Expression::InputColor.lit(),
+                ..Default::default()
             },
         )?;
 
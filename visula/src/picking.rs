@@ -0,0 +1,217 @@
+use glam::{Vec3, Vec4};
+
+use crate::camera::uniforms::CameraUniforms;
+
+/// The instance found under a screen position by [`crate::Application::pick`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PickResult {
+    /// Picking id of the pipeline that drew the instance, see
+    /// [`crate::QuadPipeline::picking_id`].
+    pub picking_id: u32,
+    /// Index of the instance in the pipeline's instance buffers.
+    pub instance_index: u32,
+    /// World-space position of the picked surface.
+    pub position: Vec3,
+    /// Distance from the camera to the picked surface along the view direction.
+    pub depth: f32,
+}
+
+/// Offscreen targets for the picking pass. Unlike the main render targets these are never
+/// multisampled, since integer ids cannot be resolved.
+pub struct Picker {
+    pub id_texture: wgpu::Texture,
+    pub id_view: wgpu::TextureView,
+    pub depth_texture: wgpu::Texture,
+    pub depth_view: wgpu::TextureView,
+    readback_buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+}
+
+// Texture-to-buffer copies need rows aligned to this, so the id and depth are stored at
+// separate aligned offsets of the readback buffer.
+const DEPTH_OFFSET: u64 = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as u64;
+
+impl Picker {
+    pub fn new(device: &wgpu::Device, width: u32, height: u32) -> Picker {
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let id_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("picking id texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: visula_core::PICKING_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let depth_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("picking depth texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Depth32Float,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("picking readback buffer"),
+            size: DEPTH_OFFSET * 2,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        Picker {
+            id_view: id_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            id_texture,
+            depth_view: depth_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            depth_texture,
+            readback_buffer,
+            width,
+            height,
+        }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Clears the id and depth targets before the pipelines render their picking passes.
+    pub fn clear(&self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("picking clear"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.id_view,
+                resolve_target: None,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+            multiview_mask: None,
+        });
+    }
+
+    /// Encodes a copy of the id and depth at `pixel` into the readback buffer.
+    pub fn copy_pixel(&self, encoder: &mut wgpu::CommandEncoder, pixel: [u32; 2]) {
+        let origin = wgpu::Origin3d {
+            x: pixel[0],
+            y: pixel[1],
+            z: 0,
+        };
+        let extent = wgpu::Extent3d {
+            width: 1,
+            height: 1,
+            depth_or_array_layers: 1,
+        };
+        for (texture, aspect, offset) in [
+            (&self.id_texture, wgpu::TextureAspect::All, 0),
+            (
+                &self.depth_texture,
+                wgpu::TextureAspect::DepthOnly,
+                DEPTH_OFFSET,
+            ),
+        ] {
+            encoder.copy_texture_to_buffer(
+                wgpu::TexelCopyTextureInfo {
+                    texture,
+                    mip_level: 0,
+                    origin,
+                    aspect,
+                },
+                wgpu::TexelCopyBufferInfo {
+                    buffer: &self.readback_buffer,
+                    layout: wgpu::TexelCopyBufferLayout {
+                        offset,
+                        bytes_per_row: None,
+                        rows_per_image: None,
+                    },
+                },
+                extent,
+            );
+        }
+    }
+
+    /// Maps the readback buffer after the copy from [`Picker::copy_pixel`] has been submitted
+    /// and converts the result to a [`PickResult`]. Returns `None` if nothing was drawn at
+    /// the pixel, or if the buffer could not be mapped.
+    pub fn read(
+        &self,
+        device: &wgpu::Device,
+        pixel: [u32; 2],
+        camera_uniforms: &CameraUniforms,
+    ) -> Option<PickResult> {
+        let slice = self.readback_buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        if let Err(e) = device.poll(wgpu::PollType::wait_indefinitely()) {
+            log::error!("device poll failed while picking: {e}");
+            return None;
+        }
+        match receiver.recv() {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => {
+                log::error!("failed to map picking buffer: {e}");
+                return None;
+            }
+            Err(_) => {
+                log::error!("picking buffer was not mapped after polling the device");
+                return None;
+            }
+        }
+        let (picking_id, instance_index, ndc_depth) = {
+            let data = slice.get_mapped_range();
+            let ids: [u32; 2] = bytemuck::pod_read_unaligned(&data[0..8]);
+            let depth_start = DEPTH_OFFSET as usize;
+            let depth: f32 = bytemuck::pod_read_unaligned(&data[depth_start..depth_start + 4]);
+            (ids[0], ids[1], depth)
+        };
+        self.readback_buffer.unmap();
+        if picking_id == 0 {
+            return None;
+        }
+
+        let ndc = Vec4::new(
+            2.0 * (pixel[0] as f32 + 0.5) / self.width as f32 - 1.0,
+            1.0 - 2.0 * (pixel[1] as f32 + 0.5) / self.height as f32,
+            ndc_depth,
+            1.0,
+        );
+        let world = camera_uniforms.inverse_view_projection_matrix * ndc;
+        let position = world.truncate() / world.w;
+        let depth =
+            (position - camera_uniforms.position).dot(camera_uniforms.view_vector.normalize());
+        Some(PickResult {
+            picking_id,
+            instance_index,
+            position,
+            depth,
+        })
+    }
+}
+
+impl std::fmt::Debug for Picker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Picker")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .finish_non_exhaustive()
+    }
+}
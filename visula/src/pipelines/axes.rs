@@ -0,0 +1,324 @@
+use crate::camera::controller::CameraController;
+use crate::rendering_descriptor::RenderingDescriptor;
+use crate::simulation::RenderData;
+use crate::{
+    InstanceBuffer, InstanceDeviceExt, LabelGeometry, LabelOptions, Labels, LineGeometry,
+    LineMaterial, Lines, Renderable,
+};
+use bytemuck::{Pod, Zeroable};
+use glam::Vec3;
+use visula_derive::Instance;
+
+/// Upper limit on the number of major ticks along each axis, however close the camera is.
+const MAX_TICK_COUNT: usize = 100;
+/// Sizes relative to the height of the view at the distance of the camera center.
+const AXIS_WIDTH: f32 = 0.003;
+const GRID_WIDTH: f32 = 0.0015;
+const MINOR_GRID_WIDTH: f32 = 0.0008;
+const TICK_LENGTH: f32 = 0.015;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Instance, Pod, Zeroable, Default)]
+struct AxisLineData {
+    start: Vec3,
+    end: Vec3,
+    color: Vec3,
+    width: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Instance, Pod, Zeroable, Default)]
+struct TickLabelData {
+    position: Vec3,
+}
+
+/// A "nice" distance between ticks that divides `range` into roughly `count` intervals.
+///
+/// The step is 1, 2 or 5 times a power of ten, so that tick labels stay short.
+pub fn nice_step(range: f32, count: usize) -> f32 {
+    if range <= 0.0 || !range.is_finite() || count == 0 {
+        return 1.0;
+    }
+    let raw = range / count as f32;
+    let magnitude = 10f32.powf(raw.log10().floor());
+    let residual = raw / magnitude;
+    let nice = if residual < 1.5 {
+        1.0
+    } else if residual < 3.0 {
+        2.0
+    } else if residual < 7.0 {
+        5.0
+    } else {
+        10.0
+    };
+    nice * magnitude
+}
+
+/// The multiples of `step` in `[min, max]`.
+fn ticks(min: f32, max: f32, step: f32) -> impl Iterator<Item = f32> {
+    let first = (min / step - 1e-4).ceil() as i64;
+    let last = (max / step + 1e-4).floor() as i64;
+    (first..=last).map(move |index| index as f32 * step)
+}
+
+fn format_tick(value: f32, step: f32) -> String {
+    let decimals = (-step.log10().floor()).max(0.0) as usize;
+    // Avoid labels such as "-0.0" for values that are zero up to rounding.
+    let value = if value.abs() < 1e-3 * step {
+        0.0
+    } else {
+        value
+    };
+    format!("{value:.decimals$}")
+}
+
+/// Axes around a box of data, with grid lines on the back planes and numeric tick labels.
+///
+/// The box is drawn with its edges, and grid lines are drawn at the major and minor ticks on
+/// the planes through [`Axes3D::min`]. The ticks are placed at [`nice_step`] intervals that
+/// become finer as the camera moves closer, and the line widths follow the camera distance so
+/// they keep a similar thickness on screen. Call [`Axes3D::update`] when the camera or the
+/// settings change.
+pub struct Axes3D {
+    /// Corner of the box with the smallest coordinates.
+    pub min: Vec3,
+    /// Corner of the box with the largest coordinates.
+    pub max: Vec3,
+    /// Approximate number of major ticks along an axis that spans the view.
+    pub tick_count: usize,
+    /// Number of intervals between minor grid lines in each major interval. Zero and one
+    /// disable the minor grid.
+    pub minor_divisions: usize,
+    /// Draw grid lines on the back planes of the box.
+    pub grid: bool,
+    pub color: Vec3,
+    pub grid_color: Vec3,
+    pub minor_grid_color: Vec3,
+    lines: Lines,
+    line_buffer: InstanceBuffer<AxisLineData>,
+    labels: Labels,
+    label_buffer: InstanceBuffer<TickLabelData>,
+    /// The ticks and bounds the labels were last built for.
+    label_key: Option<([f32; 3], Vec3, Vec3)>,
+}
+
+impl Axes3D {
+    pub fn new(
+        rendering_descriptor: &RenderingDescriptor,
+        font_data: &[u8],
+        min: Vec3,
+        max: Vec3,
+    ) -> Result<Self, crate::error::Error> {
+        let device = rendering_descriptor.device;
+
+        let line_buffer: InstanceBuffer<AxisLineData> = device.create_instance_buffer();
+        let line = line_buffer.instance();
+        let lines = Lines::new(
+            rendering_descriptor,
+            &LineGeometry {
+                start: line.start,
+                end: line.end,
+                width: line.width,
+                color: line.color,
+            },
+            &LineMaterial::default(),
+        )?;
+
+        let label_buffer: InstanceBuffer<TickLabelData> = device.create_instance_buffer();
+        let label = label_buffer.instance();
+        let labels = Labels::new(
+            rendering_descriptor,
+            &LabelGeometry {
+                position: label.position,
+                size: 14.0.into(),
+                color: Vec3::new(0.9, 0.9, 0.9).into(),
+                outline_color: Vec3::new(0.1, 0.1, 0.1).into(),
+                outline_width: 0.1.into(),
+                ..Default::default()
+            },
+            font_data,
+            &LabelOptions::default(),
+        )?;
+
+        Ok(Axes3D {
+            min,
+            max,
+            tick_count: 5,
+            minor_divisions: 5,
+            grid: true,
+            color: Vec3::new(0.8, 0.8, 0.8),
+            grid_color: Vec3::new(0.45, 0.45, 0.45),
+            minor_grid_color: Vec3::new(0.25, 0.25, 0.25),
+            lines,
+            line_buffer,
+            labels,
+            label_buffer,
+            label_key: None,
+        })
+    }
+
+    /// The major tick steps along each axis when seeing the box from `distance`.
+    pub fn steps(&self, distance: f32) -> [f32; 3] {
+        let view_height = 2.0 * distance * (CameraController::FIELD_OF_VIEW / 2.0).tan();
+        let range = self.max - self.min;
+        std::array::from_fn(|axis| {
+            let view_range = range[axis].min(view_height);
+            nice_step(view_range, self.tick_count).max(nice_step(range[axis], MAX_TICK_COUNT))
+        })
+    }
+
+    /// Rebuilds the lines for the current camera distance, and the tick labels if the ticks
+    /// have changed.
+    pub fn update(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        camera_controller: &CameraController,
+    ) {
+        let distance = camera_controller.current_transform.distance;
+        let view_height = 2.0 * distance * (CameraController::FIELD_OF_VIEW / 2.0).tan();
+        let steps = self.steps(distance);
+        let (min, max) = (self.min, self.max);
+        let tick_length = TICK_LENGTH * view_height;
+
+        let mut line_data = Vec::new();
+        let mut push_line = |start: Vec3, end: Vec3, color: Vec3, width: f32| {
+            line_data.push(AxisLineData {
+                start,
+                end,
+                color,
+                width: width * view_height,
+            });
+        };
+
+        // The twelve edges of the box, four along each axis.
+        for axis in 0..3 {
+            let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+            for corner in 0..4 {
+                let mut start = min;
+                if corner & 1 != 0 {
+                    start[u] = max[u];
+                }
+                if corner & 2 != 0 {
+                    start[v] = max[v];
+                }
+                let mut end = start;
+                end[axis] = max[axis];
+                push_line(start, end, self.color, AXIS_WIDTH);
+            }
+        }
+
+        // Grid lines across the back plane spanned by `axis` and each other axis, at the
+        // major and minor ticks along `axis`.
+        if self.grid {
+            for axis in 0..3 {
+                let step = steps[axis];
+                let minor_step = step / self.minor_divisions.max(1) as f32;
+                for value in ticks(min[axis], max[axis], minor_step) {
+                    let major = (value / step).round() * step;
+                    let is_major = (value - major).abs() < 1e-3 * minor_step;
+                    let (color, width) = if is_major {
+                        (self.grid_color, GRID_WIDTH)
+                    } else {
+                        (self.minor_grid_color, MINOR_GRID_WIDTH)
+                    };
+                    for other in (0..3).filter(|&other| other != axis) {
+                        let mut start = min;
+                        start[axis] = value;
+                        let mut end = start;
+                        end[other] = max[other];
+                        push_line(start, end, color, width);
+                    }
+                }
+            }
+        }
+
+        // Tick marks along one edge for each axis, pointing away from the box.
+        let edges = tick_edges(min, max);
+        for (axis, &(origin, direction)) in edges.iter().enumerate() {
+            for value in ticks(min[axis], max[axis], steps[axis]) {
+                let mut start = origin;
+                start[axis] = value;
+                push_line(
+                    start,
+                    start + direction * tick_length,
+                    self.color,
+                    AXIS_WIDTH,
+                );
+            }
+        }
+
+        self.line_buffer.update(device, queue, &line_data);
+
+        let label_key = (steps, min, max);
+        let label_offset = 3.0 * tick_length;
+        let mut label_data = Vec::new();
+        let mut texts = Vec::new();
+        for (axis, &(origin, direction)) in edges.iter().enumerate() {
+            for value in ticks(min[axis], max[axis], steps[axis]) {
+                let mut position = origin;
+                position[axis] = value;
+                label_data.push(TickLabelData {
+                    position: position + direction * label_offset,
+                });
+                texts.push(format_tick(value, steps[axis]));
+            }
+        }
+        // The positions follow the camera distance, while the texts only change with the
+        // ticks.
+        self.label_buffer.update(device, queue, &label_data);
+        if self.label_key != Some(label_key) {
+            self.labels.update(queue, &texts);
+            self.label_key = Some(label_key);
+        }
+    }
+}
+
+/// The edge that carries the ticks of each axis, and the direction the ticks point in.
+fn tick_edges(min: Vec3, max: Vec3) -> [(Vec3, Vec3); 3] {
+    [
+        (Vec3::new(min.x, min.y, max.z), Vec3::Z),
+        (Vec3::new(min.x, min.y, max.z), -Vec3::X),
+        (Vec3::new(max.x, min.y, min.z), Vec3::X),
+    ]
+}
+
+impl Renderable for Axes3D {
+    fn render(&self, render_data: &mut RenderData) {
+        self.lines.render(render_data);
+        self.labels.render(render_data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nice_step() {
+        assert_eq!(nice_step(10.0, 5), 2.0);
+        assert_eq!(nice_step(0.73, 5), 0.1);
+        assert_eq!(nice_step(300.0, 4), 100.0);
+        assert_eq!(nice_step(0.0, 5), 1.0);
+        assert_eq!(nice_step(-1.0, 5), 1.0);
+        assert_eq!(nice_step(f32::NAN, 5), 1.0);
+        assert_eq!(nice_step(f32::INFINITY, 5), 1.0);
+        assert_eq!(nice_step(10.0, 0), 1.0);
+    }
+
+    #[test]
+    fn test_ticks() {
+        assert_eq!(
+            ticks(-1.0, 1.0, 0.5).collect::<Vec<_>>(),
+            [-1.0, -0.5, 0.0, 0.5, 1.0]
+        );
+        assert_eq!(ticks(0.1, 0.9, 0.5).collect::<Vec<_>>(), [0.5]);
+    }
+
+    #[test]
+    fn test_format_tick() {
+        assert_eq!(format_tick(-1e-9, 0.1), "0.0");
+        assert_eq!(format_tick(2.5, 0.5), "2.5");
+        assert_eq!(format_tick(200.0, 50.0), "200");
+    }
+}
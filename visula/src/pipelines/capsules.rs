@@ -0,0 +1,131 @@
+use crate::pipelines::quad::{QuadPipeline, QuadPipelineDescriptor};
+use crate::rendering_descriptor::RenderingDescriptor;
+use crate::simulation::{PickingRenderData, RenderData, ShadowRenderData, TransparentRenderData};
+use crate::Renderable;
+use bytemuck::{Pod, Zeroable};
+use glam::Vec3;
+use std::mem::size_of;
+use visula_core::Expression;
+use visula_derive::Delegate;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Vertex {
+    position: [f32; 3],
+}
+
+unsafe impl Pod for Vertex {}
+unsafe impl Zeroable for Vertex {}
+
+fn create_box_vertices() -> (Vec<Vertex>, Vec<u16>) {
+    let v = |x: f32, y: f32, z: f32| Vertex {
+        position: [x, y, z],
+    };
+    let vertex_data = vec![
+        v(-1.0, -1.0, -1.0),
+        v(1.0, -1.0, -1.0),
+        v(1.0, 1.0, -1.0),
+        v(-1.0, 1.0, -1.0),
+        v(-1.0, -1.0, 1.0),
+        v(1.0, -1.0, 1.0),
+        v(1.0, 1.0, 1.0),
+        v(-1.0, 1.0, 1.0),
+    ];
+    #[rustfmt::skip]
+    let index_data: Vec<u16> = vec![
+        0, 1, 2, 2, 3, 0,
+        1, 5, 6, 6, 2, 1,
+        5, 4, 7, 7, 6, 5,
+        4, 0, 3, 3, 7, 4,
+        3, 2, 6, 6, 7, 3,
+        4, 5, 1, 1, 0, 4,
+    ];
+    (vertex_data, index_data)
+}
+
+pub struct Capsules(QuadPipeline);
+
+#[derive(Delegate)]
+pub struct CapsuleGeometry {
+    /// Center of the hemisphere at the start.
+    pub start: Expression,
+    /// Center of the hemisphere at the end.
+    pub end: Expression,
+    pub radius: Expression,
+    pub color: Expression,
+}
+
+#[derive(Delegate)]
+pub struct CapsuleMaterial {
+    pub color: Expression,
+    /// Opacity in `[0, 1]`. Anything but the default `1.0` draws the capsules with
+    /// order-independent transparency.
+    pub alpha: Expression,
+}
+
+impl Default for CapsuleGeometry {
+    fn default() -> Self {
+        CapsuleGeometry {
+            start: Vec3::ZERO.into(),
+            end: Vec3::X.into(),
+            radius: 0.1.into(),
+            color: Vec3::ONE.into(),
+        }
+    }
+}
+
+impl Default for CapsuleMaterial {
+    fn default() -> Self {
+        CapsuleMaterial {
+            color: Expression::InputColor.lit(),
+            alpha: 1.0.into(),
+        }
+    }
+}
+
+impl Capsules {
+    pub fn new(
+        rendering_descriptor: &RenderingDescriptor,
+        geometry: &CapsuleGeometry,
+        material: &CapsuleMaterial,
+    ) -> Result<Self, visula_core::ShaderError> {
+        let (vertex_data, index_data) = create_box_vertices();
+        Ok(Capsules(QuadPipeline::new(
+            rendering_descriptor,
+            &QuadPipelineDescriptor {
+                label: "capsules",
+                shader_source: include_str!("../shaders/capsule.wgsl"),
+                shader_variable_name: "capsule_geometry",
+                fragment_shader_variable_name: Some("capsule_material"),
+                shadow_shader_source: Some(include_str!("../shaders/capsule_shadow.wgsl")),
+                transparent: !material.alpha.is_one(),
+                vertex_data: bytemuck::cast_slice(&vertex_data),
+                vertex_stride: size_of::<Vertex>(),
+                vertex_format: wgpu::VertexFormat::Float32x3,
+                index_data: bytemuck::cast_slice(&index_data),
+                index_format: wgpu::IndexFormat::Uint16,
+            },
+            geometry,
+            Some(material),
+        )?))
+    }
+
+    pub fn picking_id(&self) -> u32 {
+        self.0.picking_id()
+    }
+}
+
+impl Renderable for Capsules {
+    fn render(&self, render_data: &mut RenderData) {
+        self.0.render(render_data);
+    }
+    fn render_transparent(&self, transparent_data: &mut TransparentRenderData) {
+        self.0.render_transparent(transparent_data);
+    }
+    fn render_shadow(&self, shadow_data: &mut ShadowRenderData) {
+        self.0.render_shadow(shadow_data);
+    }
+    fn render_picking(&self, picking_data: &mut PickingRenderData) {
+        self.0.render_picking(picking_data);
+    }
+}
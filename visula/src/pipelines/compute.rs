@@ -0,0 +1,158 @@
+use naga::{back::wgsl::WriterFlags, valid::ValidationFlags};
+use visula_core::{BindingBuilder, Delegate};
+
+pub struct ComputePipelineDescriptor<'a> {
+    pub label: &'a str,
+    pub shader_source: &'a str,
+    pub shader_variable_name: &'a str,
+}
+
+/// Runs a WGSL compute kernel once per instance of the instance buffers used by its delegate.
+///
+/// The kernel has a `cs_main` entry point with a local variable named
+/// `shader_variable_name`, which is filled from the delegate before the kernel runs. Fields
+/// of the delegate that are plain instance fields are written back when the kernel returns,
+/// so the instance buffers can be updated on the GPU and rendered directly by other pipelines.
+pub struct ComputePipeline {
+    compute_pipeline: wgpu::ComputePipeline,
+    storage_bind_group_layout: Option<wgpu::BindGroupLayout>,
+    workgroup_size: u32,
+    label: String,
+    binding_builder: BindingBuilder,
+}
+
+impl ComputePipeline {
+    pub fn new(
+        device: &wgpu::Device,
+        descriptor: &ComputePipelineDescriptor,
+        delegate: &dyn Delegate,
+    ) -> Result<Self, visula_core::ShaderError> {
+        let mut module = naga::front::wgsl::parse_str(descriptor.shader_source)?;
+        let mut binding_builder = BindingBuilder::new(&module, "cs_main", 0)?;
+
+        delegate.inject_compute(
+            descriptor.shader_variable_name,
+            &mut module,
+            &mut binding_builder,
+        )?;
+
+        let workgroup_size = module.entry_points[binding_builder.entry_point_index]
+            .workgroup_size
+            .iter()
+            .product();
+
+        log::debug!("Validating {} shader", descriptor.label);
+        let info =
+            naga::valid::Validator::new(ValidationFlags::empty(), naga::valid::Capabilities::all())
+                .validate(&module)
+                .map_err(Box::new)?;
+        let output_str = naga::back::wgsl::write_string(&module, &info, WriterFlags::all())?;
+        log::debug!("Resulting {} shader code:\n{output_str}", descriptor.label);
+
+        let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(&format!("{} shader", descriptor.label)),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(&output_str)),
+        });
+
+        let storage_bind_group_layout = binding_builder.storage_bind_group_layout(device);
+
+        let bind_group_layouts =
+            binding_builder.bind_group_layouts(&[], storage_bind_group_layout.as_ref());
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(&format!("{} pipeline layout", descriptor.label)),
+            bind_group_layouts: &bind_group_layouts
+                .iter()
+                .map(Option::as_ref)
+                .collect::<Vec<_>>(),
+            immediate_size: 0,
+        });
+
+        let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some(&format!("{} compute pipeline", descriptor.label)),
+            layout: Some(&pipeline_layout),
+            module: &shader_module,
+            entry_point: Some("cs_main"),
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+            cache: None,
+        });
+
+        Ok(ComputePipeline {
+            compute_pipeline,
+            storage_bind_group_layout,
+            workgroup_size,
+            label: descriptor.label.to_string(),
+            binding_builder,
+        })
+    }
+
+    /// The number of instances to run the kernel for, or `None` if the instance buffers are
+    /// empty or disagree on their length.
+    fn instance_count(&self) -> Option<usize> {
+        let mut count = None;
+        for binding in self.binding_builder.storage_buffers.values() {
+            let other = binding.inner.borrow().count;
+            if other == 0 || count.is_some_and(|count| count != other) {
+                log::debug!("Empty or mismatched {} buffer detected.", self.label);
+                return None;
+            }
+            count = Some(other);
+        }
+        count
+    }
+
+    /// Encodes a compute pass that runs the kernel once for every instance.
+    ///
+    /// The storage bind group is recreated on every dispatch, since updating an instance
+    /// buffer with a new length replaces its `wgpu::Buffer`.
+    pub fn dispatch(&self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder) {
+        let Some(instance_count) = self.instance_count() else {
+            return;
+        };
+
+        let storage_bind_group = self
+            .binding_builder
+            .storage_group
+            .zip(self.storage_bind_group_layout.as_ref())
+            .map(|(group, layout)| {
+                (
+                    group,
+                    self.binding_builder
+                        .storage_bind_group(device, layout, instance_count),
+                )
+            });
+        let uniform_bind_groups: Vec<(u32, wgpu::BindGroup)> = self
+            .binding_builder
+            .uniforms
+            .values()
+            .map(|binding| (binding.group, binding.inner.borrow().bind_group.clone()))
+            .collect();
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some(&self.label),
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(&self.compute_pipeline);
+        for (group, bind_group) in storage_bind_group.iter().chain(&uniform_bind_groups) {
+            log::trace!("Setting compute bind group {group}");
+            compute_pass.set_bind_group(*group, bind_group, &[]);
+        }
+        let workgroup_count = (instance_count as u32).div_ceil(self.workgroup_size);
+        let (x, y) = split_workgroups(
+            workgroup_count,
+            device.limits().max_compute_workgroups_per_dimension,
+        );
+        compute_pass.dispatch_workgroups(x, y, 1);
+    }
+}
+
+/// Spreads `count` workgroups over x and y when there are more than `max` per dimension. The
+/// injected storage index is linear in the dispatched grid, so the extra invocations in the
+/// last row are discarded by the bounds check of the kernel.
+fn split_workgroups(count: u32, max: u32) -> (u32, u32) {
+    if count <= max {
+        return (count, 1);
+    }
+    let y = count.div_ceil(max);
+    (count.div_ceil(y), y)
+}
@@ -1,6 +1,6 @@
 use crate::pipelines::quad::{QuadPipeline, QuadPipelineDescriptor};
 use crate::rendering_descriptor::RenderingDescriptor;
-use crate::simulation::{RenderData, ShadowRenderData};
+use crate::simulation::{PickingRenderData, RenderData, ShadowRenderData, TransparentRenderData};
 use crate::Renderable;
 use bytemuck::{Pod, Zeroable};
 use glam::Vec3;
@@ -57,6 +57,9 @@ pub struct CylinderGeometry {
 #[derive(Delegate)]
 pub struct CylinderMaterial {
     pub color: Expression,
+    /// Opacity in `[0, 1]`. Anything but the default `1.0` draws the cylinders with
+    /// order-independent transparency.
+    pub alpha: Expression,
 }
 
 impl Default for CylinderGeometry {
@@ -75,6 +78,7 @@ impl Default for CylinderMaterial {
     fn default() -> Self {
         CylinderMaterial {
             color: Expression::InputColor.lit(),
+            alpha: 1.0.into(),
         }
     }
 }
@@ -94,6 +98,7 @@ impl Cylinders {
                 shader_variable_name: "cylinder_geometry",
                 fragment_shader_variable_name: Some("cylinder_material"),
                 shadow_shader_source: Some(include_str!("../shaders/cylinder_shadow.wgsl")),
+                transparent: !material.alpha.is_one(),
                 vertex_data: bytemuck::cast_slice(&vertex_data),
                 vertex_stride: size_of::<Vertex>(),
                 vertex_format: wgpu::VertexFormat::Float32x3,
@@ -104,13 +109,23 @@ impl Cylinders {
             Some(material),
         )?))
     }
+
+    pub fn picking_id(&self) -> u32 {
+        self.0.picking_id()
+    }
 }
 
 impl Renderable for Cylinders {
     fn render(&self, render_data: &mut RenderData) {
         self.0.render(render_data);
     }
+    fn render_transparent(&self, transparent_data: &mut TransparentRenderData) {
+        self.0.render_transparent(transparent_data);
+    }
     fn render_shadow(&self, shadow_data: &mut ShadowRenderData) {
         self.0.render_shadow(shadow_data);
     }
+    fn render_picking(&self, picking_data: &mut PickingRenderData) {
+        self.0.render_picking(picking_data);
+    }
 }
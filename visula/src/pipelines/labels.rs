@@ -0,0 +1,500 @@
+use crate::glyph_atlas::{AtlasGlyph, GlyphAtlas, ATLAS_SIZE, SPREAD};
+use crate::rendering_descriptor::RenderingDescriptor;
+use crate::simulation::RenderData;
+use crate::{DefaultRenderPassDescriptor, Renderable};
+use bytemuck::{Pod, Zeroable};
+use glam::{Vec2, Vec3};
+use itertools::Itertools;
+use naga::{back::wgsl::WriterFlags, valid::ValidationFlags};
+use std::cell::Ref;
+use visula_core::{BindingBuilder, Delegate as _, Expression, InstanceBinding};
+use visula_derive::Delegate;
+use wgpu::util::DeviceExt;
+use wgpu::{BindGroupLayout, PipelineCompilationOptions};
+
+#[derive(Delegate)]
+pub struct LabelGeometry {
+    /// The point the label is centered on.
+    pub position: Expression,
+    /// Height of the em square, in pixels or world units depending on [`LabelScale`].
+    pub size: Expression,
+    /// Offset of the label from its position, in em.
+    pub offset: Expression,
+    pub color: Expression,
+    pub outline_color: Expression,
+    /// Width of the outline around the glyphs in em, at most 0.15.
+    pub outline_width: Expression,
+}
+
+impl Default for LabelGeometry {
+    fn default() -> Self {
+        LabelGeometry {
+            position: Vec3::ZERO.into(),
+            size: 16.0.into(),
+            offset: Vec2::ZERO.into(),
+            color: Vec3::ONE.into(),
+            outline_color: Vec3::ZERO.into(),
+            outline_width: 0.0.into(),
+        }
+    }
+}
+
+/// How the size of a [`LabelGeometry`] is measured. Labels face the camera either way.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LabelScale {
+    /// The size is given in pixels, so labels keep their size on screen at any distance.
+    #[default]
+    Screen,
+    /// The size is given in world units, so labels shrink with distance like other geometry.
+    World,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LabelOptions {
+    pub scale: LabelScale,
+    /// Hide labels behind opaque geometry drawn before them. The whole label is tested at the
+    /// depth of its position, so labels at the center of an object need an offset to be seen.
+    pub depth_test: bool,
+}
+
+impl Default for LabelOptions {
+    fn default() -> Self {
+        LabelOptions {
+            scale: LabelScale::Screen,
+            depth_test: true,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct LabelParameters {
+    spread: f32,
+    screen_space: u32,
+    _padding: [u32; 2],
+}
+
+/// Text drawn at the instances of the geometry, facing the camera.
+///
+/// The glyphs are drawn from a signed distance field atlas built from the given font, see
+/// [`GlyphAtlas`], which keeps them sharp at any size and allows outlines and halos around
+/// them. The texts are given with [`Labels::update`], one for each instance, and are drawn
+/// blended with what was drawn before them, so labels should be drawn after other geometry.
+pub struct Labels {
+    render_pipeline: wgpu::RenderPipeline,
+    atlas: GlyphAtlas,
+    atlas_texture: wgpu::Texture,
+    atlas_view: wgpu::TextureView,
+    atlas_sampler: wgpu::Sampler,
+    parameters_buffer: wgpu::Buffer,
+    label_bind_group_layout: BindGroupLayout,
+    label_bind_group: wgpu::BindGroup,
+    label_count: usize,
+    max_glyph_count: usize,
+    vertex_storage_layout: Option<BindGroupLayout>,
+    device: wgpu::Device,
+    vertex_binding_builder: BindingBuilder,
+}
+
+impl Labels {
+    pub fn new(
+        rendering_descriptor: &RenderingDescriptor,
+        geometry: &LabelGeometry,
+        font_data: &[u8],
+        options: &LabelOptions,
+    ) -> Result<Self, crate::error::Error> {
+        let &RenderingDescriptor {
+            device,
+            camera,
+            format,
+            sample_count,
+            ..
+        } = rendering_descriptor;
+
+        let atlas = GlyphAtlas::new(font_data)?;
+
+        let mut module = naga::front::wgsl::parse_str(include_str!("../shaders/labels.wgsl"))
+            .map_err(visula_core::ShaderError::from)?;
+        let mut vertex_binding_builder = BindingBuilder::new(&module, "vs_main", 0)?;
+        geometry.inject("label_geometry", &mut module, &mut vertex_binding_builder)?;
+
+        log::debug!("Validating labels shader");
+        let info =
+            naga::valid::Validator::new(ValidationFlags::empty(), naga::valid::Capabilities::all())
+                .validate(&module)
+                .map_err(|error| visula_core::ShaderError::from(Box::new(error)))?;
+        let output_str = naga::back::wgsl::write_string(&module, &info, WriterFlags::all())
+            .map_err(visula_core::ShaderError::from)?;
+        log::debug!("Resulting labels shader code:\n{output_str}");
+
+        let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("labels shader"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(&output_str)),
+        });
+
+        let atlas_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("labels atlas texture"),
+            size: wgpu::Extent3d {
+                width: ATLAS_SIZE,
+                height: ATLAS_SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let atlas_view = atlas_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let atlas_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("labels atlas sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let parameters_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("labels parameters buffer"),
+            contents: bytemuck::bytes_of(&LabelParameters {
+                spread: SPREAD,
+                screen_space: (options.scale == LabelScale::Screen) as u32,
+                _padding: [0; 2],
+            }),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let storage_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::VERTEX,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let label_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("labels bind group layout"),
+                entries: &[
+                    storage_entry(0),
+                    storage_entry(1),
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 4,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+
+        let vertex_storage_layout = vertex_binding_builder.storage_bind_group_layout(device);
+        let bind_group_layouts = vertex_binding_builder.bind_group_layouts(
+            &[&camera.bind_group_layout, &label_bind_group_layout],
+            vertex_storage_layout.as_ref(),
+        );
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("labels pipeline layout"),
+            bind_group_layouts: &bind_group_layouts.iter().map(Option::as_ref).collect_vec(),
+            immediate_size: 0,
+        });
+
+        let sorted_bindings = vertex_binding_builder.sorted_bindings();
+        let buffers = sorted_bindings
+            .iter()
+            .map(|binding| binding.layout.build())
+            .collect_vec();
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("labels render pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader_module,
+                entry_point: Some("vs_main"),
+                buffers: &buffers,
+                compilation_options: PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader_module,
+                entry_point: Some("fs_main"),
+                targets: &[
+                    Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    }),
+                    Some(wgpu::ColorTargetState {
+                        format: wgpu::TextureFormat::Rgba16Float,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    }),
+                ],
+                compilation_options: PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                ..Default::default()
+            },
+            // Labels are blended, so they do not write depth and hide what is drawn after them.
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: Some(false),
+                depth_compare: Some(if options.depth_test {
+                    wgpu::CompareFunction::LessEqual
+                } else {
+                    wgpu::CompareFunction::Always
+                }),
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview_mask: None,
+            cache: None,
+        });
+
+        let label_bind_group = create_label_bind_group(
+            device,
+            &label_bind_group_layout,
+            &[AtlasGlyph::default()],
+            &[[0, 0]],
+            &parameters_buffer,
+            &atlas_view,
+            &atlas_sampler,
+        );
+
+        Ok(Labels {
+            render_pipeline,
+            atlas,
+            atlas_texture,
+            atlas_view,
+            atlas_sampler,
+            parameters_buffer,
+            label_bind_group_layout,
+            label_bind_group,
+            label_count: 0,
+            max_glyph_count: 0,
+            vertex_storage_layout,
+            device: device.clone(),
+            vertex_binding_builder,
+        })
+    }
+
+    /// Replaces the texts of all labels, with one text for each instance of the geometry.
+    /// Instances without a text are not drawn.
+    pub fn update<S: AsRef<str>>(&mut self, queue: &wgpu::Queue, texts: &[S]) {
+        let mut glyphs = Vec::new();
+        let mut ranges = Vec::with_capacity(texts.len());
+        for quads in self.atlas.layout(texts) {
+            ranges.push([glyphs.len() as u32, quads.len() as u32]);
+            glyphs.extend(quads);
+        }
+        self.label_count = texts.len();
+        self.max_glyph_count = ranges
+            .iter()
+            .map(|range| range[1] as usize)
+            .max()
+            .unwrap_or(0);
+
+        if self.atlas.take_changed() {
+            queue.write_texture(
+                self.atlas_texture.as_image_copy(),
+                self.atlas.pixels(),
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(ATLAS_SIZE),
+                    rows_per_image: Some(ATLAS_SIZE),
+                },
+                wgpu::Extent3d {
+                    width: ATLAS_SIZE,
+                    height: ATLAS_SIZE,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+
+        // Storage buffers cannot be empty.
+        if glyphs.is_empty() {
+            glyphs.push(AtlasGlyph::default());
+        }
+        if ranges.is_empty() {
+            ranges.push([0, 0]);
+        }
+        self.label_bind_group = create_label_bind_group(
+            &self.device,
+            &self.label_bind_group_layout,
+            &glyphs,
+            &ranges,
+            &self.parameters_buffer,
+            &self.atlas_view,
+            &self.atlas_sampler,
+        );
+    }
+
+    /// The number of labels to draw, or `None` if there are none or the instance buffers are
+    /// empty or disagree on their length.
+    fn instance_count(&self) -> Option<usize> {
+        if self.label_count == 0 || self.max_glyph_count == 0 {
+            return None;
+        }
+        let builder = &self.vertex_binding_builder;
+        let counts = builder
+            .instances
+            .values()
+            .map(|binding| binding.inner.borrow().count)
+            .chain(
+                builder
+                    .storage_buffers
+                    .values()
+                    .map(|binding| binding.inner.borrow().count),
+            )
+            .collect_vec();
+        match counts.first() {
+            None => Some(self.label_count),
+            Some(&count) if count > 0 && counts.iter().all(|&other| other == count) => {
+                Some(count.min(self.label_count))
+            }
+            Some(_) => {
+                log::debug!("Empty labels buffer detected. Aborting render.");
+                None
+            }
+        }
+    }
+}
+
+fn create_label_bind_group(
+    device: &wgpu::Device,
+    layout: &BindGroupLayout,
+    glyphs: &[AtlasGlyph],
+    ranges: &[[u32; 2]],
+    parameters_buffer: &wgpu::Buffer,
+    atlas_view: &wgpu::TextureView,
+    atlas_sampler: &wgpu::Sampler,
+) -> wgpu::BindGroup {
+    let glyph_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("labels glyph buffer"),
+        contents: bytemuck::cast_slice(glyphs),
+        usage: wgpu::BufferUsages::STORAGE,
+    });
+    let range_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("labels range buffer"),
+        contents: bytemuck::cast_slice(ranges),
+        usage: wgpu::BufferUsages::STORAGE,
+    });
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("labels bind group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: glyph_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: range_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: parameters_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::TextureView(atlas_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::Sampler(atlas_sampler),
+            },
+        ],
+    })
+}
+
+impl Renderable for Labels {
+    fn render(
+        &self,
+        RenderData {
+            encoder,
+            view,
+            multisampled_framebuffer,
+            depth_texture,
+            normal_msaa,
+            normal_resolve,
+            camera,
+            ..
+        }: &mut RenderData,
+    ) {
+        log::trace!("Rendering labels");
+        let Some(instance_count) = self.instance_count() else {
+            return;
+        };
+        let bindings: Vec<(&InstanceBinding, Ref<wgpu::Buffer>)> = self
+            .vertex_binding_builder
+            .instances
+            .values()
+            .map(|v| (v, Ref::map(v.inner.borrow(), |v| &v.buffer)))
+            .collect();
+        let mut bind_groups: Vec<(u32, wgpu::BindGroup)> = self
+            .vertex_binding_builder
+            .uniforms
+            .values()
+            .map(|v| (v.group, v.inner.borrow().bind_group.clone()))
+            .collect();
+        if let (Some(group), Some(layout)) = (
+            self.vertex_binding_builder.storage_group,
+            &self.vertex_storage_layout,
+        ) {
+            bind_groups.push((
+                group,
+                self.vertex_binding_builder.storage_bind_group(
+                    &self.device,
+                    layout,
+                    instance_count,
+                ),
+            ));
+        }
+
+        let default_render_pass = DefaultRenderPassDescriptor::new(
+            "labels",
+            view,
+            multisampled_framebuffer,
+            depth_texture,
+            normal_msaa,
+            normal_resolve,
+        );
+        let mut render_pass = encoder.begin_render_pass(&default_render_pass.build());
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &camera.bind_group, &[]);
+        render_pass.set_bind_group(1, &self.label_bind_group, &[]);
+        for (group, bind_group) in bind_groups.iter() {
+            render_pass.set_bind_group(*group, bind_group, &[]);
+        }
+        for (binding, buffer) in bindings.iter() {
+            render_pass.set_vertex_buffer(binding.slot, buffer.slice(..));
+        }
+        render_pass.draw(0..6 * self.max_glyph_count as u32, 0..instance_count as u32);
+    }
+}
@@ -1,22 +1,42 @@
 use std::cell::Ref;
 use std::mem::size_of;
+use std::sync::atomic::Ordering;
 
-use glam::{Quat, Vec3};
+use glam::{Quat, Vec3, Vec4};
 use naga::back::wgsl::WriterFlags;
 use naga::valid::ValidationFlags;
 use wgpu::util::DeviceExt;
 use wgpu::{BindGroupLayout, PipelineCompilationOptions};
 
+use crate::io::gltf::GltfMesh;
+use crate::light::DirectionalLight;
+use crate::pipelines::quad::NEXT_PICKING_ID;
+use crate::post_process::transparency::TransparencyPass;
 use crate::primitives::mesh_primitive::MeshVertexAttributes;
-use crate::{DefaultRenderPassDescriptor, RenderData, RenderingDescriptor};
+use crate::{
+    DefaultRenderPassDescriptor, PickingRenderData, RenderData, Renderable, RenderingDescriptor,
+    ShadowRenderData, TransparentRenderData, TransparentRenderPassDescriptor,
+};
 use visula_core::{BindingBuilder, Delegate as _, Expression, InstanceBinding};
 use visula_derive::Delegate;
 
+struct ShadowPipeline {
+    render_pipeline: wgpu::RenderPipeline,
+    storage_bind_group_layout: Option<BindGroupLayout>,
+    binding_builder: BindingBuilder,
+}
+
 pub struct MeshPipeline {
     pub render_pipeline: wgpu::RenderPipeline,
+    shadow_pipeline: ShadowPipeline,
+    vertex_storage_layout: Option<BindGroupLayout>,
+    picking_render_pipeline: wgpu::RenderPipeline,
+    picking_id: u32,
+    transparent: bool,
     pub vertex_buffer: wgpu::Buffer,
     pub index_buffer: wgpu::Buffer,
     pub vertex_count: usize,
+    device: wgpu::Device,
     vertex_binding_builder: BindingBuilder,
     fragment_binding_builder: BindingBuilder,
 }
@@ -26,11 +46,17 @@ pub struct MeshGeometry {
     pub rotation: Expression,
     pub position: Expression,
     pub scale: Expression,
+    /// Multiplied with the vertex colors, so that instances can be tinted individually. The
+    /// result is available to the material as [`Expression::InputColor`].
+    pub color: Expression,
 }
 
 #[derive(Delegate)]
 pub struct MeshMaterial {
     pub color: Expression,
+    /// Opacity in `[0, 1]`. Anything but the default `1.0` draws the mesh with
+    /// order-independent transparency.
+    pub alpha: Expression,
 }
 
 impl Default for MeshGeometry {
@@ -39,6 +65,7 @@ impl Default for MeshGeometry {
             rotation: Quat::IDENTITY.into(),
             position: Vec3::ZERO.into(),
             scale: Vec3::ONE.into(),
+            color: Vec4::ONE.into(),
         }
     }
 }
@@ -47,6 +74,7 @@ impl Default for MeshMaterial {
     fn default() -> Self {
         MeshMaterial {
             color: Expression::InputColor.lit(),
+            alpha: 1.0.into(),
         }
     }
 }
@@ -66,6 +94,7 @@ impl MeshPipeline {
         } = rendering_descriptor;
 
         let vertex_size = size_of::<MeshVertexAttributes>();
+        let transparent = !material.alpha.is_one();
 
         let shader_with_lighting = format!(
             "{}\n{}",
@@ -92,41 +121,54 @@ impl MeshPipeline {
                 .map_err(Box::new)?;
         let output_str = naga::back::wgsl::write_string(&module, &info, WriterFlags::all())?;
         log::debug!("Resulting mesh shader code:\n{output_str}");
+        let output_str = if transparent {
+            let transparency_module = visula_core::transparency_module(&module)?;
+            let transparency_info = naga::valid::Validator::new(
+                ValidationFlags::empty(),
+                naga::valid::Capabilities::all(),
+            )
+            .validate(&transparency_module)
+            .map_err(Box::new)?;
+            naga::back::wgsl::write_string(
+                &transparency_module,
+                &transparency_info,
+                WriterFlags::all(),
+            )?
+        } else {
+            output_str
+        };
 
         let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Mesh shader module"),
             source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(&output_str)),
         });
 
-        let vertex_uniform_bind_group_layouts: Vec<&BindGroupLayout> = vertex_binding_builder
-            .uniforms
-            .values()
-            .map(|binding| binding.bind_group_layout.as_ref())
-            .collect();
-
-        let fragment_texture_bind_group_layouts: Vec<BindGroupLayout> = fragment_binding_builder
-            .textures
-            .values()
-            .map(|binding| binding.inner.borrow().bind_group_layout.clone())
-            .collect();
+        let picking_id = NEXT_PICKING_ID.fetch_add(1, Ordering::Relaxed);
+        let picking_module = visula_core::picking_module(&module, picking_id)?;
+        let picking_info =
+            naga::valid::Validator::new(ValidationFlags::empty(), naga::valid::Capabilities::all())
+                .validate(&picking_module)
+                .map_err(Box::new)?;
+        let picking_str =
+            naga::back::wgsl::write_string(&picking_module, &picking_info, WriterFlags::all())?;
+        let picking_shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Mesh picking shader module"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(&picking_str)),
+        });
 
-        let bind_group_layouts = {
-            let mut layouts: Vec<Option<&wgpu::BindGroupLayout>> = vec![
-                Some(&camera.bind_group_layout),
-                Some(&light.bind_group_layout),
-            ];
-            for layout in &vertex_uniform_bind_group_layouts {
-                layouts.push(Some(layout));
-            }
-            for layout in &fragment_texture_bind_group_layouts {
-                layouts.push(Some(layout));
-            }
-            layouts
-        };
+        let vertex_storage_layout = vertex_binding_builder.storage_bind_group_layout(device);
+        let mut bind_group_layouts = vertex_binding_builder.bind_group_layouts(
+            &[&camera.bind_group_layout, &light.bind_group_layout],
+            vertex_storage_layout.as_ref(),
+        );
+        fragment_binding_builder.extend_bind_group_layouts(&mut bind_group_layouts, None);
 
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("Mesh pipeline layout"),
-            bind_group_layouts: &bind_group_layouts,
+            bind_group_layouts: &bind_group_layouts
+                .iter()
+                .map(Option::as_ref)
+                .collect::<Vec<_>>(),
             immediate_size: 0,
         });
 
@@ -141,9 +183,9 @@ impl MeshPipeline {
             ],
         };
 
-        let mut layouts = vertex_binding_builder
-            .instances
-            .values()
+        let sorted_bindings = vertex_binding_builder.sorted_bindings();
+        let mut layouts = sorted_bindings
+            .iter()
             .map(|binding| binding.layout.build())
             .collect();
 
@@ -153,6 +195,29 @@ impl MeshPipeline {
             buffers
         };
 
+        let primitive = wgpu::PrimitiveState {
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: None,
+            ..Default::default()
+        };
+
+        let color_targets = if transparent {
+            TransparencyPass::color_targets()
+        } else {
+            [
+                Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                }),
+                Some(wgpu::ColorTargetState {
+                    format: wgpu::TextureFormat::Rgba16Float,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                }),
+            ]
+        };
+
         let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some("Mesh pipeline"),
             layout: Some(&pipeline_layout),
@@ -165,28 +230,13 @@ impl MeshPipeline {
             fragment: Some(wgpu::FragmentState {
                 module: &shader_module,
                 entry_point: Some("fs_main"),
-                targets: &[
-                    Some(wgpu::ColorTargetState {
-                        format,
-                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    }),
-                    Some(wgpu::ColorTargetState {
-                        format: wgpu::TextureFormat::Rgba16Float,
-                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    }),
-                ],
+                targets: &color_targets,
                 compilation_options: PipelineCompilationOptions::default(),
             }),
-            primitive: wgpu::PrimitiveState {
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: None,
-                ..Default::default()
-            },
+            primitive,
             depth_stencil: Some(wgpu::DepthStencilState {
                 format: wgpu::TextureFormat::Depth32Float,
-                depth_write_enabled: Some(true),
+                depth_write_enabled: Some(!transparent),
                 depth_compare: Some(wgpu::CompareFunction::Less),
                 stencil: wgpu::StencilState::default(),
                 bias: wgpu::DepthBiasState::default(),
@@ -200,6 +250,41 @@ impl MeshPipeline {
             cache: None,
         });
 
+        let picking_render_pipeline =
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Mesh picking pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &picking_shader_module,
+                    entry_point: Some("vs_main"),
+                    buffers: &buffers,
+                    compilation_options: PipelineCompilationOptions::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &picking_shader_module,
+                    entry_point: Some("fs_main"),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: visula_core::PICKING_FORMAT,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: PipelineCompilationOptions::default(),
+                }),
+                primitive,
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: wgpu::TextureFormat::Depth32Float,
+                    depth_write_enabled: Some(true),
+                    depth_compare: Some(wgpu::CompareFunction::Less),
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview_mask: None,
+                cache: None,
+            });
+
+        let shadow_pipeline = ShadowPipeline::new(device, light, geometry, vertex_size)?;
+
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Mesh vertex buffer"),
             contents: bytemuck::cast_slice(&Vec::<MeshVertexAttributes>::new()),
@@ -214,9 +299,15 @@ impl MeshPipeline {
 
         Ok(MeshPipeline {
             render_pipeline,
+            shadow_pipeline,
+            vertex_storage_layout,
+            picking_render_pipeline,
+            picking_id,
+            transparent,
             vertex_buffer,
             index_buffer,
             vertex_count: 0,
+            device: device.clone(),
             vertex_binding_builder,
             fragment_binding_builder,
         })
@@ -241,66 +332,225 @@ impl MeshPipeline {
         self.vertex_count = indices.len();
     }
 
-    pub fn render(
-        &self,
-        RenderData {
-            encoder,
-            view,
-            multisampled_framebuffer,
-            depth_texture,
-            normal_msaa,
-            normal_resolve,
-            camera,
-            light,
-            ..
-        }: &mut RenderData,
-    ) {
-        log::debug!("Rendering meshes");
+    /// Draws the given glTF mesh, sharing its buffers. Together with instance buffers in the
+    /// geometry, this draws one copy of the mesh per instance.
+    pub fn set_gltf_mesh(&mut self, mesh: &GltfMesh) {
+        self.vertex_buffer = mesh.vertex_buffer.clone();
+        self.index_buffer = mesh.index_buffer.clone();
+        self.vertex_count = mesh.index_count;
+    }
+
+    /// The id written to the picking target for instances drawn by this pipeline, as returned
+    /// in [`crate::PickResult::picking_id`].
+    pub fn picking_id(&self) -> u32 {
+        self.picking_id
+    }
+
+    /// The number of instances to draw, or `None` if there is no mesh data or the instance
+    /// buffers are empty or disagree on their length.
+    fn instance_count(&self) -> Option<usize> {
         if self.vertex_count == 0 {
-            return;
+            return None;
         }
-
+        let builder = &self.vertex_binding_builder;
+        if builder.instances.is_empty() && builder.storage_buffers.is_empty() {
+            return Some(1);
+        }
+        let inners = builder
+            .instances
+            .values()
+            .map(|binding| &binding.inner)
+            .chain(
+                builder
+                    .storage_buffers
+                    .values()
+                    .map(|binding| &binding.inner),
+            );
         let mut count = None;
-        for binding in self.vertex_binding_builder.instances.values() {
-            let other = binding.inner.borrow().count;
-            if other == 0 {
-                count = None;
-                break;
-            }
-            count = match count {
-                None => Some(other),
-                Some(old) => {
-                    if other != old {
-                        None
-                    } else {
-                        Some(old)
-                    }
-                }
+        for inner in inners {
+            let other = inner.borrow().count;
+            if other == 0 || count.is_some_and(|count| count != other) {
+                log::debug!("Empty or mismatched mesh buffer detected. Aborting render.");
+                return None;
             }
+            count = Some(other);
         }
-        log::debug!("Mesh count {count:#?}");
+        count
+    }
 
-        let instances: Vec<(&InstanceBinding, Ref<wgpu::Buffer>)> = self
+    /// Binds the mesh, the instance buffers and the vertex and fragment bind groups that
+    /// follow the camera and light, using the same layout as the main and picking pipelines.
+    fn set_instance_bindings(&self, render_pass: &mut wgpu::RenderPass, instance_count: usize) {
+        let bindings: Vec<(&InstanceBinding, Ref<wgpu::Buffer>)> = self
             .vertex_binding_builder
             .instances
             .values()
             .map(|v| (v, Ref::map(v.inner.borrow(), |v| &v.buffer)))
             .collect();
-
-        let uniforms: Vec<wgpu::BindGroup> = self
+        let mut bind_groups: Vec<(u32, wgpu::BindGroup)> = self
             .vertex_binding_builder
             .uniforms
             .values()
-            .map(|v| v.inner.borrow().bind_group.clone())
+            .map(|v| (v.group, v.inner.borrow().bind_group.clone()))
             .collect();
+        if let (Some(group), Some(layout)) = (
+            self.vertex_binding_builder.storage_group,
+            &self.vertex_storage_layout,
+        ) {
+            bind_groups.push((
+                group,
+                self.vertex_binding_builder.storage_bind_group(
+                    &self.device,
+                    layout,
+                    instance_count,
+                ),
+            ));
+        }
+        bind_groups.extend(
+            self.fragment_binding_builder
+                .textures
+                .values()
+                .map(|v| (v.group, v.inner.borrow().bind_group.clone())),
+        );
+        bind_groups.extend(
+            self.fragment_binding_builder
+                .uniforms
+                .values()
+                .map(|v| (v.group, v.inner.borrow().bind_group.clone())),
+        );
 
-        let textures: Vec<wgpu::BindGroup> = self
-            .fragment_binding_builder
-            .textures
-            .values()
-            .map(|v| v.inner.borrow().bind_group.clone())
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        for (binding, buffer) in bindings.iter() {
+            let slot = binding.slot;
+            log::trace!("Setting vertex buffer {slot}");
+            render_pass.set_vertex_buffer(slot, buffer.slice(..));
+        }
+        for (group, bind_group) in bind_groups.iter() {
+            log::trace!("Setting bind group {group}");
+            render_pass.set_bind_group(*group, bind_group, &[]);
+        }
+    }
+}
+
+impl ShadowPipeline {
+    /// Builds the depth-only pipeline with the same geometry delegate as the main pass, so
+    /// that the shadow follows the injected rotation, position and scale.
+    fn new(
+        device: &wgpu::Device,
+        light: &DirectionalLight,
+        geometry: &MeshGeometry,
+        vertex_size: usize,
+    ) -> Result<Self, visula_core::ShaderError> {
+        let mut module = naga::front::wgsl::parse_str(include_str!("../shaders/mesh_shadow.wgsl"))?;
+        let mut binding_builder = BindingBuilder::new(&module, "vs_main", 1)?;
+        geometry.inject("geometry", &mut module, &mut binding_builder)?;
+
+        let info =
+            naga::valid::Validator::new(ValidationFlags::empty(), naga::valid::Capabilities::all())
+                .validate(&module)
+                .map_err(Box::new)?;
+        let output_str = naga::back::wgsl::write_string(&module, &info, WriterFlags::all())?;
+        log::debug!("Resulting mesh shadow shader code:\n{output_str}");
+
+        let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Mesh shadow shader module"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(&output_str)),
+        });
+
+        // Only the position is read, but the stride still covers the full vertex.
+        let vertex_buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: vertex_size as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0 => Float32x3],
+        };
+        let sorted_bindings = binding_builder.sorted_bindings();
+        let mut layouts = sorted_bindings
+            .iter()
+            .map(|binding| binding.layout.build())
             .collect();
+        let buffers = {
+            let mut buffers = vec![vertex_buffer_layout];
+            buffers.append(&mut layouts);
+            buffers
+        };
+
+        let storage_bind_group_layout = binding_builder.storage_bind_group_layout(device);
+        let bind_group_layouts = binding_builder.bind_group_layouts(
+            &[&light.shadow_bind_group_layout],
+            storage_bind_group_layout.as_ref(),
+        );
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Mesh shadow pipeline layout"),
+            bind_group_layouts: &bind_group_layouts
+                .iter()
+                .map(Option::as_ref)
+                .collect::<Vec<_>>(),
+            immediate_size: 0,
+        });
 
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Mesh shadow pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader_module,
+                entry_point: Some("vs_main"),
+                buffers: &buffers,
+                compilation_options: PipelineCompilationOptions::default(),
+            },
+            fragment: None,
+            primitive: wgpu::PrimitiveState {
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: Some(true),
+                depth_compare: Some(wgpu::CompareFunction::Less),
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState {
+                    constant: 2,
+                    slope_scale: 2.0,
+                    clamp: 0.0,
+                },
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview_mask: None,
+            cache: None,
+        });
+
+        Ok(ShadowPipeline {
+            render_pipeline,
+            storage_bind_group_layout,
+            binding_builder,
+        })
+    }
+}
+
+impl Renderable for MeshPipeline {
+    fn render(
+        &self,
+        RenderData {
+            encoder,
+            view,
+            multisampled_framebuffer,
+            depth_texture,
+            normal_msaa,
+            normal_resolve,
+            camera,
+            light,
+            ..
+        }: &mut RenderData,
+    ) {
+        if self.transparent {
+            return;
+        }
+        log::trace!("Rendering meshes");
+        let Some(instance_count) = self.instance_count() else {
+            return;
+        };
         let mut render_pass = encoder.begin_render_pass(
             &DefaultRenderPassDescriptor::new(
                 "meshes",
@@ -312,37 +562,144 @@ impl MeshPipeline {
             )
             .build(),
         );
-
         render_pass.set_bind_group(0, &camera.bind_group, &[]);
         render_pass.set_bind_group(1, &light.bind_group, &[]);
-
         render_pass.set_pipeline(&self.render_pipeline);
-        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        self.set_instance_bindings(&mut render_pass, instance_count);
+        log::trace!("Drawing {instance_count} meshes");
+        render_pass.draw_indexed(0..self.vertex_count as u32, 0, 0..instance_count as u32);
+    }
 
-        let mut instance_count = usize::from(count.is_none());
-        for (binding, buffer) in instances.iter() {
-            let slot = binding.slot;
-            log::debug!("Setting vertex buffer {slot}");
-            render_pass.set_vertex_buffer(slot, buffer.slice(..));
-            instance_count = instance_count.max(binding.inner.borrow().count);
+    fn render_transparent(
+        &self,
+        TransparentRenderData {
+            accumulation,
+            accumulation_resolve,
+            revealage,
+            revealage_resolve,
+            depth_texture,
+            encoder,
+            camera,
+            light,
+        }: &mut TransparentRenderData,
+    ) {
+        if !self.transparent {
+            return;
         }
+        log::trace!("Rendering transparent meshes");
+        let Some(instance_count) = self.instance_count() else {
+            return;
+        };
+        let mut render_pass = encoder.begin_render_pass(
+            &TransparentRenderPassDescriptor::new(
+                "transparent meshes",
+                accumulation,
+                *accumulation_resolve,
+                revealage,
+                *revealage_resolve,
+                depth_texture,
+            )
+            .build(),
+        );
+        render_pass.set_bind_group(0, &camera.bind_group, &[]);
+        render_pass.set_bind_group(1, &light.bind_group, &[]);
+        render_pass.set_pipeline(&self.render_pipeline);
+        self.set_instance_bindings(&mut render_pass, instance_count);
+        render_pass.draw_indexed(0..self.vertex_count as u32, 0, 0..instance_count as u32);
+    }
 
-        let mut current_slot = 2;
+    fn render_shadow(&self, shadow_data: &mut ShadowRenderData) {
+        let Some(instance_count) = self.instance_count() else {
+            return;
+        };
+        let shadow_pipeline = &self.shadow_pipeline;
+        let shadow_builder = &shadow_pipeline.binding_builder;
 
-        for bind_group in textures.iter() {
-            log::debug!("Setting texture bind group at slot {}", current_slot);
-            render_pass.set_bind_group(current_slot, bind_group, &[]);
-            current_slot += 1;
+        let bindings: Vec<(&InstanceBinding, Ref<wgpu::Buffer>)> = shadow_builder
+            .instances
+            .values()
+            .map(|v| (v, Ref::map(v.inner.borrow(), |v| &v.buffer)))
+            .collect();
+        let mut bind_groups: Vec<(u32, wgpu::BindGroup)> = shadow_builder
+            .uniforms
+            .values()
+            .map(|v| (v.group, v.inner.borrow().bind_group.clone()))
+            .collect();
+        if let (Some(group), Some(layout)) = (
+            shadow_builder.storage_group,
+            &shadow_pipeline.storage_bind_group_layout,
+        ) {
+            bind_groups.push((
+                group,
+                shadow_builder.storage_bind_group(&self.device, layout, instance_count),
+            ));
         }
 
-        for bind_group in uniforms.iter() {
-            log::debug!("Setting bind group {}", current_slot);
-            render_pass.set_bind_group(current_slot, bind_group, &[]);
-            current_slot += 1;
+        let mut render_pass = shadow_data
+            .encoder
+            .begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("meshes shadow pass"),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: shadow_data.shadow_texture,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                occlusion_query_set: None,
+                timestamp_writes: None,
+                multiview_mask: None,
+            });
+        render_pass.set_bind_group(0, shadow_data.shadow_bind_group, &[]);
+        render_pass.set_pipeline(&shadow_pipeline.render_pipeline);
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        for (binding, buffer) in bindings.iter() {
+            render_pass.set_vertex_buffer(binding.slot, buffer.slice(..));
         }
+        for (group, bind_group) in bind_groups.iter() {
+            render_pass.set_bind_group(*group, bind_group, &[]);
+        }
+        render_pass.draw_indexed(0..self.vertex_count as u32, 0, 0..instance_count as u32);
+    }
 
-        log::debug!("Drawing {instance_count} instances");
+    fn render_picking(&self, picking_data: &mut PickingRenderData) {
+        let Some(instance_count) = self.instance_count() else {
+            return;
+        };
+        let mut render_pass = picking_data
+            .encoder
+            .begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("meshes picking pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: picking_data.id_texture,
+                    resolve_target: None,
+                    depth_slice: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: picking_data.depth_texture,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                occlusion_query_set: None,
+                timestamp_writes: None,
+                multiview_mask: None,
+            });
+        let [x, y] = picking_data.pixel;
+        render_pass.set_scissor_rect(x, y, 1, 1);
+        render_pass.set_bind_group(0, &picking_data.camera.bind_group, &[]);
+        render_pass.set_bind_group(1, &picking_data.light.bind_group, &[]);
+        render_pass.set_pipeline(&self.picking_render_pipeline);
+        self.set_instance_bindings(&mut render_pass, instance_count);
         render_pass.draw_indexed(0..self.vertex_count as u32, 0, 0..instance_count as u32);
     }
 }
@@ -1,6 +1,14 @@
+pub mod arrows;
+pub mod axes;
+pub mod boxes;
+pub mod capsules;
 pub mod circles;
+pub mod compute;
+pub mod cones;
 pub mod cylinders;
+pub mod ellipsoids;
 pub mod instanced;
+pub mod labels;
 pub mod lines;
 pub mod mesh;
 pub mod pipeline;
@@ -10,10 +18,20 @@ pub mod rects;
 pub mod renderable;
 pub mod spheres;
 pub mod torus;
+pub mod tubes;
+pub mod volume;
 
+pub use arrows::*;
+pub use axes::*;
+pub use boxes::*;
+pub use capsules::*;
 pub use circles::*;
+pub use compute::*;
+pub use cones::*;
 pub use cylinders::*;
+pub use ellipsoids::*;
 pub use instanced::*;
+pub use labels::*;
 pub use lines::*;
 pub use mesh::*;
 pub use pipeline::*;
@@ -23,3 +41,5 @@ pub use rects::*;
 pub use renderable::*;
 pub use spheres::*;
 pub use torus::*;
+pub use tubes::*;
+pub use volume::*;
@@ -1,9 +1,11 @@
+use crate::post_process::transparency::TransparencyPass;
 use crate::rendering_descriptor::RenderingDescriptor;
-use crate::simulation::{RenderData, ShadowRenderData};
-use crate::{DefaultRenderPassDescriptor, Renderable};
+use crate::simulation::{PickingRenderData, RenderData, ShadowRenderData, TransparentRenderData};
+use crate::{DefaultRenderPassDescriptor, Renderable, TransparentRenderPassDescriptor};
 use itertools::Itertools;
 use naga::{back::wgsl::WriterFlags, valid::ValidationFlags};
 use std::cell::Ref;
+use std::sync::atomic::{AtomicU32, Ordering};
 use visula_core::{BindingBuilder, Delegate, InstanceBinding};
 use wgpu::util::DeviceExt;
 use wgpu::{BindGroupLayout, BufferUsages, PipelineCompilationOptions};
@@ -14,6 +16,9 @@ pub struct QuadPipelineDescriptor<'a> {
     pub shader_variable_name: &'a str,
     pub fragment_shader_variable_name: Option<&'a str>,
     pub shadow_shader_source: Option<&'a str>,
+    /// Draw with order-independent transparency in `render_transparent` instead of opaque in
+    /// `render`, using the alpha of the fragment color as opacity.
+    pub transparent: bool,
     pub vertex_data: &'a [u8],
     pub vertex_stride: usize,
     pub vertex_format: wgpu::VertexFormat,
@@ -21,14 +26,32 @@ pub struct QuadPipelineDescriptor<'a> {
     pub index_format: wgpu::IndexFormat,
 }
 
+/// Picking id of the next created pipeline. Zero is reserved for pixels without a hit.
+pub(crate) static NEXT_PICKING_ID: AtomicU32 = AtomicU32::new(1);
+
+/// The shadow pass has its own shader module, so its bindings are numbered separately from
+/// the main pass.
+struct ShadowPipeline {
+    render_pipeline: wgpu::RenderPipeline,
+    storage_bind_group_layout: Option<BindGroupLayout>,
+    binding_builder: BindingBuilder,
+}
+
 pub struct QuadPipeline {
     render_pipeline: wgpu::RenderPipeline,
-    shadow_render_pipeline: Option<wgpu::RenderPipeline>,
+    shadow_pipeline: Option<ShadowPipeline>,
+    vertex_storage_layout: Option<BindGroupLayout>,
+    picking_render_pipeline: wgpu::RenderPipeline,
+    picking_id: u32,
+    transparent: bool,
     vertex_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
     index_count: usize,
     index_format: wgpu::IndexFormat,
     label: String,
+    /// Storage bind groups are created when drawing, since instance buffers may be
+    /// reallocated between frames.
+    device: wgpu::Device,
     vertex_binding_builder: BindingBuilder,
     fragment_binding_builder: Option<BindingBuilder>,
 }
@@ -98,58 +121,53 @@ impl QuadPipeline {
                 .map_err(Box::new)?;
         let output_str = naga::back::wgsl::write_string(&module, &info, WriterFlags::all())?;
         log::debug!("Resulting {} shader code:\n{output_str}", descriptor.label);
+        let output_str = if descriptor.transparent {
+            let transparency_module = visula_core::transparency_module(&module)?;
+            let transparency_info = naga::valid::Validator::new(
+                ValidationFlags::empty(),
+                naga::valid::Capabilities::all(),
+            )
+            .validate(&transparency_module)
+            .map_err(Box::new)?;
+            naga::back::wgsl::write_string(
+                &transparency_module,
+                &transparency_info,
+                WriterFlags::all(),
+            )?
+        } else {
+            output_str
+        };
 
         let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: None,
             source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(&output_str)),
         });
 
-        let vertex_uniform_layouts: Vec<&BindGroupLayout> = vertex_binding_builder
-            .uniforms
-            .values()
-            .map(|binding| binding.bind_group_layout.as_ref())
-            .collect();
-
-        let fragment_uniform_layouts: Vec<&BindGroupLayout> = fragment_binding_builder
-            .as_ref()
-            .map(|b| {
-                b.uniforms
-                    .values()
-                    .map(|binding| binding.bind_group_layout.as_ref())
-                    .collect()
-            })
-            .unwrap_or_default();
+        let picking_id = NEXT_PICKING_ID.fetch_add(1, Ordering::Relaxed);
+        let picking_module = visula_core::picking_module(&module, picking_id)?;
+        let picking_info =
+            naga::valid::Validator::new(ValidationFlags::empty(), naga::valid::Capabilities::all())
+                .validate(&picking_module)
+                .map_err(Box::new)?;
+        let picking_str =
+            naga::back::wgsl::write_string(&picking_module, &picking_info, WriterFlags::all())?;
+        let picking_shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(&format!("{} picking shader", descriptor.label)),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(&picking_str)),
+        });
 
-        let fragment_texture_layouts: Vec<BindGroupLayout> = fragment_binding_builder
-            .as_ref()
-            .map(|b| {
-                b.textures
-                    .values()
-                    .map(|binding| binding.inner.borrow().bind_group_layout.clone())
-                    .collect()
-            })
-            .unwrap_or_default();
-
-        let bind_group_layouts = {
-            let mut layouts: Vec<Option<&wgpu::BindGroupLayout>> = vec![
-                Some(&camera.bind_group_layout),
-                Some(&light.bind_group_layout),
-            ];
-            for layout in &vertex_uniform_layouts {
-                layouts.push(Some(layout));
-            }
-            for layout in &fragment_texture_layouts {
-                layouts.push(Some(layout));
-            }
-            for layout in &fragment_uniform_layouts {
-                layouts.push(Some(layout));
-            }
-            layouts
-        };
+        let vertex_storage_layout = vertex_binding_builder.storage_bind_group_layout(device);
+        let mut bind_group_layouts = vertex_binding_builder.bind_group_layouts(
+            &[&camera.bind_group_layout, &light.bind_group_layout],
+            vertex_storage_layout.as_ref(),
+        );
+        if let Some(builder) = &fragment_binding_builder {
+            builder.extend_bind_group_layouts(&mut bind_group_layouts, None);
+        }
 
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some(&format!("{} pipeline layout", descriptor.label)),
-            bind_group_layouts: &bind_group_layouts,
+            bind_group_layouts: &bind_group_layouts.iter().map(Option::as_ref).collect_vec(),
             immediate_size: 0,
         });
 
@@ -174,6 +192,23 @@ impl QuadPipeline {
             buffers
         };
 
+        let color_targets = if descriptor.transparent {
+            TransparencyPass::color_targets()
+        } else {
+            [
+                Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                }),
+                Some(wgpu::ColorTargetState {
+                    format: wgpu::TextureFormat::Rgba16Float,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                }),
+            ]
+        };
+
         let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some(&format!("{} render pipeline", descriptor.label)),
             layout: Some(&pipeline_layout),
@@ -186,18 +221,7 @@ impl QuadPipeline {
             fragment: Some(wgpu::FragmentState {
                 module: &shader_module,
                 entry_point: Some("fs_main"),
-                targets: &[
-                    Some(wgpu::ColorTargetState {
-                        format,
-                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    }),
-                    Some(wgpu::ColorTargetState {
-                        format: wgpu::TextureFormat::Rgba16Float,
-                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    }),
-                ],
+                targets: &color_targets,
                 compilation_options: PipelineCompilationOptions::default(),
             }),
             primitive: wgpu::PrimitiveState {
@@ -207,7 +231,7 @@ impl QuadPipeline {
             },
             depth_stencil: Some(wgpu::DepthStencilState {
                 format: wgpu::TextureFormat::Depth32Float,
-                depth_write_enabled: Some(true),
+                depth_write_enabled: Some(!descriptor.transparent),
                 depth_compare: Some(wgpu::CompareFunction::Less),
                 stencil: wgpu::StencilState::default(),
                 bias: wgpu::DepthBiasState::default(),
@@ -221,7 +245,44 @@ impl QuadPipeline {
             cache: None,
         });
 
-        let shadow_render_pipeline = if let Some(shadow_source) = descriptor.shadow_shader_source {
+        let picking_render_pipeline =
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(&format!("{} picking render pipeline", descriptor.label)),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &picking_shader_module,
+                    entry_point: Some("vs_main"),
+                    buffers: &buffers,
+                    compilation_options: PipelineCompilationOptions::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &picking_shader_module,
+                    entry_point: Some("fs_main"),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: visula_core::PICKING_FORMAT,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: PipelineCompilationOptions::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: None,
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: wgpu::TextureFormat::Depth32Float,
+                    depth_write_enabled: Some(true),
+                    depth_compare: Some(wgpu::CompareFunction::Less),
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview_mask: None,
+                cache: None,
+            });
+
+        let shadow_pipeline = if let Some(shadow_source) = descriptor.shadow_shader_source {
             let mut shadow_module = naga::front::wgsl::parse_str(shadow_source)?;
             let mut shadow_builder = BindingBuilder::new(&shadow_module, "vs_main", 1)?;
             vertex_delegate.inject(
@@ -268,25 +329,19 @@ impl QuadPipeline {
                 buffers
             };
 
-            let shadow_uniform_layouts: Vec<&BindGroupLayout> = shadow_builder
-                .uniforms
-                .values()
-                .map(|binding| binding.bind_group_layout.as_ref())
-                .collect();
-
-            let shadow_bind_group_layouts = {
-                let mut layouts: Vec<Option<&wgpu::BindGroupLayout>> =
-                    vec![Some(&light.shadow_bind_group_layout)];
-                for layout in &shadow_uniform_layouts {
-                    layouts.push(Some(layout));
-                }
-                layouts
-            };
+            let shadow_storage_layout = shadow_builder.storage_bind_group_layout(device);
+            let shadow_bind_group_layouts = shadow_builder.bind_group_layouts(
+                &[&light.shadow_bind_group_layout],
+                shadow_storage_layout.as_ref(),
+            );
 
             let shadow_pipeline_layout =
                 device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                     label: Some(&format!("{} shadow pipeline layout", descriptor.label)),
-                    bind_group_layouts: &shadow_bind_group_layouts,
+                    bind_group_layouts: &shadow_bind_group_layouts
+                        .iter()
+                        .map(Option::as_ref)
+                        .collect_vec(),
                     immediate_size: 0,
                 });
 
@@ -306,7 +361,7 @@ impl QuadPipeline {
                 None
             };
 
-            Some(
+            let shadow_render_pipeline =
                 device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
                     label: Some(&format!("{} shadow render pipeline", descriptor.label)),
                     layout: Some(&shadow_pipeline_layout),
@@ -336,48 +391,62 @@ impl QuadPipeline {
                     multisample: wgpu::MultisampleState::default(),
                     multiview_mask: None,
                     cache: None,
-                }),
-            )
+                });
+            Some(ShadowPipeline {
+                render_pipeline: shadow_render_pipeline,
+                storage_bind_group_layout: shadow_storage_layout,
+                binding_builder: shadow_builder,
+            })
         } else {
             None
         };
 
         Ok(QuadPipeline {
             render_pipeline,
-            shadow_render_pipeline,
+            shadow_pipeline,
+            vertex_storage_layout,
+            picking_render_pipeline,
+            picking_id,
+            transparent: descriptor.transparent,
             vertex_buffer,
             index_buffer,
             index_count,
             index_format: descriptor.index_format,
             label: descriptor.label.to_string(),
+            device: device.clone(),
             vertex_binding_builder,
             fragment_binding_builder,
         })
     }
 }
 
-impl Renderable for QuadPipeline {
-    fn render(
-        &self,
-        RenderData {
-            encoder,
-            view,
-            multisampled_framebuffer,
-            depth_texture,
-            normal_msaa,
-            normal_resolve,
-            camera,
-            light,
-            ..
-        }: &mut RenderData,
-    ) {
-        log::trace!("Rendering {}", self.label);
+impl QuadPipeline {
+    /// The id written to the picking target for instances drawn by this pipeline, as returned
+    /// in [`crate::PickResult::picking_id`].
+    pub fn picking_id(&self) -> u32 {
+        self.picking_id
+    }
+
+    /// The number of instances to draw, or `None` if the instance buffers are empty or
+    /// disagree on their length.
+    fn instance_count(&self) -> Option<usize> {
         if self.index_count == 0 {
-            return;
+            return None;
         }
+        let builder = &self.vertex_binding_builder;
+        let inners = builder
+            .instances
+            .values()
+            .map(|binding| &binding.inner)
+            .chain(
+                builder
+                    .storage_buffers
+                    .values()
+                    .map(|binding| &binding.inner),
+            );
         let mut count = None;
-        for binding in self.vertex_binding_builder.instances.values() {
-            let other = binding.inner.borrow().count;
+        for inner in inners {
+            let other = inner.borrow().count;
             if other == 0 {
                 count = None;
                 break;
@@ -394,119 +463,153 @@ impl Renderable for QuadPipeline {
             }
         }
         log::trace!("{} count {count:#?}", self.label);
-        if count.is_none() && !self.vertex_binding_builder.instances.is_empty() {
+        if builder.instances.is_empty() && builder.storage_buffers.is_empty() {
+            return Some(1);
+        }
+        if count.is_none() {
             log::debug!("Empty {} buffer detected. Aborting render.", self.label);
-            return;
         }
+        count
+    }
 
+    /// Binds the instance buffers and the vertex and fragment bind groups that follow the
+    /// camera and light, using the same layout as the main and picking pipelines.
+    fn set_instance_bindings(&self, render_pass: &mut wgpu::RenderPass, instance_count: usize) {
         let bindings: Vec<(&InstanceBinding, Ref<wgpu::Buffer>)> = self
             .vertex_binding_builder
             .instances
             .values()
             .map(|v| (v, Ref::map(v.inner.borrow(), |v| &v.buffer)))
             .collect();
-        let vertex_uniforms: Vec<wgpu::BindGroup> = self
+        let mut bind_groups: Vec<(u32, wgpu::BindGroup)> = self
             .vertex_binding_builder
             .uniforms
             .values()
-            .map(|v| v.inner.borrow().bind_group.clone())
+            .map(|v| (v.group, v.inner.borrow().bind_group.clone()))
             .collect();
-        let fragment_textures: Vec<wgpu::BindGroup> = self
-            .fragment_binding_builder
-            .as_ref()
-            .map(|b| {
-                b.textures
+        if let (Some(group), Some(layout)) = (
+            self.vertex_binding_builder.storage_group,
+            &self.vertex_storage_layout,
+        ) {
+            bind_groups.push((
+                group,
+                self.vertex_binding_builder.storage_bind_group(
+                    &self.device,
+                    layout,
+                    instance_count,
+                ),
+            ));
+        }
+        if let Some(builder) = &self.fragment_binding_builder {
+            bind_groups.extend(
+                builder
+                    .textures
                     .values()
-                    .map(|v| v.inner.borrow().bind_group.clone())
-                    .collect()
-            })
-            .unwrap_or_default();
-        let fragment_uniforms: Vec<wgpu::BindGroup> = self
-            .fragment_binding_builder
-            .as_ref()
-            .map(|b| {
-                b.uniforms
+                    .map(|v| (v.group, v.inner.borrow().bind_group.clone())),
+            );
+            bind_groups.extend(
+                builder
+                    .uniforms
                     .values()
-                    .map(|v| v.inner.borrow().bind_group.clone())
-                    .collect()
-            })
-            .unwrap_or_default();
-        {
-            let default_render_pass = DefaultRenderPassDescriptor::new(
-                &self.label,
-                view,
-                multisampled_framebuffer,
-                depth_texture,
-                normal_msaa,
-                normal_resolve,
+                    .map(|v| (v.group, v.inner.borrow().bind_group.clone())),
             );
-            let mut render_pass = encoder.begin_render_pass(&default_render_pass.build());
-            render_pass.set_bind_group(0, &camera.bind_group, &[]);
-            render_pass.set_bind_group(1, &light.bind_group, &[]);
+        }
 
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_index_buffer(self.index_buffer.slice(..), self.index_format);
-            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            let mut instance_count = if self.vertex_binding_builder.instances.is_empty() {
-                1
-            } else {
-                0
-            };
-            for (binding, buffer) in bindings.iter() {
-                let slot = binding.slot;
-                log::trace!("Setting vertex buffer {slot}");
-                render_pass.set_vertex_buffer(slot, buffer.slice(..));
-                instance_count = instance_count.max(binding.inner.borrow().count);
-            }
-            let mut current_slot = 2u32;
-            for bind_group in vertex_uniforms.iter() {
-                log::trace!("Setting vertex uniform bind group {current_slot}");
-                render_pass.set_bind_group(current_slot, bind_group, &[]);
-                current_slot += 1;
-            }
-            for bind_group in fragment_textures.iter() {
-                log::trace!("Setting fragment texture bind group {current_slot}");
-                render_pass.set_bind_group(current_slot, bind_group, &[]);
-                current_slot += 1;
-            }
-            for bind_group in fragment_uniforms.iter() {
-                log::trace!("Setting fragment uniform bind group {current_slot}");
-                render_pass.set_bind_group(current_slot, bind_group, &[]);
-                current_slot += 1;
-            }
-            render_pass.draw_indexed(0..self.index_count as u32, 0, 0..instance_count as u32);
+        render_pass.set_index_buffer(self.index_buffer.slice(..), self.index_format);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        for (binding, buffer) in bindings.iter() {
+            let slot = binding.slot;
+            log::trace!("Setting vertex buffer {slot}");
+            render_pass.set_vertex_buffer(slot, buffer.slice(..));
+        }
+        for (group, bind_group) in bind_groups.iter() {
+            log::trace!("Setting bind group {group}");
+            render_pass.set_bind_group(*group, bind_group, &[]);
         }
     }
+}
 
-    fn render_shadow(&self, shadow_data: &mut ShadowRenderData) {
-        let Some(ref shadow_pipeline) = self.shadow_render_pipeline else {
+impl Renderable for QuadPipeline {
+    fn render(
+        &self,
+        RenderData {
+            encoder,
+            view,
+            multisampled_framebuffer,
+            depth_texture,
+            normal_msaa,
+            normal_resolve,
+            camera,
+            light,
+            ..
+        }: &mut RenderData,
+    ) {
+        if self.transparent {
+            return;
+        }
+        log::trace!("Rendering {}", self.label);
+        let Some(instance_count) = self.instance_count() else {
             return;
         };
-        if self.index_count == 0 {
+        let default_render_pass = DefaultRenderPassDescriptor::new(
+            &self.label,
+            view,
+            multisampled_framebuffer,
+            depth_texture,
+            normal_msaa,
+            normal_resolve,
+        );
+        let mut render_pass = encoder.begin_render_pass(&default_render_pass.build());
+        render_pass.set_bind_group(0, &camera.bind_group, &[]);
+        render_pass.set_bind_group(1, &light.bind_group, &[]);
+        render_pass.set_pipeline(&self.render_pipeline);
+        self.set_instance_bindings(&mut render_pass, instance_count);
+        render_pass.draw_indexed(0..self.index_count as u32, 0, 0..instance_count as u32);
+    }
+
+    fn render_transparent(
+        &self,
+        TransparentRenderData {
+            accumulation,
+            accumulation_resolve,
+            revealage,
+            revealage_resolve,
+            depth_texture,
+            encoder,
+            camera,
+            light,
+        }: &mut TransparentRenderData,
+    ) {
+        if !self.transparent {
             return;
         }
+        log::trace!("Rendering transparent {}", self.label);
+        let Some(instance_count) = self.instance_count() else {
+            return;
+        };
+        let transparent_render_pass = TransparentRenderPassDescriptor::new(
+            &self.label,
+            accumulation,
+            *accumulation_resolve,
+            revealage,
+            *revealage_resolve,
+            depth_texture,
+        );
+        let mut render_pass = encoder.begin_render_pass(&transparent_render_pass.build());
+        render_pass.set_bind_group(0, &camera.bind_group, &[]);
+        render_pass.set_bind_group(1, &light.bind_group, &[]);
+        render_pass.set_pipeline(&self.render_pipeline);
+        self.set_instance_bindings(&mut render_pass, instance_count);
+        render_pass.draw_indexed(0..self.index_count as u32, 0, 0..instance_count as u32);
+    }
 
-        let mut count = None;
-        for binding in self.vertex_binding_builder.instances.values() {
-            let other = binding.inner.borrow().count;
-            if other == 0 {
-                count = None;
-                break;
-            }
-            count = match count {
-                None => Some(other),
-                Some(old) => {
-                    if other != old {
-                        None
-                    } else {
-                        Some(old)
-                    }
-                }
-            }
-        }
-        if count.is_none() && !self.vertex_binding_builder.instances.is_empty() {
+    fn render_shadow(&self, shadow_data: &mut ShadowRenderData) {
+        let Some(ref shadow_pipeline) = self.shadow_pipeline else {
             return;
-        }
+        };
+        let Some(instance_count) = self.instance_count() else {
+            return;
+        };
 
         let bindings: Vec<(&InstanceBinding, Ref<wgpu::Buffer>)> = self
             .vertex_binding_builder
@@ -535,33 +638,74 @@ impl Renderable for QuadPipeline {
                         multiview_mask: None,
                     });
 
-            render_pass.set_bind_group(0, &shadow_data.light.shadow_bind_group, &[]);
-            render_pass.set_pipeline(shadow_pipeline);
+            render_pass.set_bind_group(0, shadow_data.shadow_bind_group, &[]);
+            render_pass.set_pipeline(&shadow_pipeline.render_pipeline);
             render_pass.set_index_buffer(self.index_buffer.slice(..), self.index_format);
             render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
 
-            let mut instance_count = if self.vertex_binding_builder.instances.is_empty() {
-                1
-            } else {
-                0
-            };
             for (binding, buffer) in bindings.iter() {
                 let slot = binding.slot;
                 render_pass.set_vertex_buffer(slot, buffer.slice(..));
-                instance_count = instance_count.max(binding.inner.borrow().count);
             }
 
-            let vertex_uniforms: Vec<wgpu::BindGroup> = self
-                .vertex_binding_builder
+            let shadow_builder = &shadow_pipeline.binding_builder;
+            let mut bind_groups: Vec<(u32, wgpu::BindGroup)> = shadow_builder
                 .uniforms
                 .values()
-                .map(|v| v.inner.borrow().bind_group.clone())
+                .map(|v| (v.group, v.inner.borrow().bind_group.clone()))
                 .collect();
-            for (current_slot, bind_group) in (1u32..).zip(vertex_uniforms.iter()) {
-                render_pass.set_bind_group(current_slot, bind_group, &[]);
+            if let (Some(group), Some(layout)) = (
+                shadow_builder.storage_group,
+                &shadow_pipeline.storage_bind_group_layout,
+            ) {
+                bind_groups.push((
+                    group,
+                    shadow_builder.storage_bind_group(&self.device, layout, instance_count),
+                ));
+            }
+            for (group, bind_group) in bind_groups.iter() {
+                render_pass.set_bind_group(*group, bind_group, &[]);
             }
 
             render_pass.draw_indexed(0..self.index_count as u32, 0, 0..instance_count as u32);
         }
     }
+
+    fn render_picking(&self, picking_data: &mut PickingRenderData) {
+        let Some(instance_count) = self.instance_count() else {
+            return;
+        };
+        let mut render_pass = picking_data
+            .encoder
+            .begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(&format!("{} picking pass", self.label)),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: picking_data.id_texture,
+                    resolve_target: None,
+                    depth_slice: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: picking_data.depth_texture,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                occlusion_query_set: None,
+                timestamp_writes: None,
+                multiview_mask: None,
+            });
+        let [x, y] = picking_data.pixel;
+        render_pass.set_scissor_rect(x, y, 1, 1);
+        render_pass.set_bind_group(0, &picking_data.camera.bind_group, &[]);
+        render_pass.set_bind_group(1, &picking_data.light.bind_group, &[]);
+        render_pass.set_pipeline(&self.picking_render_pipeline);
+        self.set_instance_bindings(&mut render_pass, instance_count);
+        render_pass.draw_indexed(0..self.index_count as u32, 0, 0..instance_count as u32);
+    }
 }
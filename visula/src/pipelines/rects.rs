@@ -1,6 +1,6 @@
 use crate::pipelines::quad::{QuadPipeline, QuadPipelineDescriptor};
 use crate::rendering_descriptor::RenderingDescriptor;
-use crate::simulation::RenderData;
+use crate::simulation::{PickingRenderData, RenderData};
 use crate::Renderable;
 use bytemuck::{Pod, Zeroable};
 use glam::{Vec2, Vec3, Vec4};
@@ -75,6 +75,7 @@ impl Rects {
                 shader_variable_name: "rect",
                 fragment_shader_variable_name: None,
                 shadow_shader_source: None,
+                transparent: false,
                 vertex_data: bytemuck::cast_slice(&vertex_data),
                 vertex_stride: size_of::<Vertex>(),
                 vertex_format: wgpu::VertexFormat::Float32x2,
@@ -85,10 +86,17 @@ impl Rects {
             None,
         )?))
     }
+
+    pub fn picking_id(&self) -> u32 {
+        self.0.picking_id()
+    }
 }
 
 impl Renderable for Rects {
     fn render(&self, render_data: &mut RenderData) {
         self.0.render(render_data);
     }
+    fn render_picking(&self, picking_data: &mut PickingRenderData) {
+        self.0.render_picking(picking_data);
+    }
 }
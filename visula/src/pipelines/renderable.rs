@@ -1,6 +1,8 @@
-use crate::{RenderData, ShadowRenderData};
+use crate::{PickingRenderData, RenderData, ShadowRenderData, TransparentRenderData};
 
 pub trait Renderable {
     fn render(&self, render_data: &mut RenderData);
+    fn render_transparent(&self, _transparent_data: &mut TransparentRenderData) {}
     fn render_shadow(&self, _shadow_data: &mut ShadowRenderData) {}
+    fn render_picking(&self, _picking_data: &mut PickingRenderData) {}
 }
@@ -1,6 +1,6 @@
 use crate::pipelines::quad::{QuadPipeline, QuadPipelineDescriptor};
 use crate::rendering_descriptor::RenderingDescriptor;
-use crate::simulation::{RenderData, ShadowRenderData};
+use crate::simulation::{PickingRenderData, RenderData, ShadowRenderData, TransparentRenderData};
 use crate::Renderable;
 use bytemuck::{Pod, Zeroable};
 use glam::Vec3;
@@ -48,6 +48,9 @@ pub struct SphereGeometry {
 #[derive(Delegate)]
 pub struct SphereMaterial {
     pub color: Expression,
+    /// Opacity in `[0, 1]`. Anything but the default `1.0` draws the spheres with
+    /// order-independent transparency.
+    pub alpha: Expression,
 }
 
 impl Default for SphereGeometry {
@@ -64,6 +67,7 @@ impl Default for SphereMaterial {
     fn default() -> Self {
         SphereMaterial {
             color: Expression::InputColor.lit(),
+            alpha: 1.0.into(),
         }
     }
 }
@@ -83,6 +87,7 @@ impl Spheres {
                 shader_variable_name: "sphere_geometry",
                 fragment_shader_variable_name: Some("sphere_material"),
                 shadow_shader_source: Some(include_str!("../shaders/sphere_shadow.wgsl")),
+                transparent: !material.alpha.is_one(),
                 vertex_data: bytemuck::cast_slice(&vertex_data),
                 vertex_stride: size_of::<Vertex>(),
                 vertex_format: wgpu::VertexFormat::Float32x4,
@@ -93,13 +98,23 @@ impl Spheres {
             Some(material),
         )?))
     }
+
+    pub fn picking_id(&self) -> u32 {
+        self.0.picking_id()
+    }
 }
 
 impl Renderable for Spheres {
     fn render(&self, render_data: &mut RenderData) {
         self.0.render(render_data);
     }
+    fn render_transparent(&self, transparent_data: &mut TransparentRenderData) {
+        self.0.render_transparent(transparent_data);
+    }
     fn render_shadow(&self, shadow_data: &mut ShadowRenderData) {
         self.0.render_shadow(shadow_data);
     }
+    fn render_picking(&self, picking_data: &mut PickingRenderData) {
+        self.0.render_picking(picking_data);
+    }
 }
@@ -0,0 +1,65 @@
+use crate::pipelines::mesh::{MeshGeometry, MeshMaterial, MeshPipeline};
+use crate::primitives::tube::{generate_tube, TubePoint};
+use crate::rendering_descriptor::RenderingDescriptor;
+use crate::simulation::{PickingRenderData, RenderData, ShadowRenderData, TransparentRenderData};
+use crate::Renderable;
+
+/// Smooth tubes through ordered lists of points, such as neuron morphologies or trajectories.
+///
+/// The tubes are generated on the CPU with [`generate_tube`] whenever [`Tubes::update`] is
+/// called, and drawn as a single mesh. The color of the points is available to the material as
+/// [`visula_core::Expression::InputColor`].
+pub struct Tubes {
+    mesh: MeshPipeline,
+    /// Number of samples between each pair of points.
+    pub subdivisions: usize,
+    /// Number of segments around the tubes.
+    pub sides: usize,
+}
+
+impl Tubes {
+    pub fn new(
+        rendering_descriptor: &RenderingDescriptor,
+        geometry: &MeshGeometry,
+        material: &MeshMaterial,
+    ) -> Result<Self, visula_core::ShaderError> {
+        Ok(Tubes {
+            mesh: MeshPipeline::new(rendering_descriptor, geometry, material)?,
+            subdivisions: 8,
+            sides: 16,
+        })
+    }
+
+    /// Replaces all tubes, with one tube per list of points.
+    pub fn update(&mut self, device: &wgpu::Device, tubes: &[Vec<TubePoint>]) {
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        for points in tubes {
+            let (tube_vertices, tube_indices) =
+                generate_tube(points, self.subdivisions, self.sides);
+            let offset = vertices.len() as u32;
+            vertices.extend(tube_vertices);
+            indices.extend(tube_indices.into_iter().map(|index| index + offset));
+        }
+        self.mesh.set_mesh_data(device, &vertices, &indices);
+    }
+
+    pub fn picking_id(&self) -> u32 {
+        self.mesh.picking_id()
+    }
+}
+
+impl Renderable for Tubes {
+    fn render(&self, render_data: &mut RenderData) {
+        self.mesh.render(render_data);
+    }
+    fn render_transparent(&self, transparent_data: &mut TransparentRenderData) {
+        self.mesh.render_transparent(transparent_data);
+    }
+    fn render_shadow(&self, shadow_data: &mut ShadowRenderData) {
+        self.mesh.render_shadow(shadow_data);
+    }
+    fn render_picking(&self, picking_data: &mut PickingRenderData) {
+        self.mesh.render_picking(picking_data);
+    }
+}
@@ -0,0 +1,431 @@
+use crate::post_process::transparency::TransparencyPass;
+use crate::rendering_descriptor::RenderingDescriptor;
+use crate::simulation::{RenderData, TransparentRenderData};
+use crate::Renderable;
+use glam::Vec3;
+use itertools::Itertools;
+use naga::{back::wgsl::WriterFlags, valid::ValidationFlags};
+use std::cell::Ref;
+use visula_core::{colormap, BindingBuilder, Colormap, Delegate as _, Expression, InstanceBinding};
+use visula_derive::Delegate;
+use wgpu::util::DeviceExt;
+use wgpu::{BindGroupLayout, PipelineCompilationOptions};
+
+/// Corners of the unit cube, indexed by `x + 2 y + 4 z`.
+const CORNERS: [[f32; 3]; 8] = [
+    [0.0, 0.0, 0.0],
+    [1.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0],
+    [1.0, 1.0, 0.0],
+    [0.0, 0.0, 1.0],
+    [1.0, 0.0, 1.0],
+    [0.0, 1.0, 1.0],
+    [1.0, 1.0, 1.0],
+];
+
+/// Faces of the unit cube, counter-clockwise when seen from the outside.
+const INDICES: [u16; 36] = [
+    0, 2, 1, 1, 2, 3, // -z
+    4, 5, 6, 5, 7, 6, // +z
+    0, 4, 2, 2, 4, 6, // -x
+    1, 3, 5, 3, 7, 5, // +x
+    0, 1, 4, 1, 5, 4, // -y
+    2, 6, 3, 3, 6, 7, // +y
+];
+
+#[derive(Delegate)]
+pub struct VolumeGeometry {
+    /// The corner of the box with the lowest coordinates.
+    pub position: Expression,
+    /// The extent of the box along each axis.
+    pub size: Expression,
+}
+
+/// The transfer function of a [`Volume`], given as expressions of
+/// [`Expression::VolumeValue`].
+#[derive(Delegate)]
+pub struct VolumeMaterial {
+    pub color: Expression,
+    /// Opacity in `[0, 1]` of a single voxel, corrected for the step length when raymarching.
+    pub opacity: Expression,
+}
+
+impl Default for VolumeGeometry {
+    fn default() -> Self {
+        VolumeGeometry {
+            position: Vec3::ZERO.into(),
+            size: Vec3::ONE.into(),
+        }
+    }
+}
+
+impl Default for VolumeMaterial {
+    fn default() -> Self {
+        VolumeMaterial {
+            color: colormap(Expression::VolumeValue, Colormap::Viridis),
+            opacity: Expression::VolumeValue.clamp(0.0, 1.0) * 0.1,
+        }
+    }
+}
+
+/// A 3D scalar field drawn with direct volume rendering.
+///
+/// The values are stored in a 3D texture and raymarched through the box given by the
+/// geometry, mapping each sampled value to color and opacity with the material. The volume is
+/// drawn along with other translucent geometry in `render_transparent` and ends where it meets
+/// opaque geometry. The geometry describes a single box, so it should be given by constants
+/// or uniforms rather than instance fields. Texture lookups are not supported in the material,
+/// since it is evaluated in a loop with non-uniform control flow.
+pub struct Volume {
+    render_pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    texture: wgpu::Texture,
+    dimensions: [u32; 3],
+    volume_bind_group: wgpu::BindGroup,
+    depth_bind_group_layout: BindGroupLayout,
+    /// The depth bind group is created when drawing, since the depth texture is recreated
+    /// when the window is resized.
+    device: wgpu::Device,
+    vertex_binding_builder: BindingBuilder,
+    fragment_binding_builder: BindingBuilder,
+}
+
+impl Volume {
+    /// Creates a volume with `dimensions` voxels along each axis, initially filled with zeros.
+    /// Upload values with [`Volume::update`].
+    pub fn new(
+        rendering_descriptor: &RenderingDescriptor,
+        geometry: &VolumeGeometry,
+        material: &VolumeMaterial,
+        dimensions: [u32; 3],
+    ) -> Result<Self, visula_core::ShaderError> {
+        let &RenderingDescriptor {
+            device,
+            camera,
+            sample_count,
+            ..
+        } = rendering_descriptor;
+
+        // The depth texture is only multisampled with multisampling.
+        let shader_source = if sample_count > 1 {
+            include_str!("../shaders/volume.wgsl").to_string()
+        } else {
+            include_str!("../shaders/volume.wgsl")
+                .replace("texture_depth_multisampled_2d", "texture_depth_2d")
+        };
+        let mut module = naga::front::wgsl::parse_str(&shader_source)?;
+        let mut vertex_binding_builder = BindingBuilder::new(&module, "vs_main", 1)?;
+        geometry.inject("volume_geometry", &mut module, &mut vertex_binding_builder)?;
+        let mut fragment_binding_builder = BindingBuilder::new(&module, "fs_transfer", 0)?;
+        material.inject(
+            "volume_material",
+            &mut module,
+            &mut fragment_binding_builder,
+        )?;
+        visula_core::inject::replace_function_with_entry_point(
+            &mut module,
+            "fs_transfer",
+            "visula_volume_transfer",
+        )?;
+
+        log::debug!("Validating volume shader");
+        let info =
+            naga::valid::Validator::new(ValidationFlags::empty(), naga::valid::Capabilities::all())
+                .validate(&module)
+                .map_err(Box::new)?;
+        let output_str = naga::back::wgsl::write_string(&module, &info, WriterFlags::all())?;
+        log::debug!("Resulting volume shader code:\n{output_str}");
+
+        let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("volume shader"),
+            source: wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(&output_str)),
+        });
+
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("volume vertex buffer"),
+            contents: bytemuck::cast_slice(&CORNERS),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("volume index buffer"),
+            contents: bytemuck::cast_slice(&INDICES),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        let [width, height, depth] = dimensions;
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("volume texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: depth,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D3,
+            format: wgpu::TextureFormat::R16Float,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("volume sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let parameters_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("volume parameters buffer"),
+            contents: bytemuck::cast_slice(&[width as f32, height as f32, depth as f32, 0.0]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let volume_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("volume bind group layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D3,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+        let volume_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("volume bind group"),
+            layout: &volume_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: parameters_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+        let depth_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("volume depth bind group layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Depth,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: sample_count > 1,
+                    },
+                    count: None,
+                }],
+            });
+
+        let mut bind_group_layouts = vertex_binding_builder.bind_group_layouts(
+            &[
+                &camera.bind_group_layout,
+                &volume_bind_group_layout,
+                &depth_bind_group_layout,
+            ],
+            None,
+        );
+        fragment_binding_builder.extend_bind_group_layouts(&mut bind_group_layouts, None);
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("volume pipeline layout"),
+            bind_group_layouts: &bind_group_layouts.iter().map(Option::as_ref).collect_vec(),
+            immediate_size: 0,
+        });
+
+        let vertex_buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x3,
+                offset: 0,
+                shader_location: 0,
+            }],
+        };
+        let sorted_bindings = vertex_binding_builder.sorted_bindings();
+        let buffers = std::iter::once(vertex_buffer_layout)
+            .chain(sorted_bindings.iter().map(|binding| binding.layout.build()))
+            .collect_vec();
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("volume render pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader_module,
+                entry_point: Some("vs_main"),
+                buffers: &buffers,
+                compilation_options: PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader_module,
+                entry_point: Some("fs_main"),
+                targets: &TransparencyPass::color_targets(),
+                compilation_options: PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Front),
+                ..Default::default()
+            },
+            // The opaque depth is read in the shader instead, since the ray may start in front
+            // of it even where the back faces are hidden.
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview_mask: None,
+            cache: None,
+        });
+
+        Ok(Volume {
+            render_pipeline,
+            vertex_buffer,
+            index_buffer,
+            texture,
+            dimensions,
+            volume_bind_group,
+            depth_bind_group_layout,
+            device: device.clone(),
+            vertex_binding_builder,
+            fragment_binding_builder,
+        })
+    }
+
+    /// Uploads the values of all voxels, ordered with x varying fastest and z slowest.
+    pub fn update(&self, queue: &wgpu::Queue, values: &[f32]) {
+        let [width, height, depth] = self.dimensions;
+        assert_eq!(
+            values.len(),
+            (width * height * depth) as usize,
+            "Volume data size must be width*height*depth"
+        );
+        let data: Vec<u16> = values
+            .iter()
+            .map(|&value| half::f16::from_f32(value).to_bits())
+            .collect();
+        queue.write_texture(
+            self.texture.as_image_copy(),
+            bytemuck::cast_slice(&data),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(width * 2),
+                rows_per_image: Some(height),
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: depth,
+            },
+        );
+    }
+}
+
+impl Renderable for Volume {
+    fn render(&self, _render_data: &mut RenderData) {}
+
+    fn render_transparent(
+        &self,
+        TransparentRenderData {
+            accumulation,
+            accumulation_resolve,
+            revealage,
+            revealage_resolve,
+            depth_texture,
+            encoder,
+            camera,
+            ..
+        }: &mut TransparentRenderData,
+    ) {
+        log::trace!("Rendering volume");
+        let depth_bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("volume depth bind group"),
+            layout: &self.depth_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(depth_texture),
+            }],
+        });
+        let bindings: Vec<(&InstanceBinding, Ref<wgpu::Buffer>)> = self
+            .vertex_binding_builder
+            .instances
+            .values()
+            .map(|v| (v, Ref::map(v.inner.borrow(), |v| &v.buffer)))
+            .collect();
+        let bind_groups: Vec<(u32, wgpu::BindGroup)> = self
+            .vertex_binding_builder
+            .uniforms
+            .values()
+            .chain(self.fragment_binding_builder.uniforms.values())
+            .map(|v| (v.group, v.inner.borrow().bind_group.clone()))
+            .collect();
+
+        let attachment = |view, resolve_target| {
+            Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })
+        };
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("volume"),
+            color_attachments: &[
+                attachment(*accumulation, *accumulation_resolve),
+                attachment(*revealage, *revealage_resolve),
+            ],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+            multiview_mask: None,
+        });
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &camera.bind_group, &[]);
+        render_pass.set_bind_group(1, &self.volume_bind_group, &[]);
+        render_pass.set_bind_group(2, &depth_bind_group, &[]);
+        for (group, bind_group) in bind_groups.iter() {
+            render_pass.set_bind_group(*group, bind_group, &[]);
+        }
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        for (binding, buffer) in bindings.iter() {
+            render_pass.set_vertex_buffer(binding.slot, buffer.slice(..));
+        }
+        render_pass.draw_indexed(0..INDICES.len() as u32, 0, 0..1);
+    }
+}
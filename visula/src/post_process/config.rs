@@ -43,6 +43,8 @@ pub enum SkyMode {
     Off,
     NormalMap,
     SkyGround,
+    /// The environment set with [`crate::Application::set_environment`].
+    Environment,
 }
 
 impl std::fmt::Display for SkyMode {
@@ -51,6 +53,7 @@ impl std::fmt::Display for SkyMode {
             SkyMode::Off => write!(f, "Off"),
             SkyMode::NormalMap => write!(f, "Normal Map"),
             SkyMode::SkyGround => write!(f, "Sky / Ground"),
+            SkyMode::Environment => write!(f, "Environment"),
         }
     }
 }
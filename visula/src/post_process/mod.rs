@@ -4,14 +4,17 @@ pub mod outline;
 pub mod sky;
 pub mod ssao;
 pub mod tonemap;
+pub mod transparency;
 
 use crate::camera::Camera;
+use crate::environment::Environment;
 use bloom::BloomPass;
 use config::PostProcessConfig;
 use outline::OutlinePass;
 use sky::SkyPass;
 use ssao::SsaoPass;
 use tonemap::TonemapPass;
+use transparency::TransparencyPass;
 
 pub struct PostProcessor {
     pub config: PostProcessConfig,
@@ -27,6 +30,7 @@ pub struct PostProcessor {
     sky: SkyPass,
     ssao: Option<SsaoPass>,
     bloom: Option<BloomPass>,
+    pub(crate) transparency: TransparencyPass,
     sample_count: u32,
     _output_format: wgpu::TextureFormat,
 }
@@ -93,6 +97,14 @@ impl PostProcessor {
             sample_count,
         );
 
+        let transparency = TransparencyPass::new(
+            device,
+            wgpu::TextureFormat::Rgba16Float,
+            width,
+            height,
+            sample_count,
+        );
+
         let outline = if sample_count > 1 {
             Some(OutlinePass::new(
                 device,
@@ -119,6 +131,7 @@ impl PostProcessor {
             sky,
             ssao,
             bloom,
+            transparency,
             sample_count,
             _output_format: output_format,
         }
@@ -243,6 +256,7 @@ impl PostProcessor {
         if let Some(ref mut outline) = self.outline {
             outline.rebuild_bind_group(device, &self.normal_resolve_view, depth_texture_view);
         }
+        self.transparency.resize(device, width, height);
         self.rebuild_tonemap(device);
     }
 
@@ -296,6 +310,10 @@ impl PostProcessor {
         self.rebuild_tonemap(device);
     }
 
+    pub fn set_environment(&mut self, device: &wgpu::Device, environment: &Environment) {
+        self.sky.set_environment(device, environment);
+    }
+
     pub fn render_sky(
         &self,
         encoder: &mut wgpu::CommandEncoder,
@@ -320,6 +338,16 @@ impl PostProcessor {
         );
     }
 
+    pub fn clear_transparency(&self, encoder: &mut wgpu::CommandEncoder) {
+        self.transparency.clear(encoder);
+    }
+
+    /// Composites the translucent geometry drawn since [`Self::clear_transparency`] onto the
+    /// HDR target.
+    pub fn render_transparency(&self, encoder: &mut wgpu::CommandEncoder) {
+        self.transparency.render(encoder, &self.hdr_view);
+    }
+
     pub fn render_ssao(&self, encoder: &mut wgpu::CommandEncoder, queue: &wgpu::Queue) {
         if let Some(ref ssao) = self.ssao {
             if let Some(ref config) = self.config.ssao {
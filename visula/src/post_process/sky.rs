@@ -3,6 +3,7 @@ use wgpu::util::DeviceExt;
 
 use super::config::SkyMode;
 use crate::camera::Camera;
+use crate::environment::Environment;
 
 #[repr(C)]
 #[derive(Clone, Copy, Pod, Zeroable)]
@@ -14,6 +15,7 @@ struct SkyParams {
 pub struct SkyPass {
     pipeline: wgpu::RenderPipeline,
     params_buffer: wgpu::Buffer,
+    params_bind_group_layout: wgpu::BindGroupLayout,
     params_bind_group: wgpu::BindGroup,
 }
 
@@ -42,26 +44,42 @@ impl SkyPass {
         let params_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 label: Some("sky params bind group layout"),
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
                     },
-                    count: None,
-                }],
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
             });
 
-        let params_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("sky params bind group"),
-            layout: &params_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: params_buffer.as_entire_binding(),
-            }],
-        });
+        let params_bind_group = create_params_bind_group(
+            device,
+            &params_bind_group_layout,
+            &params_buffer,
+            &Environment::empty(device),
+        );
 
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("sky pipeline layout"),
@@ -118,15 +136,27 @@ impl SkyPass {
         Self {
             pipeline,
             params_buffer,
+            params_bind_group_layout,
             params_bind_group,
         }
     }
 
+    /// Use `environment` as the background in [`SkyMode::Environment`].
+    pub fn set_environment(&mut self, device: &wgpu::Device, environment: &Environment) {
+        self.params_bind_group = create_params_bind_group(
+            device,
+            &self.params_bind_group_layout,
+            &self.params_buffer,
+            environment,
+        );
+    }
+
     pub fn update_params(&self, queue: &wgpu::Queue, mode: SkyMode) {
         let mode_u32 = match mode {
             SkyMode::Off => 0u32,
             SkyMode::NormalMap => 1,
             SkyMode::SkyGround => 2,
+            SkyMode::Environment => 3,
         };
         let params = SkyParams {
             mode: mode_u32,
@@ -189,3 +219,29 @@ impl SkyPass {
         render_pass.draw(0..3, 0..1);
     }
 }
+
+fn create_params_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    params_buffer: &wgpu::Buffer,
+    environment: &Environment,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("sky params bind group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(&environment.sky_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Sampler(&environment.sampler),
+            },
+        ],
+    })
+}
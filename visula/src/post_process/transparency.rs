@@ -0,0 +1,303 @@
+use visula_core::{ACCUMULATION_FORMAT, REVEALAGE_FORMAT};
+
+/// Targets for weighted blended order-independent transparency and the pass that composites
+/// them onto the HDR target.
+///
+/// Translucent geometry is drawn into the accumulation and revealage targets after all opaque
+/// geometry, testing against the opaque depth without writing to it. With multisampling, the
+/// targets are drawn multisampled and resolved into single-sampled textures for compositing.
+pub struct TransparencyPass {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    pub accumulation_msaa_view: wgpu::TextureView,
+    pub accumulation_view: wgpu::TextureView,
+    pub revealage_msaa_view: wgpu::TextureView,
+    pub revealage_view: wgpu::TextureView,
+    sample_count: u32,
+}
+
+impl TransparencyPass {
+    pub fn new(
+        device: &wgpu::Device,
+        hdr_format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+        sample_count: u32,
+    ) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("transparency composite bind group layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let targets = Self::create_targets(device, width, height, sample_count);
+        let bind_group = Self::create_bind_group(device, &bind_group_layout, &targets);
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("transparency composite pipeline layout"),
+            bind_group_layouts: &[Some(&bind_group_layout)],
+            immediate_size: 0,
+        });
+
+        let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("transparency composite shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                include_str!("../shaders/transparency_composite.wgsl").into(),
+            ),
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("transparency composite pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader_module,
+                entry_point: Some("vs_fullscreen"),
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader_module,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: hdr_format,
+                    blend: Some(wgpu::BlendState {
+                        color: wgpu::BlendComponent {
+                            src_factor: wgpu::BlendFactor::SrcAlpha,
+                            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                            operation: wgpu::BlendOperation::Add,
+                        },
+                        alpha: wgpu::BlendComponent {
+                            src_factor: wgpu::BlendFactor::One,
+                            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                            operation: wgpu::BlendOperation::Add,
+                        },
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview_mask: None,
+            cache: None,
+        });
+
+        let [accumulation_msaa_view, accumulation_view, revealage_msaa_view, revealage_view] =
+            targets;
+        Self {
+            pipeline,
+            bind_group_layout,
+            bind_group,
+            accumulation_msaa_view,
+            accumulation_view,
+            revealage_msaa_view,
+            revealage_view,
+            sample_count,
+        }
+    }
+
+    /// The color targets of pipelines drawing into this pass, with additive blending for the
+    /// accumulation and multiplicative blending of `1 - alpha` for the revealage.
+    pub fn color_targets() -> [Option<wgpu::ColorTargetState>; 2] {
+        [
+            Some(wgpu::ColorTargetState {
+                format: ACCUMULATION_FORMAT,
+                blend: Some(wgpu::BlendState {
+                    color: wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::One,
+                        dst_factor: wgpu::BlendFactor::One,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    alpha: wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::One,
+                        dst_factor: wgpu::BlendFactor::One,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                }),
+                write_mask: wgpu::ColorWrites::ALL,
+            }),
+            Some(wgpu::ColorTargetState {
+                format: REVEALAGE_FORMAT,
+                blend: Some(wgpu::BlendState {
+                    color: wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::Zero,
+                        dst_factor: wgpu::BlendFactor::OneMinusSrc,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    alpha: wgpu::BlendComponent::REPLACE,
+                }),
+                write_mask: wgpu::ColorWrites::ALL,
+            }),
+        ]
+    }
+
+    /// Creates the multisampled and resolved accumulation and revealage views. Without
+    /// multisampling, the multisampled views are the resolved ones.
+    fn create_targets(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        sample_count: u32,
+    ) -> [wgpu::TextureView; 4] {
+        let create = |label: &str, format: wgpu::TextureFormat, sample_count: u32| {
+            let usage = if sample_count > 1 {
+                wgpu::TextureUsages::RENDER_ATTACHMENT
+            } else {
+                wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING
+            };
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(label),
+                    size: wgpu::Extent3d {
+                        width,
+                        height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        };
+        let accumulation_view = create("transparency accumulation", ACCUMULATION_FORMAT, 1);
+        let revealage_view = create("transparency revealage", REVEALAGE_FORMAT, 1);
+        if sample_count > 1 {
+            [
+                create(
+                    "transparency accumulation MSAA",
+                    ACCUMULATION_FORMAT,
+                    sample_count,
+                ),
+                accumulation_view,
+                create(
+                    "transparency revealage MSAA",
+                    REVEALAGE_FORMAT,
+                    sample_count,
+                ),
+                revealage_view,
+            ]
+        } else {
+            [
+                accumulation_view.clone(),
+                accumulation_view,
+                revealage_view.clone(),
+                revealage_view,
+            ]
+        }
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        [_, accumulation_view, _, revealage_view]: &[wgpu::TextureView; 4],
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("transparency composite bind group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(accumulation_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(revealage_view),
+                },
+            ],
+        })
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        let targets = Self::create_targets(device, width, height, self.sample_count);
+        self.bind_group = Self::create_bind_group(device, &self.bind_group_layout, &targets);
+        [
+            self.accumulation_msaa_view,
+            self.accumulation_view,
+            self.revealage_msaa_view,
+            self.revealage_view,
+        ] = targets;
+    }
+
+    /// Clears the accumulation to zero and the revealage to one.
+    pub fn clear(&self, encoder: &mut wgpu::CommandEncoder) {
+        let msaa = self.sample_count > 1;
+        let attachment = |view, resolve_target, clear| {
+            Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: if msaa { Some(resolve_target) } else { None },
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(clear),
+                    store: wgpu::StoreOp::Store,
+                },
+            })
+        };
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("transparency clear"),
+            color_attachments: &[
+                attachment(
+                    &self.accumulation_msaa_view,
+                    &self.accumulation_view,
+                    wgpu::Color::TRANSPARENT,
+                ),
+                attachment(
+                    &self.revealage_msaa_view,
+                    &self.revealage_view,
+                    wgpu::Color::WHITE,
+                ),
+            ],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+            multiview_mask: None,
+        });
+    }
+
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, hdr_view: &wgpu::TextureView) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("transparency composite pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: hdr_view,
+                resolve_target: None,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+            multiview_mask: None,
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
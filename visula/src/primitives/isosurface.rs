@@ -0,0 +1,173 @@
+use glam::Vec3;
+use ndarray::Array3;
+
+use super::MeshVertexAttributes;
+
+/// Offsets of the corners of a cell, indexed by `x + 2 y + 4 z`.
+const CORNERS: [[usize; 3]; 8] = [
+    [0, 0, 0],
+    [1, 0, 0],
+    [0, 1, 0],
+    [1, 1, 0],
+    [0, 0, 1],
+    [1, 0, 1],
+    [0, 1, 1],
+    [1, 1, 1],
+];
+
+/// The corners connected by each of the twelve edges of a cell.
+const EDGES: [[usize; 2]; 12] = [
+    [0, 1],
+    [2, 3],
+    [4, 5],
+    [6, 7],
+    [0, 2],
+    [1, 3],
+    [4, 6],
+    [5, 7],
+    [0, 4],
+    [1, 5],
+    [2, 6],
+    [3, 7],
+];
+
+/// Extracts the surface where `values` cross `iso_level`, ready for
+/// [`crate::MeshPipeline::set_mesh_data`].
+///
+/// Uses surface nets, a simple form of dual contouring: every cell the surface passes through
+/// gets one vertex at the average of the points where the surface crosses its edges, and
+/// every crossed grid edge gets a quad connecting the four cells around it. The vertices are
+/// shared between faces, and the normals are taken from the gradient of the values, pointing
+/// from values above `iso_level` towards values below it.
+///
+/// Positions are in grid units, with the first axis of `values` along x, the second along y
+/// and the third along z.
+pub fn generate_isosurface(
+    values: &Array3<f32>,
+    iso_level: f32,
+    color: [u8; 4],
+) -> (Vec<MeshVertexAttributes>, Vec<u32>) {
+    let shape = values.dim();
+    let [nx, ny, nz] = [shape.0, shape.1, shape.2];
+    if nx < 2 || ny < 2 || nz < 2 {
+        return (Vec::new(), Vec::new());
+    }
+    // Central differences, falling back to one-sided differences at the borders.
+    let gradient = |[x, y, z]: [usize; 3]| {
+        let difference = |index: usize, size: usize, at: &dyn Fn(usize) -> f32| {
+            let before = index.saturating_sub(1);
+            let after = (index + 1).min(size - 1);
+            (at(after) - at(before)) / (after - before) as f32
+        };
+        Vec3::new(
+            difference(x, nx, &|i| values[[i, y, z]]),
+            difference(y, ny, &|j| values[[x, j, z]]),
+            difference(z, nz, &|k| values[[x, y, k]]),
+        )
+    };
+    let point = |[x, y, z]: [usize; 3]| Vec3::new(x as f32, y as f32, z as f32);
+
+    let cell_index = |[x, y, z]: [usize; 3]| x + (nx - 1) * (y + (ny - 1) * z);
+    let mut cell_vertices = vec![u32::MAX; (nx - 1) * (ny - 1) * (nz - 1)];
+    let mut vertices = Vec::new();
+    for z in 0..nz - 1 {
+        for y in 0..ny - 1 {
+            for x in 0..nx - 1 {
+                let corners = CORNERS.map(|[dx, dy, dz]| [x + dx, y + dy, z + dz]);
+                let corner_values = corners.map(|corner| values[corner]);
+                let inside = corner_values.map(|value| value > iso_level);
+                if inside.iter().all(|&i| i) || inside.iter().all(|&i| !i) {
+                    continue;
+                }
+                let mut position = Vec3::ZERO;
+                let mut normal = Vec3::ZERO;
+                let mut crossings = 0;
+                for [a, b] in EDGES {
+                    if inside[a] == inside[b] {
+                        continue;
+                    }
+                    let t = (iso_level - corner_values[a]) / (corner_values[b] - corner_values[a]);
+                    position += point(corners[a]).lerp(point(corners[b]), t);
+                    normal += gradient(corners[a]).lerp(gradient(corners[b]), t);
+                    crossings += 1;
+                }
+                cell_vertices[cell_index([x, y, z])] = vertices.len() as u32;
+                vertices.push(MeshVertexAttributes {
+                    position: (position / crossings as f32).to_array(),
+                    normal: (-normal).normalize_or_zero().to_array(),
+                    uv: [0.0, 0.0],
+                    color,
+                });
+            }
+        }
+    }
+
+    // Each grid edge along `axis` that crosses the surface is surrounded by the cells offset
+    // by zero or one along the two other axes, taken in the cyclic order so that the quad is
+    // counter-clockwise around the axis.
+    let mut indices = Vec::new();
+    let size = [nx, ny, nz];
+    for z in 0..nz {
+        for y in 0..ny {
+            for x in 0..nx {
+                let point = [x, y, z];
+                for axis in 0..3 {
+                    let (b, c) = ((axis + 1) % 3, (axis + 2) % 3);
+                    // Edges on the border of the grid only touch cells on one side.
+                    if point[axis] + 1 >= size[axis]
+                        || point[b] == 0
+                        || point[c] == 0
+                        || point[b] + 1 >= size[b]
+                        || point[c] + 1 >= size[c]
+                    {
+                        continue;
+                    }
+                    let mut next = point;
+                    next[axis] += 1;
+                    let inside = values[point] > iso_level;
+                    if inside == (values[next] > iso_level) {
+                        continue;
+                    }
+                    let cell = |offset_b: usize, offset_c: usize| {
+                        let mut cell = point;
+                        cell[b] -= 1 - offset_b;
+                        cell[c] -= 1 - offset_c;
+                        cell_vertices[cell_index(cell)]
+                    };
+                    let quad = [cell(0, 0), cell(1, 0), cell(1, 1), cell(0, 1)];
+                    // Flip the quad when the values rise above the level going along the axis,
+                    // so that it faces towards the lower values.
+                    let [q0, q1, q2, q3] = if inside {
+                        quad
+                    } else {
+                        [quad[0], quad[3], quad[2], quad[1]]
+                    };
+                    indices.extend_from_slice(&[q0, q1, q2, q0, q2, q3]);
+                }
+            }
+        }
+    }
+
+    (vertices, indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_isosurface_cutting_border() {
+        // A plane across the whole grid, reaching the far border along y and z.
+        let values = Array3::from_shape_fn((4, 4, 4), |(x, _, _)| x as f32);
+        let (vertices, indices) = generate_isosurface(&values, 1.5, [255; 4]);
+        assert_eq!(vertices.len(), 9);
+        assert_eq!(indices.len(), 4 * 6);
+        assert!(indices
+            .iter()
+            .all(|&index| (index as usize) < vertices.len()));
+        for vertex in &vertices {
+            assert!((vertex.position[0] - 1.5).abs() < 1e-6);
+            assert_eq!(vertex.normal, [-1.0, 0.0, 0.0]);
+        }
+    }
+}
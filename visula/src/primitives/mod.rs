@@ -1,7 +1,11 @@
 pub mod geometry;
+pub mod isosurface;
 pub mod mesh_primitive;
 pub mod sphere_primitive;
+pub mod tube;
 
 pub use geometry::*;
+pub use isosurface::*;
 pub use mesh_primitive::*;
 pub use sphere_primitive::*;
+pub use tube::*;
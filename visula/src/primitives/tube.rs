@@ -0,0 +1,190 @@
+use std::ops::{Add, Mul};
+
+use glam::{Quat, Vec3};
+
+use super::MeshVertexAttributes;
+
+/// A control point of a tube, see [`generate_tube`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TubePoint {
+    pub position: Vec3,
+    pub radius: f32,
+    pub color: Vec3,
+}
+
+/// Evaluates the Catmull-Rom spline through `values` between the two middle ones, at `u` in
+/// `[0, 1]`, with the given knots, using the Barry-Goldman pyramidal formulation.
+fn catmull_rom<T>(values: [T; 4], knots: [f32; 4], u: f32) -> T
+where
+    T: Copy + Add<Output = T> + Mul<f32, Output = T>,
+{
+    let [t0, t1, t2, t3] = knots;
+    let t = t1 + u * (t2 - t1);
+    let lerp =
+        |a: T, b: T, ta: f32, tb: f32| a * ((tb - t) / (tb - ta)) + b * ((t - ta) / (tb - ta));
+    let a1 = lerp(values[0], values[1], t0, t1);
+    let a2 = lerp(values[1], values[2], t1, t2);
+    let a3 = lerp(values[2], values[3], t2, t3);
+    let b1 = lerp(a1, a2, t0, t2);
+    let b2 = lerp(a2, a3, t1, t3);
+    lerp(b1, b2, t1, t2)
+}
+
+/// Samples the centripetal Catmull-Rom spline through `points`, with `subdivisions` samples
+/// per segment. The radius and color are interpolated with the same spline as the position.
+fn sample_spline(points: &[TubePoint], subdivisions: usize) -> Vec<TubePoint> {
+    let count = points.len();
+    // Phantom points mirror the second and second to last points around the ends, so that the
+    // spline passes through every control point.
+    let point = |index: isize| -> TubePoint {
+        let mirror = |inner: &TubePoint, end: &TubePoint| TubePoint {
+            position: 2.0 * end.position - inner.position,
+            radius: end.radius,
+            color: end.color,
+        };
+        if index < 0 {
+            mirror(&points[1], &points[0])
+        } else if index as usize >= count {
+            mirror(&points[count - 2], &points[count - 1])
+        } else {
+            points[index as usize]
+        }
+    };
+
+    let mut samples = Vec::with_capacity((count - 1) * subdivisions + 1);
+    for segment in 0..count - 1 {
+        let controls = [-1, 0, 1, 2].map(|offset| point(segment as isize + offset));
+        let mut knots = [0.0; 4];
+        for i in 1..4 {
+            let distance = controls[i].position.distance(controls[i - 1].position);
+            knots[i] = knots[i - 1] + distance.sqrt().max(1e-6);
+        }
+        for step in 0..subdivisions {
+            let u = step as f32 / subdivisions as f32;
+            samples.push(TubePoint {
+                position: catmull_rom(controls.map(|c| c.position), knots, u),
+                radius: catmull_rom(controls.map(|c| c.radius), knots, u).max(0.0),
+                color: catmull_rom(controls.map(|c| c.color), knots, u)
+                    .clamp(Vec3::ZERO, Vec3::ONE),
+            });
+        }
+    }
+    samples.push(points[count - 1]);
+    samples
+}
+
+/// Generates a smooth tube through `points`, for use with [`crate::MeshPipeline`].
+///
+/// The points are interpolated with a centripetal Catmull-Rom spline, sampled `subdivisions`
+/// times between each pair of points, and swept with a circle of `sides` segments. The circle
+/// is carried along the spline with parallel transport to avoid twisting, and the ends are
+/// closed with flat caps. Consecutive points at the same position are merged, and nothing is
+/// generated for fewer than two distinct points.
+pub fn generate_tube(
+    points: &[TubePoint],
+    subdivisions: usize,
+    sides: usize,
+) -> (Vec<MeshVertexAttributes>, Vec<u32>) {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+
+    let mut distinct: Vec<TubePoint> = Vec::with_capacity(points.len());
+    for point in points {
+        if distinct
+            .last()
+            .is_none_or(|last| last.position != point.position)
+        {
+            distinct.push(*point);
+        }
+    }
+    if distinct.len() < 2 || sides < 3 {
+        return (vertices, indices);
+    }
+    let samples = sample_spline(&distinct, subdivisions.max(1));
+
+    let count = samples.len();
+    let mut tangents: Vec<Vec3> = Vec::with_capacity(count);
+    for i in 0..count {
+        let before = samples[i.saturating_sub(1)].position;
+        let after = samples[(i + 1).min(count - 1)].position;
+        let fallback = tangents.last().copied().unwrap_or(Vec3::X);
+        tangents.push((after - before).normalize_or(fallback));
+    }
+    let mut arc_lengths = vec![0.0; count];
+    for i in 1..count {
+        arc_lengths[i] = arc_lengths[i - 1] + samples[i].position.distance(samples[i - 1].position);
+    }
+    let total_length = arc_lengths[count - 1].max(f32::EPSILON);
+
+    let color = |color: Vec3| {
+        let [r, g, b] = (color * 255.0).round().to_array().map(|c| c as u8);
+        [r, g, b, 255]
+    };
+
+    let mut normal = tangents[0].any_orthonormal_vector();
+    let mut previous_tangent = tangents[0];
+    for (i, sample) in samples.iter().enumerate() {
+        let tangent = tangents[i];
+        normal = Quat::from_rotation_arc(previous_tangent, tangent) * normal;
+        normal = (normal - normal.dot(tangent) * tangent).normalize_or(normal);
+        previous_tangent = tangent;
+        let binormal = tangent.cross(normal);
+
+        // Where the radius changes along the tube, the surface normal tilts towards the
+        // narrowing end.
+        let before = i.saturating_sub(1);
+        let after = (i + 1).min(count - 1);
+        let slope = (samples[after].radius - samples[before].radius)
+            / (arc_lengths[after] - arc_lengths[before]).max(f32::EPSILON);
+
+        for j in 0..=sides {
+            let angle = j as f32 / sides as f32 * std::f32::consts::TAU;
+            let radial = angle.cos() * normal + angle.sin() * binormal;
+            vertices.push(MeshVertexAttributes {
+                position: (sample.position + sample.radius * radial).to_array(),
+                normal: (radial - slope * tangent).normalize().to_array(),
+                uv: [arc_lengths[i] / total_length, j as f32 / sides as f32],
+                color: color(sample.color),
+            });
+        }
+    }
+
+    let ring = sides as u32 + 1;
+    for i in 0..count as u32 - 1 {
+        for j in 0..sides as u32 {
+            let a = i * ring + j;
+            let b = (i + 1) * ring + j;
+            indices.extend_from_slice(&[a, a + 1, b + 1, a, b + 1, b]);
+        }
+    }
+
+    for (sample, tangent, ring_index) in [
+        (&samples[0], -tangents[0], 0),
+        (&samples[count - 1], tangents[count - 1], count - 1),
+    ] {
+        let center = vertices.len() as u32;
+        vertices.push(MeshVertexAttributes {
+            position: sample.position.to_array(),
+            normal: tangent.to_array(),
+            uv: [0.5, 0.5],
+            color: color(sample.color),
+        });
+        for j in 0..sides {
+            let mut vertex = vertices[ring_index * ring as usize + j];
+            vertex.normal = tangent.to_array();
+            vertices.push(vertex);
+        }
+        for j in 0..sides as u32 {
+            let current = center + 1 + j;
+            let next = center + 1 + (j + 1) % sides as u32;
+            // The start cap faces backwards along the tube, so it is wound the other way.
+            if ring_index == 0 {
+                indices.extend_from_slice(&[center, next, current]);
+            } else {
+                indices.extend_from_slice(&[center, current, next]);
+            }
+        }
+    }
+
+    (vertices, indices)
+}
@@ -59,3 +59,65 @@ impl DefaultRenderPassDescriptor<'_> {
         }
     }
 }
+
+/// Render pass into the order-independent transparency targets of [`crate::TransparentRenderData`],
+/// testing against the depth of the opaque geometry.
+pub struct TransparentRenderPassDescriptor<'a> {
+    label: String,
+    color_attachments: [Option<wgpu::RenderPassColorAttachment<'a>>; 2],
+    depth_texture: &'a wgpu::TextureView,
+}
+
+impl TransparentRenderPassDescriptor<'_> {
+    pub fn new<'a>(
+        label: &'a str,
+        accumulation: &'a wgpu::TextureView,
+        accumulation_resolve: Option<&'a wgpu::TextureView>,
+        revealage: &'a wgpu::TextureView,
+        revealage_resolve: Option<&'a wgpu::TextureView>,
+        depth_texture: &'a wgpu::TextureView,
+    ) -> TransparentRenderPassDescriptor<'a> {
+        let color_attachments = [
+            Some(wgpu::RenderPassColorAttachment {
+                view: accumulation,
+                resolve_target: accumulation_resolve,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            }),
+            Some(wgpu::RenderPassColorAttachment {
+                view: revealage,
+                resolve_target: revealage_resolve,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            }),
+        ];
+        TransparentRenderPassDescriptor {
+            color_attachments,
+            label: label.to_string(),
+            depth_texture,
+        }
+    }
+    pub fn build(&self) -> RenderPassDescriptor<'_> {
+        wgpu::RenderPassDescriptor {
+            label: Some(&self.label),
+            color_attachments: &self.color_attachments,
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: self.depth_texture,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+            multiview_mask: None,
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::application::Application;
+use crate::application::{Application, RecordingConfig};
 use crate::post_process::config::{
     BloomConfig, OutlineConfig, SkyConfig, SkyMode, SsaoConfig, Tonemapping,
 };
@@ -11,6 +11,9 @@ pub struct RenderingControls {
     sky_config_update: Option<SkyConfig>,
     tonemapping_update: Option<Tonemapping>,
     outline_config: OutlineConfig,
+    recording_toggle_requested: Option<bool>,
+    recording_config: RecordingConfig,
+    recording_directory: String,
     initialized: bool,
 }
 
@@ -24,6 +27,12 @@ impl RenderingControls {
             sky_config_update: None,
             tonemapping_update: None,
             outline_config: OutlineConfig::default(),
+            recording_toggle_requested: None,
+            recording_config: RecordingConfig::default(),
+            recording_directory: RecordingConfig::default()
+                .directory
+                .to_string_lossy()
+                .into_owned(),
             initialized: false,
         }
     }
@@ -33,7 +42,12 @@ impl RenderingControls {
 
         ui.collapsing("Sky", |ui| {
             let current_mode = config.sky.mode;
-            for mode in [SkyMode::Off, SkyMode::NormalMap, SkyMode::SkyGround] {
+            for mode in [
+                SkyMode::Off,
+                SkyMode::NormalMap,
+                SkyMode::SkyGround,
+                SkyMode::Environment,
+            ] {
                 if ui
                     .selectable_label(current_mode == mode, mode.to_string())
                     .clicked()
@@ -100,6 +114,31 @@ impl RenderingControls {
                 ui.color_edit_button_rgb(&mut self.outline_config.color);
             });
         });
+
+        ui.collapsing("Recording", |ui| {
+            let mut recording = application.is_recording();
+            if ui.checkbox(&mut recording, "Record").changed() {
+                self.recording_toggle_requested = Some(recording);
+            }
+            ui.add_enabled_ui(!application.is_recording(), |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Directory");
+                    ui.text_edit_singleline(&mut self.recording_directory);
+                });
+                ui.add(
+                    egui::Slider::new(&mut self.recording_config.frame_interval, 1..=60)
+                        .text("Every Nth frame"),
+                );
+                ui.add(
+                    egui::Slider::new(&mut self.recording_config.time_step, 0.001..=0.1)
+                        .logarithmic(true)
+                        .text("Time step"),
+                );
+            });
+            if let Some(frames) = application.recorded_frames() {
+                ui.label(format!("{frames} frames written"));
+            }
+        });
     }
 
     pub fn update(&mut self, application: &mut Application) {
@@ -151,6 +190,16 @@ impl RenderingControls {
             application.post_processor.config.bloom = Some(self.bloom_config.clone());
         }
         application.post_processor.config.outline = self.outline_config.clone();
+        if let Some(enable) = self.recording_toggle_requested.take() {
+            if enable {
+                self.recording_config.directory = self.recording_directory.clone().into();
+                if let Err(e) = application.start_recording(self.recording_config.clone()) {
+                    log::error!("Failed to start recording: {e}");
+                }
+            } else {
+                application.stop_recording();
+            }
+        }
     }
 }
 
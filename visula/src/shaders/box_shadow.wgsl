@@ -0,0 +1,227 @@
+struct Light {
+    direction: vec3<f32>,
+    _pad0: f32,
+    color: vec3<f32>,
+    intensity: f32,
+    light_view_proj: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> u_light: Light;
+
+struct VertexOutput {
+    @builtin(position) proj_position: vec4<f32>,
+    @location(0) vertex_position: vec3<f32>,
+    @location(1) box_center: vec3<f32>,
+    @location(2) half_size: vec3<f32>,
+    @location(3) corner_radius: f32,
+    @location(4) input_color: vec3<f32>,
+    @location(5) box_right: vec3<f32>,
+    @location(6) box_up: vec3<f32>,
+    @location(7) box_forward: vec3<f32>,
+};
+
+struct BoxGeometry {
+    position: vec3<f32>,
+    rotation: vec4<f32>,
+    size: vec3<f32>,
+    corner_radius: f32,
+    color: vec3<f32>,
+};
+
+struct FragmentOutput {
+    @builtin(frag_depth) depth: f32,
+};
+
+fn quat_rotate(q: vec4<f32>, v: vec3<f32>) -> vec3<f32> {
+    let u = q.xyz;
+    let s = q.w;
+    return 2.0 * dot(u, v) * u + (s * s - dot(u, u)) * v + 2.0 * s * cross(u, v);
+}
+
+fn boxes_shadow(
+    vertex_position: vec3<f32>,
+    box: BoxGeometry,
+) -> VertexOutput {
+    var output: VertexOutput;
+
+    let right = quat_rotate(box.rotation, vec3<f32>(1.0, 0.0, 0.0));
+    let up = quat_rotate(box.rotation, vec3<f32>(0.0, 1.0, 0.0));
+    let forward = quat_rotate(box.rotation, vec3<f32>(0.0, 0.0, 1.0));
+
+    let half_size = 0.5 * box.size;
+    let padding = 0.1 * max(max(half_size.x, half_size.y), half_size.z);
+    let extent = half_size + padding;
+
+    let world_pos = box.position
+        + right * vertex_position.x * extent.x
+        + up * vertex_position.y * extent.y
+        + forward * vertex_position.z * extent.z;
+
+    output.proj_position = u_light.light_view_proj * vec4<f32>(world_pos, 1.0);
+    output.vertex_position = world_pos;
+    output.box_center = box.position;
+    output.half_size = half_size;
+    output.corner_radius = clamp(box.corner_radius, 0.0, min(min(half_size.x, half_size.y), half_size.z));
+    output.input_color = box.color;
+    output.box_right = right;
+    output.box_up = up;
+    output.box_forward = forward;
+
+    return output;
+}
+
+@vertex
+fn vs_main(
+    @location(0) vertex_position: vec3<f32>,
+) -> VertexOutput {
+    var box_geometry: BoxGeometry;
+    return boxes_shadow(vertex_position, box_geometry);
+}
+
+// Distance to where the ray enters the sphere and the normal there, or a negative distance if
+// the ray misses. The ray direction must be normalized.
+fn sphere_entry(
+    ray_origin: vec3<f32>,
+    ray_dir: vec3<f32>,
+    center: vec3<f32>,
+    radius: f32,
+) -> vec4<f32> {
+    let oc = ray_origin - center;
+    let b = dot(oc, ray_dir);
+    let c = dot(oc, oc) - radius * radius;
+    let h = b * b - c;
+    if h < 0.0 {
+        return vec4<f32>(-1e30, 0.0, 0.0, 0.0);
+    }
+    let t = -b - sqrt(h);
+    return vec4<f32>(t, (oc + t * ray_dir) / radius);
+}
+
+// Like sphere_entry, for the side of a cylinder around `axis` through `center`, extending
+// `half_length` along the axis in both directions. The ends are left open.
+fn cylinder_entry(
+    ray_origin: vec3<f32>,
+    ray_dir: vec3<f32>,
+    center: vec3<f32>,
+    axis: vec3<f32>,
+    half_length: f32,
+    radius: f32,
+) -> vec4<f32> {
+    let oc = ray_origin - center;
+    let oc_dot_axis = dot(oc, axis);
+    let ray_dot_axis = dot(ray_dir, axis);
+    let a = 1.0 - ray_dot_axis * ray_dot_axis;
+    let b = dot(oc, ray_dir) - oc_dot_axis * ray_dot_axis;
+    let c = dot(oc, oc) - oc_dot_axis * oc_dot_axis - radius * radius;
+    let h = b * b - a * c;
+    if a < 1e-8 || h < 0.0 {
+        return vec4<f32>(-1e30, 0.0, 0.0, 0.0);
+    }
+    let t = (-b - sqrt(h)) / a;
+    let y = oc_dot_axis + t * ray_dot_axis;
+    if abs(y) > half_length {
+        return vec4<f32>(-1e30, 0.0, 0.0, 0.0);
+    }
+    return vec4<f32>(t, (oc + t * ray_dir - y * axis) / radius);
+}
+
+// Like sphere_entry, for an axis-aligned box centered at the origin.
+fn box_entry(
+    ray_origin: vec3<f32>,
+    ray_dir: vec3<f32>,
+    half_size: vec3<f32>,
+) -> vec4<f32> {
+    let inverse_dir = 1.0 / ray_dir;
+    let t0 = (-half_size - ray_origin) * inverse_dir;
+    let t1 = (half_size - ray_origin) * inverse_dir;
+    let t_min = min(t0, t1);
+    let t_max = max(t0, t1);
+    let t_near = max(max(t_min.x, t_min.y), t_min.z);
+    let t_far = min(min(t_max.x, t_max.y), t_max.z);
+    if t_near > t_far {
+        return vec4<f32>(-1e30, 0.0, 0.0, 0.0);
+    }
+    let normal = select(vec3<f32>(0.0), -sign(ray_dir), t_min >= vec3<f32>(t_near));
+    return vec4<f32>(t_near, normal);
+}
+
+// Keeps the closest of two entries in front of the ray origin.
+fn closest_entry(a: vec4<f32>, b: vec4<f32>) -> vec4<f32> {
+    if b.x > 0.0 && (a.x <= 0.0 || b.x < a.x) {
+        return b;
+    }
+    return a;
+}
+
+// The rounded box is the inner box, with the corner radius subtracted from the size, swept by
+// a sphere of the corner radius. This is the union of the inner box grown along each of the
+// axes, a cylinder along each of the twelve edges and a sphere at each of the eight corners.
+fn intersect_box(
+    ray_origin: vec3<f32>,
+    ray_dir: vec3<f32>,
+    in: VertexOutput,
+) -> vec4<f32> {
+    let oc = ray_origin - in.box_center;
+    let o = vec3<f32>(
+        dot(oc, in.box_right),
+        dot(oc, in.box_up),
+        dot(oc, in.box_forward),
+    );
+    let d = vec3<f32>(
+        dot(ray_dir, in.box_right),
+        dot(ray_dir, in.box_up),
+        dot(ray_dir, in.box_forward),
+    );
+    let r = in.corner_radius;
+    let inner = in.half_size - r;
+
+    var axes = array<vec3<f32>, 3>(
+        vec3<f32>(1.0, 0.0, 0.0),
+        vec3<f32>(0.0, 1.0, 0.0),
+        vec3<f32>(0.0, 0.0, 1.0),
+    );
+    var result = vec4<f32>(-1e30, 0.0, 0.0, 0.0);
+    for (var axis = 0; axis < 3; axis++) {
+        result = closest_entry(result, box_entry(o, d, inner + r * axes[axis]));
+    }
+    if r > 0.0 {
+        for (var axis = 0; axis < 3; axis++) {
+            let b = axes[(axis + 1) % 3] * inner;
+            let c = axes[(axis + 2) % 3] * inner;
+            for (var edge = 0; edge < 4; edge++) {
+                let center = select(-b, b, (edge & 1) != 0) + select(-c, c, (edge & 2) != 0);
+                result = closest_entry(result, cylinder_entry(o, d, center, axes[axis], inner[axis], r));
+            }
+        }
+        for (var corner = 0; corner < 8; corner++) {
+            let signs = vec3<f32>(
+                select(-1.0, 1.0, (corner & 1) != 0),
+                select(-1.0, 1.0, (corner & 2) != 0),
+                select(-1.0, 1.0, (corner & 4) != 0),
+            );
+            result = closest_entry(result, sphere_entry(o, d, signs * inner, r));
+        }
+    }
+
+    let normal = result.y * in.box_right + result.z * in.box_up + result.w * in.box_forward;
+    return vec4<f32>(result.x, normal);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> FragmentOutput {
+    let ray_direction = normalize(u_light.direction);
+    let ray_origin = in.vertex_position;
+    let t = intersect_box(ray_origin, ray_direction, in).x;
+
+    if t <= 0.0 {
+        discard;
+    }
+
+    let hit_pos = ray_origin + t * ray_direction;
+    let clip = u_light.light_view_proj * vec4<f32>(hit_pos, 1.0);
+
+    var output: FragmentOutput;
+    output.depth = clip.z / clip.w;
+    return output;
+}
@@ -0,0 +1,164 @@
+struct Light {
+    direction: vec3<f32>,
+    _pad0: f32,
+    color: vec3<f32>,
+    intensity: f32,
+    light_view_proj: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> u_light: Light;
+
+struct VertexOutput {
+    @builtin(position) proj_position: vec4<f32>,
+    @location(0) vertex_position: vec3<f32>,
+    @location(1) capsule_start: vec3<f32>,
+    @location(2) capsule_end: vec3<f32>,
+    @location(3) radius: f32,
+    @location(4) input_color: vec3<f32>,
+};
+
+struct CapsuleGeometry {
+    start: vec3<f32>,
+    end: vec3<f32>,
+    radius: f32,
+    color: vec3<f32>,
+};
+
+struct FragmentOutput {
+    @builtin(frag_depth) depth: f32,
+};
+
+fn capsules_shadow(
+    vertex_position: vec3<f32>,
+    capsule: CapsuleGeometry,
+) -> VertexOutput {
+    var output: VertexOutput;
+
+    let axis = capsule.end - capsule.start;
+    let height = length(axis);
+    let dir = axis / max(height, 0.0001);
+
+    let up_candidate = select(vec3<f32>(0.0, 1.0, 0.0), vec3<f32>(1.0, 0.0, 0.0), abs(dot(dir, vec3<f32>(0.0, 1.0, 0.0))) > 0.99);
+    let right = normalize(cross(dir, up_candidate));
+    let up = normalize(cross(right, dir));
+
+    let padding = capsule.radius * 0.5;
+
+    let t = vertex_position.y * 0.5 + 0.5;
+    let center = capsule.start + axis * t;
+    let world_pos = center
+        + right * vertex_position.x * (capsule.radius + padding)
+        + up * vertex_position.z * (capsule.radius + padding)
+        + dir * vertex_position.y * (capsule.radius + padding);
+
+    output.proj_position = u_light.light_view_proj * vec4<f32>(world_pos, 1.0);
+    output.vertex_position = world_pos;
+    output.capsule_start = capsule.start;
+    output.capsule_end = capsule.end;
+    output.radius = capsule.radius;
+    output.input_color = capsule.color;
+
+    return output;
+}
+
+@vertex
+fn vs_main(
+    @location(0) vertex_position: vec3<f32>,
+) -> VertexOutput {
+    var capsule_geometry: CapsuleGeometry;
+    return capsules_shadow(vertex_position, capsule_geometry);
+}
+
+// Distance to where the ray enters the sphere and the normal there, or a negative distance if
+// the ray misses. The ray direction must be normalized.
+fn sphere_entry(
+    ray_origin: vec3<f32>,
+    ray_dir: vec3<f32>,
+    center: vec3<f32>,
+    radius: f32,
+) -> vec4<f32> {
+    let oc = ray_origin - center;
+    let b = dot(oc, ray_dir);
+    let c = dot(oc, oc) - radius * radius;
+    let h = b * b - c;
+    if h < 0.0 {
+        return vec4<f32>(-1e30, 0.0, 0.0, 0.0);
+    }
+    let t = -b - sqrt(h);
+    return vec4<f32>(t, (oc + t * ray_dir) / radius);
+}
+
+// Like sphere_entry, for the side of a cylinder around `axis` through `center`, extending
+// `half_length` along the axis in both directions. The ends are left open.
+fn cylinder_entry(
+    ray_origin: vec3<f32>,
+    ray_dir: vec3<f32>,
+    center: vec3<f32>,
+    axis: vec3<f32>,
+    half_length: f32,
+    radius: f32,
+) -> vec4<f32> {
+    let oc = ray_origin - center;
+    let oc_dot_axis = dot(oc, axis);
+    let ray_dot_axis = dot(ray_dir, axis);
+    let a = 1.0 - ray_dot_axis * ray_dot_axis;
+    let b = dot(oc, ray_dir) - oc_dot_axis * ray_dot_axis;
+    let c = dot(oc, oc) - oc_dot_axis * oc_dot_axis - radius * radius;
+    let h = b * b - a * c;
+    if a < 1e-8 || h < 0.0 {
+        return vec4<f32>(-1e30, 0.0, 0.0, 0.0);
+    }
+    let t = (-b - sqrt(h)) / a;
+    let y = oc_dot_axis + t * ray_dot_axis;
+    if abs(y) > half_length {
+        return vec4<f32>(-1e30, 0.0, 0.0, 0.0);
+    }
+    return vec4<f32>(t, (oc + t * ray_dir - y * axis) / radius);
+}
+
+// Keeps the closest of two entries in front of the ray origin.
+fn closest_entry(a: vec4<f32>, b: vec4<f32>) -> vec4<f32> {
+    if b.x > 0.0 && (a.x <= 0.0 || b.x < a.x) {
+        return b;
+    }
+    return a;
+}
+
+// The capsule is the union of a cylinder and the spheres at its ends, so the ray enters it
+// where it first enters any of them.
+fn intersect_capsule(
+    ray_origin: vec3<f32>,
+    ray_dir: vec3<f32>,
+    capsule_start: vec3<f32>,
+    capsule_end: vec3<f32>,
+    radius: f32,
+) -> vec4<f32> {
+    let axis = capsule_end - capsule_start;
+    let height = length(axis);
+    var result = sphere_entry(ray_origin, ray_dir, capsule_start, radius);
+    result = closest_entry(result, sphere_entry(ray_origin, ray_dir, capsule_end, radius));
+    if height > 0.0 {
+        let center = 0.5 * (capsule_start + capsule_end);
+        result = closest_entry(result, cylinder_entry(ray_origin, ray_dir, center, axis / height, 0.5 * height, radius));
+    }
+    return result;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> FragmentOutput {
+    let ray_direction = normalize(u_light.direction);
+    let ray_origin = in.vertex_position;
+    let t = intersect_capsule(ray_origin, ray_direction, in.capsule_start, in.capsule_end, in.radius).x;
+
+    if t <= 0.0 {
+        discard;
+    }
+
+    let hit_pos = ray_origin + t * ray_direction;
+    let clip = u_light.light_view_proj * vec4<f32>(hit_pos, 1.0);
+
+    var output: FragmentOutput;
+    output.depth = clip.z / clip.w;
+    return output;
+}
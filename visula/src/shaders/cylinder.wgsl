@@ -35,6 +35,7 @@ struct CylinderGeometry {
 
 struct CylinderMaterial {
     color: vec3<f32>,
+    alpha: f32,
 };
 
 fn cylinders(
@@ -196,7 +197,7 @@ fn fs_main(in: VertexOutput) -> FragmentOutput {
     var cylinder_material: CylinderMaterial;
 
     var output: FragmentOutput;
-    output.color = vec4<f32>(cylinder_material.color, 1.0);
+    output.color = vec4<f32>(cylinder_material.color, cylinder_material.alpha);
     output.normal = vec4<f32>(_visula_normal, 0.0);
     output.depth = frag_depth;
     return output;
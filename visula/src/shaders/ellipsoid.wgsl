@@ -0,0 +1,157 @@
+struct Camera {
+    view_matrix: mat4x4<f32>,
+    transform: mat4x4<f32>,
+    camera_center: vec4<f32>,
+    camera_view_vector: vec4<f32>,
+    camera_position: vec4<f32>,
+    camera_up: vec4<f32>,
+    inverse_view_proj: mat4x4<f32>,
+    screen_size: vec4<f32>,
+    projection_matrix: mat4x4<f32>,
+    inverse_projection_matrix: mat4x4<f32>,
+};
+
+@group(0)
+@binding(0)
+var<uniform> u_globals: Camera;
+
+struct VertexOutput {
+    @builtin(position) proj_position: vec4<f32>,
+    @location(0) vertex_position: vec3<f32>,
+    @location(1) ellipsoid_center: vec3<f32>,
+    @location(2) radii: vec3<f32>,
+    @location(3) input_color: vec3<f32>,
+    @location(4) ellipsoid_right: vec3<f32>,
+    @location(5) ellipsoid_up: vec3<f32>,
+    @location(6) ellipsoid_forward: vec3<f32>,
+};
+
+struct EllipsoidGeometry {
+    position: vec3<f32>,
+    rotation: vec4<f32>,
+    radii: vec3<f32>,
+    color: vec3<f32>,
+};
+
+struct EllipsoidMaterial {
+    color: vec3<f32>,
+    alpha: f32,
+};
+
+fn quat_rotate(q: vec4<f32>, v: vec3<f32>) -> vec3<f32> {
+    let u = q.xyz;
+    let s = q.w;
+    return 2.0 * dot(u, v) * u + (s * s - dot(u, u)) * v + 2.0 * s * cross(u, v);
+}
+
+fn ellipsoids(
+    vertex_position: vec3<f32>,
+    ellipsoid: EllipsoidGeometry,
+) -> VertexOutput {
+    var output: VertexOutput;
+
+    let right = quat_rotate(ellipsoid.rotation, vec3<f32>(1.0, 0.0, 0.0));
+    let up = quat_rotate(ellipsoid.rotation, vec3<f32>(0.0, 1.0, 0.0));
+    let forward = quat_rotate(ellipsoid.rotation, vec3<f32>(0.0, 0.0, 1.0));
+
+    let padding = 0.1 * max(max(ellipsoid.radii.x, ellipsoid.radii.y), ellipsoid.radii.z);
+    let extent = ellipsoid.radii + padding;
+
+    let world_pos = ellipsoid.position
+        + right * vertex_position.x * extent.x
+        + up * vertex_position.y * extent.y
+        + forward * vertex_position.z * extent.z;
+
+    output.proj_position = u_globals.transform * vec4<f32>(world_pos, 1.0);
+    output.vertex_position = world_pos;
+    output.ellipsoid_center = ellipsoid.position;
+    output.radii = ellipsoid.radii;
+    output.input_color = ellipsoid.color;
+    output.ellipsoid_right = right;
+    output.ellipsoid_up = up;
+    output.ellipsoid_forward = forward;
+
+    return output;
+}
+
+@vertex
+fn vs_main(
+    @location(0) vertex_position: vec3<f32>,
+) -> VertexOutput {
+    var ellipsoid_geometry: EllipsoidGeometry;
+    return ellipsoids(vertex_position, ellipsoid_geometry);
+}
+
+struct FragmentOutput {
+    @location(0) color: vec4<f32>,
+    @location(1) normal: vec4<f32>,
+    @builtin(frag_depth) depth: f32,
+};
+
+// Scales the ray into the space where the ellipsoid is the unit sphere. The distance along
+// the ray is the same in both spaces.
+fn intersect_ellipsoid(
+    ray_origin: vec3<f32>,
+    ray_dir: vec3<f32>,
+    in: VertexOutput,
+) -> vec4<f32> {
+    let oc = ray_origin - in.ellipsoid_center;
+    let local_origin = vec3<f32>(
+        dot(oc, in.ellipsoid_right),
+        dot(oc, in.ellipsoid_up),
+        dot(oc, in.ellipsoid_forward),
+    );
+    let local_dir = vec3<f32>(
+        dot(ray_dir, in.ellipsoid_right),
+        dot(ray_dir, in.ellipsoid_up),
+        dot(ray_dir, in.ellipsoid_forward),
+    );
+    let o = local_origin / in.radii;
+    let d = local_dir / in.radii;
+
+    let a = dot(d, d);
+    let b = dot(o, d);
+    let c = dot(o, o) - 1.0;
+    let h = b * b - a * c;
+    if h < 0.0 {
+        return vec4<f32>(-1e30, 0.0, 0.0, 0.0);
+    }
+    let t = (-b - sqrt(h)) / a;
+
+    let local_n = (local_origin + t * local_dir) / (in.radii * in.radii);
+    let normal = local_n.x * in.ellipsoid_right
+        + local_n.y * in.ellipsoid_up
+        + local_n.z * in.ellipsoid_forward;
+    return vec4<f32>(t, normal);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> FragmentOutput {
+    let ray_origin = in.vertex_position;
+    let ray_direction = normalize(in.vertex_position - u_globals.camera_position.xyz);
+
+    let result = intersect_ellipsoid(ray_origin, ray_direction, in);
+    let t = result.x;
+
+    if t <= 0.0 {
+        discard;
+    }
+
+    let hit_pos = ray_origin + t * ray_direction;
+
+    var _visula_normal: vec3<f32> = normalize(result.yzw);
+    var _visula_position: vec3<f32> = hit_pos;
+    var _visula_view_direction: vec3<f32> = -ray_direction;
+    var _visula_input_color: vec3<f32> = in.input_color;
+
+    let clip_position = u_globals.transform * vec4<f32>(hit_pos, 1.0);
+    let frag_depth = clip_position.z / clip_position.w;
+
+    var ellipsoid_material: EllipsoidMaterial;
+
+    var output: FragmentOutput;
+    output.color = vec4<f32>(ellipsoid_material.color, ellipsoid_material.alpha);
+    output.normal = vec4<f32>(_visula_normal, 0.0);
+    output.depth = frag_depth;
+    return output;
+}
@@ -0,0 +1,131 @@
+struct Light {
+    direction: vec3<f32>,
+    _pad0: f32,
+    color: vec3<f32>,
+    intensity: f32,
+    light_view_proj: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> u_light: Light;
+
+struct VertexOutput {
+    @builtin(position) proj_position: vec4<f32>,
+    @location(0) vertex_position: vec3<f32>,
+    @location(1) ellipsoid_center: vec3<f32>,
+    @location(2) radii: vec3<f32>,
+    @location(3) input_color: vec3<f32>,
+    @location(4) ellipsoid_right: vec3<f32>,
+    @location(5) ellipsoid_up: vec3<f32>,
+    @location(6) ellipsoid_forward: vec3<f32>,
+};
+
+struct EllipsoidGeometry {
+    position: vec3<f32>,
+    rotation: vec4<f32>,
+    radii: vec3<f32>,
+    color: vec3<f32>,
+};
+
+struct FragmentOutput {
+    @builtin(frag_depth) depth: f32,
+};
+
+fn quat_rotate(q: vec4<f32>, v: vec3<f32>) -> vec3<f32> {
+    let u = q.xyz;
+    let s = q.w;
+    return 2.0 * dot(u, v) * u + (s * s - dot(u, u)) * v + 2.0 * s * cross(u, v);
+}
+
+fn ellipsoids_shadow(
+    vertex_position: vec3<f32>,
+    ellipsoid: EllipsoidGeometry,
+) -> VertexOutput {
+    var output: VertexOutput;
+
+    let right = quat_rotate(ellipsoid.rotation, vec3<f32>(1.0, 0.0, 0.0));
+    let up = quat_rotate(ellipsoid.rotation, vec3<f32>(0.0, 1.0, 0.0));
+    let forward = quat_rotate(ellipsoid.rotation, vec3<f32>(0.0, 0.0, 1.0));
+
+    let padding = 0.1 * max(max(ellipsoid.radii.x, ellipsoid.radii.y), ellipsoid.radii.z);
+    let extent = ellipsoid.radii + padding;
+
+    let world_pos = ellipsoid.position
+        + right * vertex_position.x * extent.x
+        + up * vertex_position.y * extent.y
+        + forward * vertex_position.z * extent.z;
+
+    output.proj_position = u_light.light_view_proj * vec4<f32>(world_pos, 1.0);
+    output.vertex_position = world_pos;
+    output.ellipsoid_center = ellipsoid.position;
+    output.radii = ellipsoid.radii;
+    output.input_color = ellipsoid.color;
+    output.ellipsoid_right = right;
+    output.ellipsoid_up = up;
+    output.ellipsoid_forward = forward;
+
+    return output;
+}
+
+@vertex
+fn vs_main(
+    @location(0) vertex_position: vec3<f32>,
+) -> VertexOutput {
+    var ellipsoid_geometry: EllipsoidGeometry;
+    return ellipsoids_shadow(vertex_position, ellipsoid_geometry);
+}
+
+// Scales the ray into the space where the ellipsoid is the unit sphere. The distance along
+// the ray is the same in both spaces.
+fn intersect_ellipsoid(
+    ray_origin: vec3<f32>,
+    ray_dir: vec3<f32>,
+    in: VertexOutput,
+) -> vec4<f32> {
+    let oc = ray_origin - in.ellipsoid_center;
+    let local_origin = vec3<f32>(
+        dot(oc, in.ellipsoid_right),
+        dot(oc, in.ellipsoid_up),
+        dot(oc, in.ellipsoid_forward),
+    );
+    let local_dir = vec3<f32>(
+        dot(ray_dir, in.ellipsoid_right),
+        dot(ray_dir, in.ellipsoid_up),
+        dot(ray_dir, in.ellipsoid_forward),
+    );
+    let o = local_origin / in.radii;
+    let d = local_dir / in.radii;
+
+    let a = dot(d, d);
+    let b = dot(o, d);
+    let c = dot(o, o) - 1.0;
+    let h = b * b - a * c;
+    if h < 0.0 {
+        return vec4<f32>(-1e30, 0.0, 0.0, 0.0);
+    }
+    let t = (-b - sqrt(h)) / a;
+
+    let local_n = (local_origin + t * local_dir) / (in.radii * in.radii);
+    let normal = local_n.x * in.ellipsoid_right
+        + local_n.y * in.ellipsoid_up
+        + local_n.z * in.ellipsoid_forward;
+    return vec4<f32>(t, normal);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> FragmentOutput {
+    let ray_direction = normalize(u_light.direction);
+    let ray_origin = in.vertex_position;
+    let t = intersect_ellipsoid(ray_origin, ray_direction, in).x;
+
+    if t <= 0.0 {
+        discard;
+    }
+
+    let hit_pos = ray_origin + t * ray_direction;
+    let clip = u_light.light_view_proj * vec4<f32>(hit_pos, 1.0);
+
+    var output: FragmentOutput;
+    output.depth = clip.z / clip.w;
+    return output;
+}
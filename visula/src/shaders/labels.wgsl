@@ -0,0 +1,137 @@
+struct Camera {
+    view_matrix: mat4x4<f32>,
+    transform: mat4x4<f32>,
+    camera_center: vec4<f32>,
+    camera_view_vector: vec4<f32>,
+    camera_position: vec4<f32>,
+    camera_up: vec4<f32>,
+    inverse_view_proj: mat4x4<f32>,
+    screen_size: vec4<f32>,
+    projection_matrix: mat4x4<f32>,
+    inverse_projection_matrix: mat4x4<f32>,
+};
+
+@group(0)
+@binding(0)
+var<uniform> u_globals: Camera;
+
+struct Glyph {
+    min: vec2<f32>,
+    max: vec2<f32>,
+    uv_min: vec2<f32>,
+    uv_max: vec2<f32>,
+};
+
+struct LabelParameters {
+    spread: f32,
+    screen_space: u32,
+};
+
+// The glyphs of all labels, one after the other.
+@group(1)
+@binding(0)
+var<storage, read> u_glyphs: array<Glyph>;
+
+// The first glyph and glyph count of each label.
+@group(1)
+@binding(1)
+var<storage, read> u_ranges: array<vec2<u32>>;
+
+@group(1)
+@binding(2)
+var<uniform> u_parameters: LabelParameters;
+
+@group(1)
+@binding(3)
+var u_atlas: texture_2d<f32>;
+
+@group(1)
+@binding(4)
+var u_atlas_sampler: sampler;
+
+struct LabelGeometry {
+    position: vec3<f32>,
+    size: f32,
+    offset: vec2<f32>,
+    color: vec3<f32>,
+    outline_color: vec3<f32>,
+    outline_width: f32,
+};
+
+struct VertexOutput {
+    @builtin(position) proj_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec3<f32>,
+    @location(2) outline_color: vec3<f32>,
+    @location(3) outline_width: f32,
+};
+
+struct FragmentOutput {
+    @location(0) color: vec4<f32>,
+    @location(1) normal: vec4<f32>,
+};
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+    @builtin(instance_index) instance_index: u32,
+) -> VertexOutput {
+    var label_geometry: LabelGeometry;
+    var output: VertexOutput;
+
+    // Every label is drawn with as many quads as the longest one, and the quads past the end
+    // of shorter labels are moved outside the view.
+    let range = u_ranges[instance_index];
+    let glyph_index = vertex_index / 6u;
+    if glyph_index >= range.y {
+        output.proj_position = vec4<f32>(2.0, 2.0, 2.0, 1.0);
+        return output;
+    }
+    let glyph = u_glyphs[range.x + glyph_index];
+
+    // Two triangles with the corners (0, 0), (1, 0), (0, 1) and (0, 1), (1, 0), (1, 1).
+    let corner_index = vertex_index % 6u;
+    let corner = vec2<f32>(
+        f32((0x32u >> corner_index) & 1u),
+        f32((0x2cu >> corner_index) & 1u),
+    );
+    let local = (mix(glyph.min, glyph.max, corner) + label_geometry.offset) * label_geometry.size;
+
+    if u_parameters.screen_space != 0u {
+        // Offset in clip space, so the size is given in pixels at any distance.
+        let anchor = u_globals.transform * vec4<f32>(label_geometry.position, 1.0);
+        let offset = 2.0 * local / u_globals.screen_size.xy * anchor.w;
+        output.proj_position = anchor + vec4<f32>(offset, 0.0, 0.0);
+    } else {
+        let forward = normalize(u_globals.camera_view_vector.xyz);
+        let right = normalize(cross(forward, u_globals.camera_up.xyz));
+        let up = cross(right, forward);
+        let position = label_geometry.position + local.x * right + local.y * up;
+        output.proj_position = u_globals.transform * vec4<f32>(position, 1.0);
+    }
+    output.uv = mix(glyph.uv_min, glyph.uv_max, corner);
+    output.color = label_geometry.color;
+    output.outline_color = label_geometry.outline_color;
+    output.outline_width = min(label_geometry.outline_width, u_parameters.spread);
+    return output;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> FragmentOutput {
+    let value = textureSample(u_atlas, u_atlas_sampler, in.uv).r;
+    // Distance to the glyph outline in em, positive inside.
+    let distance = (2.0 * value - 1.0) * u_parameters.spread;
+    let smoothing = max(0.5 * fwidth(distance), 1e-5);
+    let fill = smoothstep(-smoothing, smoothing, distance);
+    let outline = smoothstep(-smoothing, smoothing, distance + in.outline_width);
+    if outline <= 0.0 {
+        discard;
+    }
+
+    // The fill is relative to the outline, so edges without an outline keep the text color.
+    var output: FragmentOutput;
+    output.color = vec4<f32>(mix(in.outline_color, in.color, fill / outline), outline);
+    // Labels leave the normals of the geometry behind them untouched.
+    output.normal = vec4<f32>(0.0);
+    return output;
+}
@@ -31,6 +31,7 @@ struct LineGeometry {
 
 struct LineMaterial {
     color: vec3<f32>,
+    alpha: f32,
 };
 
 fn offset(pos: vec3<f32>, direction: vec3<f32>, unit_offset: vec3<f32>) -> vec3<f32> {
@@ -107,7 +108,7 @@ fn fs_main(input: VertexOutput) -> FragmentOutput {
     var line_material: LineMaterial;
 
     var output: FragmentOutput;
-    output.color = vec4<f32>(line_material.color, 1.0);
+    output.color = vec4<f32>(line_material.color, line_material.alpha);
     output.normal = vec4<f32>(_visula_normal, 0.0);
     return output;
 }
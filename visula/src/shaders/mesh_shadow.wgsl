@@ -0,0 +1,60 @@
+struct Light {
+    direction: vec3<f32>,
+    _pad0: f32,
+    color: vec3<f32>,
+    intensity: f32,
+    light_view_proj: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> u_light: Light;
+
+struct VertexOutput {
+    @builtin(position) proj_position: vec4<f32>,
+};
+
+struct MeshGeometry {
+    rotation: vec4<f32>,
+    position: vec3<f32>,
+    scale: vec3<f32>,
+    color: vec4<f32>,
+};
+
+fn calculate_transform_matrix(
+    rotation: vec4<f32>,
+    translation: vec3<f32>,
+    scale: vec3<f32>,
+) -> mat4x4<f32> {
+    let x = rotation.x;
+    let y = rotation.y;
+    let z = rotation.z;
+    let w = rotation.w;
+    let x2 = x + x;
+    let y2 = y + y;
+    let z2 = z + z;
+    let xx = x * x2;
+    let xy = x * y2;
+    let xz = x * z2;
+    let yy = y * y2;
+    let yz = y * z2;
+    let zz = z * z2;
+    let wx = w * x2;
+    let wy = w * y2;
+    let wz = w * z2;
+
+    let x_axis = vec4<f32>(1.0 - (yy + zz), xy + wz, xz - wy, 0.0);
+    let y_axis = vec4<f32>(xy - wz, 1.0 - (xx + zz), yz + wx, 0.0);
+    let z_axis = vec4<f32>(xz + wy, yz - wx, 1.0 - (xx + yy), 0.0);
+    return mat4x4(scale.x * x_axis, scale.y * y_axis, scale.z * z_axis, vec4(translation, 1.0));
+}
+
+@vertex
+fn vs_main(
+    @location(0) position: vec3<f32>,
+) -> VertexOutput {
+    var geometry: MeshGeometry;
+    var output: VertexOutput;
+    let transform_matrix = calculate_transform_matrix(geometry.rotation, geometry.position, geometry.scale);
+    output.proj_position = u_light.light_view_proj * transform_matrix * vec4<f32>(position, 1.0);
+    return output;
+}
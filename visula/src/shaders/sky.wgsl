@@ -24,6 +24,22 @@ var<uniform> u_globals: Camera;
 @group(1) @binding(0)
 var<uniform> sky_params: SkyParams;
 
+@group(1) @binding(1)
+var environment: texture_2d<f32>;
+
+@group(1) @binding(2)
+var environment_sampler: sampler;
+
+const PI: f32 = 3.14159265;
+
+// Must match visula_equirect_uv in lighting.wgsl.
+fn equirect_uv(direction: vec3<f32>) -> vec2<f32> {
+    return vec2<f32>(
+        atan2(direction.z, direction.x) / (2.0 * PI) + 0.5,
+        acos(clamp(direction.y, -1.0, 1.0)) / PI,
+    );
+}
+
 struct FullscreenOutput {
     @builtin(position) position: vec4<f32>,
     @location(0) uv: vec2<f32>,
@@ -73,6 +89,9 @@ fn fs_main(in: FullscreenOutput) -> FragmentOutput {
             let t = pow(-world_dir.y, 0.5);
             sky_color = mix(ground_horizon, ground_bottom, t);
         }
+    } else if sky_params.mode == 3u {
+        // Environment mode
+        sky_color = textureSampleLevel(environment, environment_sampler, equirect_uv(world_dir), 0.0).rgb;
     } else {
         sky_color = vec3<f32>(0.0, 0.0, 0.0);
     }
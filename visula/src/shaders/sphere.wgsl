@@ -32,6 +32,7 @@ struct SphereGeometry {
 
 struct SphereMaterial {
     color: vec3<f32>,
+    alpha: f32,
 };
 
 fn spheres(
@@ -122,7 +123,7 @@ fn fs_main(in: VertexOutput) -> FragmentOutput {
     var sphere_material: SphereMaterial;
 
     var output: FragmentOutput;
-    output.color = vec4<f32>(sphere_material.color, 1.0);
+    output.color = vec4<f32>(sphere_material.color, sphere_material.alpha);
     output.normal = vec4<f32>(_visula_normal, 0.0);
     output.depth = frag_depth;
     return output;
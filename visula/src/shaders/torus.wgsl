@@ -36,6 +36,7 @@ struct TorusGeometry {
 
 struct TorusMaterial {
     color: vec3<f32>,
+    alpha: f32,
 };
 
 fn quat_rotate(q: vec4<f32>, v: vec3<f32>) -> vec3<f32> {
@@ -274,7 +275,7 @@ fn fs_main(in: VertexOutput) -> FragmentOutput {
     var torus_material: TorusMaterial;
 
     var output: FragmentOutput;
-    output.color = vec4<f32>(torus_material.color, 1.0);
+    output.color = vec4<f32>(torus_material.color, torus_material.alpha);
     output.normal = vec4<f32>(_visula_normal, 0.0);
     output.depth = frag_depth;
     return output;
@@ -0,0 +1,32 @@
+struct FullscreenOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) vertex_index: u32) -> FullscreenOutput {
+    let x = f32(i32(vertex_index & 1u)) * 4.0 - 1.0;
+    let y = f32(i32(vertex_index >> 1u)) * 4.0 - 1.0;
+    var output: FullscreenOutput;
+    output.position = vec4<f32>(x, -y, 0.0, 1.0);
+    output.uv = vec2<f32>(x * 0.5 + 0.5, 1.0 - (-y * 0.5 + 0.5));
+    return output;
+}
+
+@group(0) @binding(0)
+var accumulation_texture: texture_2d<f32>;
+
+@group(0) @binding(1)
+var revealage_texture: texture_2d<f32>;
+
+@fragment
+fn fs_main(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    let coord = vec2<i32>(in.position.xy);
+    let revealage = textureLoad(revealage_texture, coord, 0).r;
+    if revealage >= 1.0 {
+        discard;
+    }
+    let accumulation = textureLoad(accumulation_texture, coord, 0);
+    let color = accumulation.rgb / max(accumulation.a, 1e-5);
+    return vec4<f32>(color, 1.0 - revealage);
+}
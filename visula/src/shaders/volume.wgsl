@@ -0,0 +1,152 @@
+struct Camera {
+    view_matrix: mat4x4<f32>,
+    transform: mat4x4<f32>,
+    camera_center: vec4<f32>,
+    camera_view_vector: vec4<f32>,
+    camera_position: vec4<f32>,
+    camera_up: vec4<f32>,
+    inverse_view_proj: mat4x4<f32>,
+    screen_size: vec4<f32>,
+    projection_matrix: mat4x4<f32>,
+    inverse_projection_matrix: mat4x4<f32>,
+};
+
+@group(0)
+@binding(0)
+var<uniform> u_globals: Camera;
+
+struct VolumeParameters {
+    dimensions: vec4<f32>,
+};
+
+@group(1)
+@binding(0)
+var<uniform> u_volume: VolumeParameters;
+
+@group(1)
+@binding(1)
+var u_volume_texture: texture_3d<f32>;
+
+@group(1)
+@binding(2)
+var u_volume_sampler: sampler;
+
+@group(2)
+@binding(0)
+var u_depth: texture_depth_multisampled_2d;
+
+struct VertexOutput {
+    @builtin(position) proj_position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+    @location(1) @interpolate(flat) box_position: vec3<f32>,
+    @location(2) @interpolate(flat) box_size: vec3<f32>,
+};
+
+struct VolumeGeometry {
+    position: vec3<f32>,
+    size: vec3<f32>,
+};
+
+struct VolumeMaterial {
+    color: vec3<f32>,
+    opacity: f32,
+};
+
+struct VolumeOutput {
+    @location(0) accumulation: vec4<f32>,
+    @location(1) revealage: f32,
+};
+
+const MAX_STEPS: u32 = 1024u;
+
+// Replaced by the transfer function in fs_transfer once the material is injected.
+fn visula_volume_transfer(value: f32) -> vec4<f32> {
+    return vec4<f32>(0.0);
+}
+
+@vertex
+fn vs_main(
+    @location(0) corner: vec3<f32>,
+) -> VertexOutput {
+    var volume_geometry: VolumeGeometry;
+    let world_position = volume_geometry.position + corner * volume_geometry.size;
+    var output: VertexOutput;
+    output.proj_position = u_globals.transform * vec4<f32>(world_position, 1.0);
+    output.world_position = world_position;
+    output.box_position = volume_geometry.position;
+    output.box_size = volume_geometry.size;
+    return output;
+}
+
+@fragment
+fn fs_transfer(@location(0) value: f32) -> @location(0) vec4<f32> {
+    var volume_material: VolumeMaterial;
+    return vec4<f32>(volume_material.color, volume_material.opacity);
+}
+
+// Distance along the ray to the opaque geometry drawn at this pixel.
+fn opaque_distance(coord: vec2<f32>, origin: vec3<f32>, direction: vec3<f32>) -> f32 {
+    let depth = textureLoad(u_depth, vec2<i32>(coord), 0);
+    let uv = coord / vec2<f32>(textureDimensions(u_depth));
+    let ndc = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+    let position = u_globals.inverse_view_proj * ndc;
+    return dot(position.xyz / position.w - origin, direction);
+}
+
+// Back faces of the box are drawn, so that the volume is also visible from the inside. The
+// ray is marched from where it enters the box, or the camera, to where it leaves the box or
+// hits opaque geometry, and the result is written to the weighted blended order-independent
+// transparency targets with the weight used by `visula_core::transparency_module`.
+@fragment
+fn fs_main(in: VertexOutput) -> VolumeOutput {
+    let origin = u_globals.camera_position.xyz;
+    let direction = normalize(in.world_position - origin);
+
+    let inverse_direction = 1.0 / direction;
+    let t0 = (in.box_position - origin) * inverse_direction;
+    let t1 = (in.box_position + in.box_size - origin) * inverse_direction;
+    let t_min = min(t0, t1);
+    let t_max = max(t0, t1);
+    let t_near = max(max(max(t_min.x, t_min.y), t_min.z), 0.0);
+    let t_box = min(min(t_max.x, t_max.y), t_max.z);
+    let t_far = min(t_box, opaque_distance(in.proj_position.xy, origin, direction));
+    if t_far <= t_near {
+        discard;
+    }
+
+    // Take two steps per voxel, and correct the opacity, which is given per voxel, for the
+    // step length.
+    let voxel = in.box_size / u_volume.dimensions.xyz;
+    let voxel_length = min(min(voxel.x, voxel.y), voxel.z);
+    let steps = min(u32(ceil(2.0 * (t_far - t_near) / voxel_length)), MAX_STEPS);
+    let step_length = (t_far - t_near) / f32(steps);
+
+    var color = vec3<f32>(0.0);
+    var transmittance = 1.0;
+    for (var i = 0u; i < steps; i++) {
+        let t = t_near + (f32(i) + 0.5) * step_length;
+        let coordinate = (origin + t * direction - in.box_position) / in.box_size;
+        let value = textureSampleLevel(u_volume_texture, u_volume_sampler, coordinate, 0.0).r;
+        let sample = visula_volume_transfer(value);
+        let alpha = 1.0 - pow(1.0 - clamp(sample.a, 0.0, 1.0), step_length / voxel_length);
+        color += transmittance * alpha * sample.rgb;
+        transmittance *= 1.0 - alpha;
+        if transmittance < 0.01 {
+            break;
+        }
+    }
+
+    let alpha = 1.0 - transmittance;
+    if alpha <= 0.0 {
+        discard;
+    }
+    // The entry point is the camera itself when inside the box, where w is zero.
+    let entry = u_globals.transform * vec4<f32>(origin + t_near * direction, 1.0);
+    let z = clamp(entry.z / max(entry.w, 1e-5), 0.0, 1.0);
+    let weight = max(1e-2, 3e3 * pow(1.0 - z, 3.0));
+
+    var output: VolumeOutput;
+    output.accumulation = vec4<f32>(color, alpha) * weight;
+    output.revealage = alpha;
+    return output;
+}
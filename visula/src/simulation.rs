@@ -4,7 +4,10 @@ use winit::event::Event;
 use crate::application::Application;
 use crate::camera::Camera;
 use crate::light::DirectionalLight;
-use crate::pipelines::{Circles, Cylinders, Lines, Polygons, Rects, Renderable, Spheres, Torus};
+use crate::pipelines::{
+    Arrows, Axes3D, Boxes, Capsules, Circles, Cones, Cylinders, Ellipsoids, Labels, Lines,
+    MeshPipeline, Polygons, Rects, Renderable, Spheres, Torus, Tubes, Volume,
+};
 use crate::CustomEvent;
 
 pub struct RenderData<'a> {
@@ -18,17 +21,49 @@ pub struct RenderData<'a> {
     pub light: &'a DirectionalLight,
 }
 
+/// Passed to `render_transparent` after all opaque geometry has been rendered.
+///
+/// Translucent geometry is drawn into the weighted blended order-independent transparency
+/// targets, testing against `depth_texture` without writing to it. The resolve targets are
+/// `None` without multisampling.
+pub struct TransparentRenderData<'a> {
+    pub accumulation: &'a wgpu::TextureView,
+    pub accumulation_resolve: Option<&'a wgpu::TextureView>,
+    pub revealage: &'a wgpu::TextureView,
+    pub revealage_resolve: Option<&'a wgpu::TextureView>,
+    pub depth_texture: &'a wgpu::TextureView,
+    pub encoder: &'a mut wgpu::CommandEncoder,
+    pub camera: &'a Camera,
+    pub light: &'a DirectionalLight,
+}
+
+/// Passed to `render_shadow` once for every shadow cascade of the light.
 pub struct ShadowRenderData<'a> {
     pub encoder: &'a mut wgpu::CommandEncoder,
+    /// The cascade being rendered.
     pub shadow_texture: &'a wgpu::TextureView,
+    /// Light uniforms with `light_view_proj` set to the cascade's projection.
+    pub shadow_bind_group: &'a wgpu::BindGroup,
+    pub light: &'a DirectionalLight,
+}
+
+pub struct PickingRenderData<'a> {
+    pub encoder: &'a mut wgpu::CommandEncoder,
+    pub id_texture: &'a wgpu::TextureView,
+    pub depth_texture: &'a wgpu::TextureView,
+    pub camera: &'a Camera,
     pub light: &'a DirectionalLight,
+    /// The pixel being picked. Pipelines restrict their picking pass to this pixel.
+    pub pixel: [u32; 2],
 }
 
 pub trait Simulation {
     fn handle_event(&mut self, _application: &mut Application, _event: &Event<CustomEvent>) {}
     fn update(&mut self, _application: &mut Application) {}
     fn render(&mut self, _data: &mut RenderData) {}
+    fn render_transparent(&mut self, _data: &mut TransparentRenderData) {}
     fn render_shadow(&mut self, _data: &mut ShadowRenderData) {}
+    fn render_picking(&mut self, _data: &mut PickingRenderData) {}
     fn gui(&mut self, _application: &Application, _context: &Context) {}
     fn clear_color(&self) -> wgpu::Color {
         wgpu::Color {
@@ -47,15 +82,39 @@ macro_rules! impl_simulation_for_renderable {
                 fn render(&mut self, data: &mut RenderData) {
                     Renderable::render(self, data)
                 }
+                fn render_transparent(&mut self, data: &mut TransparentRenderData) {
+                    Renderable::render_transparent(self, data)
+                }
                 fn render_shadow(&mut self, data: &mut ShadowRenderData) {
                     Renderable::render_shadow(self, data)
                 }
+                fn render_picking(&mut self, data: &mut PickingRenderData) {
+                    Renderable::render_picking(self, data)
+                }
             }
         )*
     };
 }
 
-impl_simulation_for_renderable!(Circles, Cylinders, Lines, Polygons, Rects, Spheres, Torus);
+impl_simulation_for_renderable!(
+    Arrows,
+    Axes3D,
+    Boxes,
+    Capsules,
+    Circles,
+    Cones,
+    Cylinders,
+    Ellipsoids,
+    Labels,
+    Lines,
+    MeshPipeline,
+    Polygons,
+    Rects,
+    Spheres,
+    Torus,
+    Tubes,
+    Volume,
+);
 
 impl Simulation for Vec<Box<dyn Renderable>> {
     fn render(&mut self, data: &mut RenderData) {
@@ -63,11 +122,21 @@ impl Simulation for Vec<Box<dyn Renderable>> {
             renderable.render(data);
         }
     }
+    fn render_transparent(&mut self, data: &mut TransparentRenderData) {
+        for renderable in self.iter() {
+            renderable.render_transparent(data);
+        }
+    }
     fn render_shadow(&mut self, data: &mut ShadowRenderData) {
         for renderable in self.iter() {
             renderable.render_shadow(data);
         }
     }
+    fn render_picking(&mut self, data: &mut PickingRenderData) {
+        for renderable in self.iter() {
+            renderable.render_picking(data);
+        }
+    }
 }
 
 impl Simulation for Box<dyn Simulation> {
@@ -80,9 +149,15 @@ impl Simulation for Box<dyn Simulation> {
     fn render(&mut self, data: &mut RenderData) {
         self.as_mut().render(data)
     }
+    fn render_transparent(&mut self, data: &mut TransparentRenderData) {
+        self.as_mut().render_transparent(data)
+    }
     fn render_shadow(&mut self, data: &mut ShadowRenderData) {
         self.as_mut().render_shadow(data)
     }
+    fn render_picking(&mut self, data: &mut PickingRenderData) {
+        self.as_mut().render_picking(data)
+    }
     fn gui(&mut self, application: &Application, context: &Context) {
         self.as_mut().gui(application, context)
     }
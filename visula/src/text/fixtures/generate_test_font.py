@@ -0,0 +1,135 @@
+"""Writes test_font.ttf, a minimal TrueType font for the text layout tests.
+
+The font has 1000 units per em, an ascender of 800 and a descender of -200, and no
+outlines. Its glyphs and advances are:
+
+    0  .notdef  500
+    1  'A'      600
+    2  'B'      700
+    3  ' '      250
+"""
+
+import struct
+from pathlib import Path
+
+UNITS_PER_EM = 1000
+ASCENDER = 800
+DESCENDER = -200
+ADVANCES = [500, 600, 700, 250]
+
+
+def head():
+    return struct.pack(
+        ">IIIIHHqqhhhhHHhhh",
+        0x00010000,  # version
+        0x00010000,  # font revision
+        0,  # checksum adjustment
+        0x5F0F3CF5,  # magic number
+        0,  # flags
+        UNITS_PER_EM,
+        0,  # created
+        0,  # modified
+        0,  # x min
+        DESCENDER,  # y min
+        max(ADVANCES),  # x max
+        ASCENDER,  # y max
+        0,  # mac style
+        8,  # lowest recommended size in pixels
+        2,  # font direction hint
+        0,  # short loca offsets
+        0,  # glyph data format
+    )
+
+
+def hhea():
+    return struct.pack(
+        ">IhhhHhhhhhhhhhhhH",
+        0x00010000,  # version
+        ASCENDER,
+        DESCENDER,
+        0,  # line gap
+        max(ADVANCES),
+        0,  # min left side bearing
+        0,  # min right side bearing
+        max(ADVANCES),  # x max extent
+        1,  # caret slope rise
+        0,  # caret slope run
+        0,  # caret offset
+        0,
+        0,
+        0,
+        0,
+        0,  # metric data format
+        len(ADVANCES),  # number of horizontal metrics
+    )
+
+
+def maxp():
+    return struct.pack(">IH", 0x00005000, len(ADVANCES))
+
+
+def hmtx():
+    return b"".join(struct.pack(">Hh", advance, 0) for advance in ADVANCES)
+
+
+def cmap():
+    # Segments of (start, end, first glyph), ending with the required 0xFFFF segment.
+    segments = [(0x20, 0x20, 3), (0x41, 0x42, 1), (0xFFFF, 0xFFFF, 0)]
+    count = len(segments)
+    search_range = 2 * 2 ** (count.bit_length() - 1)
+    subtable = struct.pack(
+        ">HHHHHHH",
+        4,  # format
+        16 + 8 * count,  # length
+        0,  # language
+        2 * count,
+        search_range,
+        count.bit_length() - 1,
+        2 * count - search_range,
+    )
+    subtable += b"".join(struct.pack(">H", end) for _, end, _ in segments)
+    subtable += struct.pack(">H", 0)
+    subtable += b"".join(struct.pack(">H", start) for start, _, _ in segments)
+    subtable += b"".join(
+        struct.pack(">H", (glyph - start) % 0x10000 if glyph else 1)
+        for start, _, glyph in segments
+    )
+    subtable += b"".join(struct.pack(">H", 0) for _ in segments)
+    # Windows Unicode BMP encoding.
+    return struct.pack(">HHHHI", 0, 1, 3, 1, 12) + subtable
+
+
+def checksum(data):
+    data += b"\0" * (-len(data) % 4)
+    return sum(struct.unpack(f">{len(data) // 4}I", data)) & 0xFFFFFFFF
+
+
+def font():
+    tables = {
+        b"cmap": cmap(),
+        b"head": head(),
+        b"hhea": hhea(),
+        b"hmtx": hmtx(),
+        b"maxp": maxp(),
+    }
+    count = len(tables)
+    search_range = 16 * 2 ** (count.bit_length() - 1)
+    directory = struct.pack(
+        ">IHHHH",
+        0x00010000,
+        count,
+        search_range,
+        count.bit_length() - 1,
+        16 * count - search_range,
+    )
+    offset = len(directory) + 16 * count
+    records = b""
+    data = b""
+    for tag, table in sorted(tables.items()):
+        records += struct.pack(">4sIII", tag, checksum(table), offset + len(data), len(table))
+        data += table + b"\0" * (-len(table) % 4)
+    return directory + records + data
+
+
+if __name__ == "__main__":
+    Path(__file__).with_name("test_font.ttf").write_bytes(font())
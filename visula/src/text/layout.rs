@@ -0,0 +1,358 @@
+use glam::Vec2;
+use ttf_parser::gpos::{PairAdjustment, PositioningSubtable};
+use ttf_parser::{Face, GlyphId, Tag};
+
+use super::{tessellate_path, GlyphOutlineCollector, TessellatedGeometry};
+use crate::error::Error;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextAlign {
+    /// Lines start at the origin.
+    #[default]
+    Left,
+    /// Lines are centered on the origin.
+    Center,
+    /// Lines end at the origin.
+    Right,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextLayoutOptions {
+    /// Height of the em square.
+    pub size: f32,
+    /// Distance between the baselines of consecutive lines, relative to the size.
+    pub line_height: f32,
+    pub align: TextAlign,
+}
+
+impl Default for TextLayoutOptions {
+    fn default() -> Self {
+        TextLayoutOptions {
+            size: 1.0,
+            line_height: 1.2,
+            align: TextAlign::Left,
+        }
+    }
+}
+
+/// A glyph placed by [`FontChain::layout`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PositionedGlyph {
+    /// Index of the font in the chain that the glyph belongs to.
+    pub font: usize,
+    pub glyph: GlyphId,
+    /// Origin of the glyph on the baseline.
+    pub position: Vec2,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TextBounds {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl TextBounds {
+    pub fn size(&self) -> Vec2 {
+        self.max - self.min
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TextLayout {
+    pub glyphs: Vec<PositionedGlyph>,
+    /// The box from the ascender of the first line to the descender of the last line, and
+    /// from the start of the leftmost line to the end of the rightmost line.
+    pub bounds: TextBounds,
+}
+
+struct Font<'a> {
+    face: Face<'a>,
+    /// Lookups of the `kern` feature in the GPOS table.
+    kern_lookups: Vec<u16>,
+}
+
+impl<'a> Font<'a> {
+    fn new(face: Face<'a>) -> Self {
+        let kern = Tag::from_bytes(b"kern");
+        let mut kern_lookups: Vec<u16> = face
+            .tables()
+            .gpos
+            .map(|gpos| {
+                gpos.features
+                    .into_iter()
+                    .filter(|feature| feature.tag == kern)
+                    .flat_map(|feature| feature.lookup_indices)
+                    .collect()
+            })
+            .unwrap_or_default();
+        kern_lookups.sort_unstable();
+        kern_lookups.dedup();
+        Font { face, kern_lookups }
+    }
+
+    /// Adjustment of the advance between two glyphs in font units, from the GPOS table if it
+    /// has kerning and otherwise from the `kern` table.
+    fn kerning(&self, left: GlyphId, right: GlyphId) -> i16 {
+        if let Some(gpos) = self
+            .face
+            .tables()
+            .gpos
+            .filter(|_| !self.kern_lookups.is_empty())
+        {
+            return self
+                .kern_lookups
+                .iter()
+                .filter_map(|&index| gpos.lookups.get(index))
+                .filter_map(|lookup| {
+                    (0..lookup.subtables.len())
+                        .filter_map(|index| lookup.subtables.get::<PositioningSubtable>(index))
+                        .find_map(|subtable| pair_kerning(&subtable, left, right))
+                })
+                .sum();
+        }
+        self.face
+            .tables()
+            .kern
+            .and_then(|kern| {
+                kern.subtables
+                    .into_iter()
+                    .filter(|subtable| subtable.horizontal && !subtable.variable)
+                    .find_map(|subtable| subtable.glyphs_kerning(left, right))
+            })
+            .unwrap_or(0)
+    }
+}
+
+fn pair_kerning(subtable: &PositioningSubtable, left: GlyphId, right: GlyphId) -> Option<i16> {
+    let PositioningSubtable::Pair(adjustment) = subtable else {
+        return None;
+    };
+    let (first, _) = match adjustment {
+        PairAdjustment::Format1 { coverage, sets } => sets.get(coverage.get(left)?)?.get(right)?,
+        PairAdjustment::Format2 {
+            coverage,
+            classes,
+            matrix,
+        } => {
+            coverage.get(left)?;
+            matrix.get((classes.0.get(left), classes.1.get(right)))?
+        }
+    };
+    Some(first.x_advance)
+}
+
+/// A primary font with fallback fonts for characters it does not have.
+///
+/// Each character is taken from the first font that has a glyph for it, and characters that
+/// no font has are drawn with the replacement glyph of the primary font. The line metrics are
+/// taken from the primary font.
+pub struct FontChain<'a> {
+    fonts: Vec<Font<'a>>,
+}
+
+impl<'a> FontChain<'a> {
+    pub fn new(primary: &'a [u8], fallbacks: &[&'a [u8]]) -> Result<Self, Error> {
+        let fonts = std::iter::once(primary)
+            .chain(fallbacks.iter().copied())
+            .map(|data| {
+                Face::parse(data, 0)
+                    .map(Font::new)
+                    .map_err(|_| Error::FontParse)
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(FontChain { fonts })
+    }
+
+    /// The font at `index` in the chain, where the primary font is at index zero.
+    pub fn face(&self, index: usize) -> &Face<'a> {
+        &self.fonts[index].face
+    }
+
+    /// Places the glyphs of `text`, with lines separated by `\n`.
+    ///
+    /// The baseline of the first line is at `y = 0`, and the following lines are placed below
+    /// it. Pairs of glyphs from the same font are kerned.
+    pub fn layout(&self, text: &str, options: &TextLayoutOptions) -> TextLayout {
+        let primary = &self.fonts[0].face;
+        let line_advance = options.size * options.line_height;
+
+        let mut glyphs = Vec::new();
+        let mut left = f32::MAX;
+        let mut right = f32::MIN;
+        let mut line_count = 0;
+        for (line_index, line) in text.split('\n').enumerate() {
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            let baseline = -(line_index as f32) * line_advance;
+            let start = glyphs.len();
+            let mut pen = 0.0;
+            let mut previous: Option<(usize, GlyphId)> = None;
+            for character in line.chars() {
+                let (font, glyph) = self.glyph(character);
+                let face = &self.fonts[font].face;
+                let scale = options.size / face.units_per_em() as f32;
+                if let Some((previous_font, previous_glyph)) = previous {
+                    if previous_font == font {
+                        pen += self.fonts[font].kerning(previous_glyph, glyph) as f32 * scale;
+                    }
+                }
+                glyphs.push(PositionedGlyph {
+                    font,
+                    glyph,
+                    position: Vec2::new(pen, baseline),
+                });
+                pen += face.glyph_hor_advance(glyph).unwrap_or(0) as f32 * scale;
+                previous = Some((font, glyph));
+            }
+
+            let offset = match options.align {
+                TextAlign::Left => 0.0,
+                TextAlign::Center => -0.5 * pen,
+                TextAlign::Right => -pen,
+            };
+            for glyph in &mut glyphs[start..] {
+                glyph.position.x += offset;
+            }
+            left = left.min(offset);
+            right = right.max(offset + pen);
+            line_count += 1;
+        }
+
+        let scale = options.size / primary.units_per_em() as f32;
+        let bounds = TextBounds {
+            min: Vec2::new(
+                left,
+                -((line_count - 1) as f32) * line_advance + primary.descender() as f32 * scale,
+            ),
+            max: Vec2::new(right, primary.ascender() as f32 * scale),
+        };
+        TextLayout { glyphs, bounds }
+    }
+
+    /// The bounds of `text` as laid out by [`FontChain::layout`].
+    pub fn measure(&self, text: &str, options: &TextLayoutOptions) -> TextBounds {
+        self.layout(text, options).bounds
+    }
+
+    /// Lays out `text` and tessellates the glyph outlines for [`crate::Polygons`].
+    pub fn tessellate(
+        &self,
+        text: &str,
+        options: &TextLayoutOptions,
+    ) -> Result<TessellatedGeometry, Error> {
+        let mut geometry = TessellatedGeometry {
+            vertices: Vec::new(),
+            indices: Vec::new(),
+        };
+        for positioned in self.layout(text, options).glyphs {
+            let face = &self.fonts[positioned.font].face;
+            let scale = options.size / face.units_per_em() as f32;
+            let mut outline =
+                GlyphOutlineCollector::new(scale, positioned.position.x, positioned.position.y);
+            if face.outline_glyph(positioned.glyph, &mut outline).is_some() {
+                geometry.merge(&tessellate_path(&outline.build())?);
+            }
+        }
+        Ok(geometry)
+    }
+
+    fn glyph(&self, character: char) -> (usize, GlyphId) {
+        self.fonts
+            .iter()
+            .enumerate()
+            .find_map(|(index, font)| Some((index, font.face.glyph_index(character)?)))
+            .unwrap_or((0, GlyphId(0)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Has 1000 units per em, an ascender of 800, a descender of -200 and advances of 600 for
+    /// 'A', 700 for 'B' and 500 for the replacement glyph, see `fixtures/generate_test_font.py`.
+    const TEST_FONT: &[u8] = include_bytes!("fixtures/test_font.ttf");
+
+    fn layout(text: &str, options: TextLayoutOptions) -> TextLayout {
+        FontChain::new(TEST_FONT, &[])
+            .unwrap()
+            .layout(text, &options)
+    }
+
+    fn positions(layout: &TextLayout) -> Vec<Vec2> {
+        layout.glyphs.iter().map(|glyph| glyph.position).collect()
+    }
+
+    fn assert_close(actual: Vec2, expected: Vec2) {
+        assert!(
+            actual.abs_diff_eq(expected, 1e-6),
+            "{actual} is not {expected}"
+        );
+    }
+
+    #[test]
+    fn test_alignment() {
+        for (align, offset) in [
+            (TextAlign::Left, 0.0),
+            (TextAlign::Center, -0.65),
+            (TextAlign::Right, -1.3),
+        ] {
+            let layout = layout(
+                "AB",
+                TextLayoutOptions {
+                    align,
+                    ..Default::default()
+                },
+            );
+            let positions = positions(&layout);
+            assert_eq!(positions.len(), 2);
+            assert_close(positions[0], Vec2::new(offset, 0.0));
+            assert_close(positions[1], Vec2::new(offset + 0.6, 0.0));
+            assert_close(layout.bounds.min, Vec2::new(offset, -0.2));
+            assert_close(layout.bounds.max, Vec2::new(offset + 1.3, 0.8));
+        }
+    }
+
+    #[test]
+    fn test_multiple_lines() {
+        let layout = layout(
+            "A\nAB",
+            TextLayoutOptions {
+                size: 2.0,
+                line_height: 1.5,
+                align: TextAlign::Center,
+            },
+        );
+        let positions = positions(&layout);
+        assert_eq!(positions.len(), 3);
+        assert_close(positions[0], Vec2::new(-0.6, 0.0));
+        assert_close(positions[1], Vec2::new(-1.3, -3.0));
+        assert_close(positions[2], Vec2::new(-0.1, -3.0));
+        // As wide as the widest line, down to the descender of the last line.
+        assert_close(layout.bounds.min, Vec2::new(-1.3, -3.4));
+        assert_close(layout.bounds.max, Vec2::new(1.3, 1.6));
+        assert!((layout.bounds.size().x - 2.6).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_carriage_returns_are_stripped() {
+        let layout = layout("A\r\nB", TextLayoutOptions::default());
+        assert_eq!(
+            layout
+                .glyphs
+                .iter()
+                .map(|glyph| glyph.glyph)
+                .collect::<Vec<_>>(),
+            [GlyphId(1), GlyphId(2)]
+        );
+        assert_close(layout.glyphs[1].position, Vec2::new(0.0, -1.2));
+        assert!((layout.bounds.size().x - 0.7).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_missing_characters_use_the_replacement_glyph() {
+        let layout = layout("A?B", TextLayoutOptions::default());
+        assert_eq!(layout.glyphs[1].glyph, GlyphId(0));
+        assert_eq!(layout.glyphs[1].font, 0);
+        assert_close(layout.glyphs[2].position, Vec2::new(1.1, 0.0));
+    }
+}
@@ -3,6 +3,10 @@ use lyon::math::point;
 use lyon::path::Path;
 use lyon::tessellation::{BuffersBuilder, FillOptions, FillTessellator, FillVertex, VertexBuffers};
 
+pub mod layout;
+
+pub use layout::*;
+
 pub struct TessellatedGeometry {
     pub vertices: Vec<PolygonVertex>,
     pub indices: Vec<u32>,
@@ -17,54 +21,20 @@ impl TessellatedGeometry {
     }
 }
 
+/// Tessellates `text` with its baseline at `y = 0` and the em square scaled to `scale`, see
+/// [`FontChain::tessellate`] for alignment and fallback fonts.
 pub fn tessellate_text(
     font_data: &[u8],
     text: &str,
     scale: f32,
 ) -> Result<TessellatedGeometry, crate::error::Error> {
-    let face = ttf_parser::Face::parse(font_data, 0).map_err(|_| crate::error::Error::FontParse)?;
-    let units_per_em = face.units_per_em() as f32;
-    let scale_factor = scale / units_per_em;
-
-    let mut all_vertices = Vec::new();
-    let mut all_indices = Vec::new();
-    let mut cursor_x: f32 = 0.0;
-
-    for ch in text.chars() {
-        let glyph_id = match face.glyph_index(ch) {
-            Some(id) => id,
-            None => continue,
-        };
-
-        let mut outline = GlyphOutlineCollector::new(scale_factor, cursor_x, 0.0);
-        if face.outline_glyph(glyph_id, &mut outline).is_some() {
-            let path = outline.build();
-            let mut geometry: VertexBuffers<PolygonVertex, u32> = VertexBuffers::new();
-            let mut tessellator = FillTessellator::new();
-            if tessellator
-                .tessellate_path(
-                    &path,
-                    &FillOptions::tolerance(0.01),
-                    &mut BuffersBuilder::new(&mut geometry, |vertex: FillVertex| PolygonVertex {
-                        position: [vertex.position().x, vertex.position().y],
-                    }),
-                )
-                .is_ok()
-            {
-                let base_index = all_vertices.len() as u32;
-                all_vertices.extend_from_slice(&geometry.vertices);
-                all_indices.extend(geometry.indices.iter().map(|i| i + base_index));
-            }
-        }
-
-        let advance = face.glyph_hor_advance(glyph_id).unwrap_or(0) as f32 * scale_factor;
-        cursor_x += advance;
-    }
-
-    Ok(TessellatedGeometry {
-        vertices: all_vertices,
-        indices: all_indices,
-    })
+    FontChain::new(font_data, &[])?.tessellate(
+        text,
+        &TextLayoutOptions {
+            size: scale,
+            ..Default::default()
+        },
+    )
 }
 
 pub fn tessellate_path(path: &Path) -> Result<TessellatedGeometry, crate::error::Error> {
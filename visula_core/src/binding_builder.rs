@@ -1,5 +1,8 @@
 use crate::error::ShaderError;
-use crate::{InstanceBufferInner, TextureBufferInner, UniformBufferInner};
+use crate::{
+    ImportedFunctions, InstanceBufferInner, InstanceDescriptor, TextureBufferInner,
+    UniformBufferInner,
+};
 use itertools::Itertools;
 use naga::{Expression, Handle};
 use naga::{Module, ShaderStage};
@@ -41,31 +44,50 @@ pub struct InstanceBinding {
 }
 
 pub struct TextureBinding {
+    pub group: u32,
     pub inner: Rc<RefCell<TextureBufferInner>>,
 }
 
 pub struct UniformBinding {
     pub expression: Handle<Expression>,
+    pub group: u32,
     pub bind_group_layout: Rc<BindGroupLayout>,
     pub inner: Rc<RefCell<UniformBufferInner>>,
 }
 
+/// An instance buffer bound as a storage buffer of `u32` words and indexed by the current
+/// instance. All storage bindings of an entry point share one bind group.
+#[derive(Clone, Debug)]
+pub struct StorageBinding {
+    pub binding: u32,
+    pub expression: Handle<Expression>,
+    pub descriptor: Rc<InstanceDescriptor>,
+    pub inner: Rc<RefCell<InstanceBufferInner>>,
+}
+
 pub type InstanceMap = HashMap<uuid::Uuid, InstanceBinding>;
+pub type StorageMap = HashMap<uuid::Uuid, StorageBinding>;
 pub type UniformMap = HashMap<uuid::Uuid, UniformBinding>;
 pub type TextureMap = HashMap<uuid::Uuid, TextureBinding>;
 pub type BindGroupMap = HashMap<uuid::Uuid, BindGroup>;
+pub type FunctionMap = HashMap<uuid::Uuid, ImportedFunctions>;
 
 pub struct BindingBuilder {
     pub instances: InstanceMap,
+    pub storage_buffers: StorageMap,
     pub uniforms: UniformMap,
     pub textures: TextureMap,
     pub bind_groups: BindGroupMap,
+    /// [`crate::ShaderFunctions`] that have been copied into the module.
+    pub functions: FunctionMap,
     pub shader_location_offset: u32,
     pub entry_point_index: usize,
     pub current_slot: u32,
     pub current_bind_group: u32,
     pub shader_stage: ShaderStage,
     pub pending_statements: Vec<naga::Statement>,
+    pub storage_group: Option<u32>,
+    pub storage_index: Option<Handle<Expression>>,
 }
 
 impl BindingBuilder {
@@ -108,15 +130,19 @@ impl BindingBuilder {
 
         Ok(BindingBuilder {
             instances: HashMap::new(),
+            storage_buffers: HashMap::new(),
             uniforms: HashMap::new(),
             textures: HashMap::new(),
             bind_groups: HashMap::new(),
+            functions: HashMap::new(),
             entry_point_index,
             shader_location_offset,
             current_slot,
             current_bind_group,
             shader_stage,
             pending_statements: Vec::new(),
+            storage_group: None,
+            storage_index: None,
         })
     }
 
@@ -147,4 +173,111 @@ impl BindingBuilder {
         sorted_bindings.sort_by_key(|a| a.slot);
         sorted_bindings
     }
+
+    /// The bind group layouts of a pipeline layout, with `fixed` in the first groups, which are
+    /// declared by the shader itself, followed by the groups this builder assigned to
+    /// uniforms, textures and storage buffers. `storage_layout` is the layout returned by
+    /// [`BindingBuilder::storage_bind_group_layout`].
+    pub fn bind_group_layouts(
+        &self,
+        fixed: &[&BindGroupLayout],
+        storage_layout: Option<&BindGroupLayout>,
+    ) -> Vec<Option<BindGroupLayout>> {
+        let mut layouts = fixed.iter().map(|&layout| Some(layout.clone())).collect();
+        self.extend_bind_group_layouts(&mut layouts, storage_layout);
+        layouts
+    }
+
+    /// Adds the groups of this builder to `layouts`, for pipelines where another shader stage
+    /// was integrated by a builder that assigned the groups before these.
+    ///
+    /// Groups are assigned in the order the delegate's fields were integrated, so they are
+    /// placed by their group index rather than by map order.
+    pub fn extend_bind_group_layouts(
+        &self,
+        layouts: &mut Vec<Option<BindGroupLayout>>,
+        storage_layout: Option<&BindGroupLayout>,
+    ) {
+        let count = layouts.len().max(self.current_bind_group as usize);
+        layouts.resize(count, None);
+        for binding in self.uniforms.values() {
+            layouts[binding.group as usize] = Some(binding.bind_group_layout.as_ref().clone());
+        }
+        for binding in self.textures.values() {
+            layouts[binding.group as usize] =
+                Some(binding.inner.borrow().bind_group_layout.clone());
+        }
+        if let (Some(group), Some(layout)) = (self.storage_group, storage_layout) {
+            layouts[group as usize] = Some(layout.clone());
+        }
+    }
+
+    /// The layout of the bind group holding all storage bindings, or `None` if the entry
+    /// point has none. Storage buffers are writable in compute shaders and read-only
+    /// elsewhere.
+    pub fn storage_bind_group_layout(&self, device: &wgpu::Device) -> Option<BindGroupLayout> {
+        self.storage_group?;
+        let compute = self.shader_stage == ShaderStage::Compute;
+        let entries = (0..self.storage_buffers.len() as u32)
+            .map(|binding| wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility: if compute {
+                    wgpu::ShaderStages::COMPUTE
+                } else {
+                    wgpu::ShaderStages::VERTEX
+                },
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage {
+                        read_only: !compute,
+                    },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            })
+            .collect_vec();
+        Some(
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("storage bind group layout"),
+                entries: &entries,
+            }),
+        )
+    }
+
+    /// Binds the first `instance_count` instances of each storage buffer.
+    ///
+    /// Only the instances in use are bound, since that is what the bounds checks in compute
+    /// kernels compare against. The bind group has to be recreated whenever an instance
+    /// buffer is reallocated.
+    pub fn storage_bind_group(
+        &self,
+        device: &wgpu::Device,
+        layout: &BindGroupLayout,
+        instance_count: usize,
+    ) -> BindGroup {
+        let bindings = self
+            .storage_buffers
+            .values()
+            .sorted_by_key(|binding| binding.binding)
+            .map(|binding| (binding, binding.inner.borrow()))
+            .collect_vec();
+        let entries = bindings
+            .iter()
+            .map(|(binding, inner)| wgpu::BindGroupEntry {
+                binding: binding.binding,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &inner.buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(
+                        instance_count as u64 * binding.descriptor.struct_size,
+                    ),
+                }),
+            })
+            .collect_vec();
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("storage bind group"),
+            layout,
+            entries: &entries,
+        })
+    }
 }
@@ -12,4 +12,12 @@ pub enum ShaderError {
     EntryPointNotFound(String),
     #[error("variable '{0}' not found in shader")]
     VariableNotFound(String),
+    #[error("function '{0}' not found in shader")]
+    FunctionNotFound(String),
+    #[error("invalid swizzle: {0}")]
+    InvalidSwizzle(String),
+    #[error("unsupported shader functions: {0}")]
+    UnsupportedFunctions(String),
+    #[error("invalid call: {0}")]
+    InvalidCall(String),
 }
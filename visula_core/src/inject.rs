@@ -332,12 +332,324 @@ pub fn inject_before_return(
     Ok(())
 }
 
+/// Injects `fields` into a compute entry point.
+///
+/// The fields are stored in the local variable `variable_name` before the kernel runs. Fields
+/// that are plain instance fields are written back to their storage buffers before the kernel
+/// returns, so that the kernel updates the instances in place by modifying the variable.
+/// The kernel only runs for invocations within the length of the bound instance buffers.
+pub fn inject_compute(
+    module: &mut Module,
+    binding_builder: &mut BindingBuilder,
+    variable_name: &str,
+    fields: &[Expression],
+) -> Result<(), ShaderError> {
+    let variable = entry_point!(module, binding_builder.shader_stage)
+        .function
+        .local_variables
+        .fetch_if(|variable| variable.name == Some(variable_name.into()))
+        .ok_or_else(|| ShaderError::VariableNotFound(variable_name.to_string()))?;
+    let variable_expression = entry_point!(module, binding_builder.shader_stage)
+        .function
+        .expressions
+        .fetch_if(|expression| match expression {
+            naga::Expression::LocalVariable(v) => v == &variable,
+            _ => false,
+        })
+        .ok_or_else(|| ShaderError::VariableNotFound(variable_name.to_string()))?;
+
+    let fields_setup = fields
+        .iter()
+        .enumerate()
+        .map(|(index, value)| {
+            let expression = value.setup(module, binding_builder);
+            let access_index = entry_point!(module, binding_builder.shader_stage)
+                .function
+                .expressions
+                .append(
+                    naga::Expression::AccessIndex {
+                        index: index as u32,
+                        base: variable_expression,
+                    },
+                    naga::Span::default(),
+                );
+            Ok(::naga::Statement::Store {
+                pointer: access_index,
+                value: expression,
+            })
+        })
+        .collect::<Result<Vec<_>, ShaderError>>()?;
+
+    let mut write_back = Vec::new();
+    for (index, value) in fields.iter().enumerate() {
+        let Expression::InstanceField(field) = value else {
+            continue;
+        };
+        let expressions = &mut entry_point!(module, binding_builder.shader_stage)
+            .function
+            .expressions;
+        let access_index = expressions.append(
+            naga::Expression::AccessIndex {
+                index: index as u32,
+                base: variable_expression,
+            },
+            naga::Span::default(),
+        );
+        let load = expressions.append(
+            naga::Expression::Load {
+                pointer: access_index,
+            },
+            naga::Span::default(),
+        );
+        write_back.append(&mut crate::integrate::store_storage_field(
+            module,
+            binding_builder,
+            &field.buffer_handle,
+            field.field_index,
+            load,
+        ));
+    }
+
+    let mut new_body =
+        ::naga::Block::from_vec(binding_builder.pending_statements.drain(..).collect());
+    for store in fields_setup {
+        new_body.push(store, naga::Span::default());
+    }
+    let original_body = insert_before_returns(
+        &entry_point!(module, binding_builder.shader_stage)
+            .function
+            .body,
+        &write_back,
+    );
+    let ends_with_return = matches!(original_body.last(), Some(naga::Statement::Return { .. }));
+    for (statement, span) in original_body.span_iter() {
+        new_body.push(statement.clone(), *span);
+    }
+    if !ends_with_return {
+        for statement in write_back {
+            new_body.push(statement, naga::Span::default());
+        }
+    }
+
+    // Workgroups usually extend past the last instance, so skip invocations outside the
+    // buffers rather than letting them read and write out of bounds.
+    let mut in_bounds = None;
+    if !binding_builder.storage_buffers.is_empty() {
+        let index = crate::integrate::storage_index(module, binding_builder);
+        let expressions = &mut entry_point!(module, binding_builder.shader_stage)
+            .function
+            .expressions;
+        for binding in binding_builder.storage_buffers.values() {
+            let length = expressions.append(
+                naga::Expression::ArrayLength(binding.expression),
+                naga::Span::default(),
+            );
+            let stride = expressions.append(
+                naga::Expression::Literal(naga::Literal::U32(
+                    (binding.descriptor.struct_size / 4) as u32,
+                )),
+                naga::Span::default(),
+            );
+            let count = expressions.append(
+                naga::Expression::Binary {
+                    op: naga::BinaryOperator::Divide,
+                    left: length,
+                    right: stride,
+                },
+                naga::Span::default(),
+            );
+            let condition = expressions.append(
+                naga::Expression::Binary {
+                    op: naga::BinaryOperator::Less,
+                    left: index,
+                    right: count,
+                },
+                naga::Span::default(),
+            );
+            in_bounds = Some(match in_bounds {
+                None => condition,
+                Some(previous) => expressions.append(
+                    naga::Expression::Binary {
+                        op: naga::BinaryOperator::LogicalAnd,
+                        left: previous,
+                        right: condition,
+                    },
+                    naga::Span::default(),
+                ),
+            });
+        }
+    }
+    if let Some(condition) = in_bounds {
+        new_body = naga::Block::from_vec(vec![naga::Statement::If {
+            condition,
+            accept: new_body,
+            reject: naga::Block::new(),
+        }]);
+    }
+
+    entry_point!(module, binding_builder.shader_stage)
+        .function
+        .body = new_body;
+
+    let info =
+        naga::valid::Validator::new(ValidationFlags::empty(), naga::valid::Capabilities::all())
+            .validate(module)
+            .map_err(Box::new)?;
+    let output_str = naga::back::wgsl::write_string(module, &info, WriterFlags::all())?;
+    log::debug!("Resulting shader code (inject_compute):\n{output_str}");
+    Ok(())
+}
+
+/// Turns the entry point `entry_point_name` into a regular function and redirects all calls
+/// from entry points to the placeholder function `function_name` to it.
+///
+/// Delegates can only be injected into entry points, so this is how they end up in a function
+/// that is called from another entry point, such as a transfer function evaluated for every
+/// step of a loop. The entry point must have the same signature as the placeholder, and its
+/// arguments and result lose their bindings.
+pub fn replace_function_with_entry_point(
+    module: &mut Module,
+    entry_point_name: &str,
+    function_name: &str,
+) -> Result<(), ShaderError> {
+    let entry_point_index = module
+        .entry_points
+        .iter()
+        .position(|entry_point| entry_point.name == entry_point_name)
+        .ok_or_else(|| ShaderError::EntryPointNotFound(entry_point_name.to_string()))?;
+    let placeholder = module
+        .functions
+        .fetch_if(|function| function.name.as_deref() == Some(function_name))
+        .ok_or_else(|| ShaderError::FunctionNotFound(function_name.to_string()))?;
+
+    let mut function = module.entry_points.remove(entry_point_index).function;
+    function.name = Some(function_name.into());
+    for argument in function.arguments.iter_mut() {
+        argument.binding = None;
+    }
+    if let Some(result) = function.result.as_mut() {
+        result.binding = None;
+    }
+    let replacement = module.functions.append(function, naga::Span::default());
+
+    for entry_point in module.entry_points.iter_mut() {
+        for (_, expression) in entry_point.function.expressions.iter_mut() {
+            if *expression == naga::Expression::CallResult(placeholder) {
+                *expression = naga::Expression::CallResult(replacement);
+            }
+        }
+        redirect_calls(&mut entry_point.function.body, placeholder, replacement);
+    }
+    Ok(())
+}
+
+/// Replaces calls to `from` with calls to `to` in `block`, including nested blocks.
+fn redirect_calls(
+    block: &mut naga::Block,
+    from: naga::Handle<naga::Function>,
+    to: naga::Handle<naga::Function>,
+) {
+    for statement in block.iter_mut() {
+        match statement {
+            naga::Statement::Call { function, .. } if *function == from => *function = to,
+            naga::Statement::Block(inner) => redirect_calls(inner, from, to),
+            naga::Statement::If { accept, reject, .. } => {
+                redirect_calls(accept, from, to);
+                redirect_calls(reject, from, to);
+            }
+            naga::Statement::Loop {
+                body, continuing, ..
+            } => {
+                redirect_calls(body, from, to);
+                redirect_calls(continuing, from, to);
+            }
+            naga::Statement::Switch { cases, .. } => {
+                for case in cases.iter_mut() {
+                    redirect_calls(&mut case.body, from, to);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Copies `block`, inserting `statements` before every return, including nested ones.
+fn insert_before_returns(block: &naga::Block, statements: &[naga::Statement]) -> naga::Block {
+    let mut new_block = naga::Block::new();
+    for (statement, span) in block.span_iter() {
+        let statement = match statement {
+            naga::Statement::Return { .. } => {
+                for inserted in statements {
+                    new_block.push(inserted.clone(), naga::Span::default());
+                }
+                statement.clone()
+            }
+            naga::Statement::Block(inner) => {
+                naga::Statement::Block(insert_before_returns(inner, statements))
+            }
+            naga::Statement::If {
+                condition,
+                accept,
+                reject,
+            } => naga::Statement::If {
+                condition: *condition,
+                accept: insert_before_returns(accept, statements),
+                reject: insert_before_returns(reject, statements),
+            },
+            naga::Statement::Loop {
+                body,
+                continuing,
+                break_if,
+            } => naga::Statement::Loop {
+                body: insert_before_returns(body, statements),
+                continuing: continuing.clone(),
+                break_if: *break_if,
+            },
+            naga::Statement::Switch { selector, cases } => naga::Statement::Switch {
+                selector: *selector,
+                cases: cases
+                    .iter()
+                    .map(|case| naga::SwitchCase {
+                        value: case.value,
+                        body: insert_before_returns(&case.body, statements),
+                        fall_through: case.fall_through,
+                    })
+                    .collect(),
+            },
+            _ => statement.clone(),
+        };
+        new_block.push(statement, *span);
+    }
+    new_block
+}
+
 #[cfg(test)]
 mod tests {
     use glam::Vec3;
 
     use super::*;
 
+    /// Injects `fields` into the line vertex of `basic.wgsl` and returns the written shader,
+    /// after checking that it parses and validates again.
+    fn inject_vertex_fields(fields: &[Expression]) -> String {
+        let _ = env_logger::try_init();
+        let mut module =
+            naga::front::wgsl::parse_str(include_str!("./shaders/basic.wgsl")).unwrap();
+        let mut binding_builder = BindingBuilder::new(&module, "vs_main", 2).unwrap();
+        inject(&mut module, &mut binding_builder, "line_vertex", fields).unwrap();
+
+        let info =
+            naga::valid::Validator::new(ValidationFlags::empty(), naga::valid::Capabilities::all())
+                .validate(&module)
+                .unwrap();
+        let output = naga::back::wgsl::write_string(&module, &info, WriterFlags::empty()).unwrap();
+        let written = naga::front::wgsl::parse_str(&output).unwrap();
+        naga::valid::Validator::new(ValidationFlags::all(), naga::valid::Capabilities::all())
+            .validate(&written)
+            .unwrap_or_else(|error| panic!("{error:?}\n{output}"));
+        output
+    }
+
     #[test]
     fn test_inject() {
         let _ = env_logger::try_init();
@@ -367,4 +679,245 @@ mod tests {
         )
         .unwrap();
     }
+
+    #[test]
+    fn test_inject_compute() {
+        let _ = env_logger::try_init();
+        let mut module =
+            naga::front::wgsl::parse_str(include_str!("./shaders/compute.wgsl")).unwrap();
+        let fields: Vec<Expression> = vec![
+            Vec3::new(0.0, 0.0, 0.0).into(),
+            Vec3::new(1.0, 0.0, 0.0).into(),
+            0.1.into(),
+        ];
+        let mut binding_builder = BindingBuilder::new(&module, "cs_main", 0).unwrap();
+        inject_compute(&mut module, &mut binding_builder, "particle", &fields).unwrap();
+        assert!(binding_builder.storage_buffers.is_empty());
+    }
+
+    #[test]
+    fn test_inject_compute_instance_field() {
+        let _ = env_logger::try_init();
+        let (device, _queue) = wgpu::Device::noop(&Default::default());
+        let positions = crate::InstanceBuffer::<Vec3>::new_with_init(
+            &device,
+            &[Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 2.0, 3.0)],
+        );
+        let mut module =
+            naga::front::wgsl::parse_str(include_str!("./shaders/compute.wgsl")).unwrap();
+        let fields: Vec<Expression> = vec![
+            positions.instance(),
+            Vec3::new(1.0, 0.0, 0.0).into(),
+            0.1.into(),
+        ];
+        let mut binding_builder = BindingBuilder::new(&module, "cs_main", 0).unwrap();
+        inject_compute(&mut module, &mut binding_builder, "particle", &fields).unwrap();
+        assert_eq!(binding_builder.storage_group, Some(1));
+        assert_eq!(binding_builder.storage_buffers.len(), 1);
+        let storage = binding_builder.storage_buffers.values().next().unwrap();
+        assert_eq!(storage.binding, 0);
+
+        let info =
+            naga::valid::Validator::new(ValidationFlags::empty(), naga::valid::Capabilities::all())
+                .validate(&module)
+                .unwrap();
+        let output = naga::back::wgsl::write_string(&module, &info, WriterFlags::empty()).unwrap();
+        assert!(output
+            .contains("@group(1) @binding(0) \nvar<storage, read_write> instances_0_: array<u32>"));
+        assert!(output.contains("arrayLength((&instances_0_)) / 3u"));
+        assert!(output.contains("@builtin(num_workgroups)"));
+        // The position is written back both before the early return and at the end.
+        let early_return = output.find("return;").unwrap();
+        assert!(output[..early_return].contains("instances_0_["));
+        assert!(output[early_return + 1..].contains("instances_0_["));
+        let written = naga::front::wgsl::parse_str(&output).unwrap();
+        naga::valid::Validator::new(ValidationFlags::all(), naga::valid::Capabilities::all())
+            .validate(&written)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_inject_select() {
+        let width = Expression::from(1.0);
+        let start = Expression::from(Vec3::new(0.0, 1.0, 2.0));
+        let end = Expression::from(Vec3::new(1.0, 0.0, 0.0));
+        let output = inject_vertex_fields(&[
+            crate::select(width.gt(0.5).and(!width.eq(2.0)), &start, &end),
+            crate::select(
+                start.lt(&end).and(start.gt(&end)).or(!start.eq(&end)),
+                &start,
+                &end,
+            ),
+            crate::select(width.le(0.0).or(width.ge(1.0)).not(), 1.0, width.clone()),
+        ]);
+        assert!(output.contains("select("));
+        // Boolean vectors are combined component-wise, since `&&` and `||` are scalar only.
+        assert!(output.contains(
+            "((vec3<f32>(0f, 1f, 2f) < vec3<f32>(1f, 0f, 0f)) & (vec3<f32>(0f, 1f, 2f) > vec3<f32>(1f, 0f, 0f))) | !("
+        ));
+        assert!(!output.contains("&&") && !output.contains("||"));
+    }
+
+    #[test]
+    fn test_inject_swizzle() {
+        let color = Expression::from(glam::Vec4::new(0.1, 0.2, 0.3, 0.4));
+        let position = Expression::from(glam::Vec2::new(1.0, 2.0));
+        let output = inject_vertex_fields(&[
+            color.swizzle("bgr"),
+            crate::vec3(position.x(), color.w(), position.swizzle("yx").y()),
+            color.swizzle("xyz").z() + position.y(),
+        ]);
+        assert!(output.contains("line_vertex.start = vec4<f32>(0.1f, 0.2f, 0.3f, 0.4f).zyx;"));
+        assert!(output.contains("vec2<f32>(1f, 2f).yx.y"));
+
+        assert_eq!(color.swizzle("rgb").component_count(), Some(3));
+        assert!(position.try_swizzle("xz").is_err());
+        assert!(position.x().try_swizzle("x").is_err());
+        assert!(color.try_swizzle("xyzwx").is_err());
+        assert!(color.try_swizzle("xq").is_err());
+        assert!(Expression::UV.try_swizzle("xy").is_ok());
+    }
+
+    #[test]
+    fn test_inject_conversions() {
+        let id = Expression::from(7u32);
+        let packed_color = Expression::from(0xff80_4020u32);
+        let output = inject_vertex_fields(&[
+            packed_color.unpack_unorm().swizzle("rgb"),
+            crate::vec3(id.as_f32(), Expression::from(2.5).as_i32().as_f32(), 1.0),
+            (id.as_i32() + 1).as_f32() * 0.5,
+        ]);
+        assert!(output.contains("unpack4x8unorm(4286595104u)"));
+        assert!(output.contains("f32(7u)"));
+        assert!(output.contains("i32(7u)"));
+
+        assert_eq!(packed_color.unpack_unorm().component_count(), Some(4));
+        assert_eq!(id.as_f32().component_count(), Some(1));
+    }
+
+    #[test]
+    fn test_inject_matrices_and_quaternions() {
+        let direction = Expression::from(glam::Vec3::new(1.0, 2.0, 3.0));
+        let rotation = crate::quat_mul(
+            crate::quat_from_axis_angle(glam::Vec3::Y, 0.5),
+            crate::quat_from_to(glam::Vec3::Z, &direction),
+        );
+        let matrix = Expression::from(glam::Mat3::from_rotation_z(0.25))
+            .mat_mul(crate::look_at(&direction, glam::Vec3::Y).transpose());
+        let transform = Expression::from(glam::Mat4::IDENTITY).inverse();
+        let output = inject_vertex_fields(&[
+            crate::quat_rotate(&rotation, glam::Vec3::X),
+            matrix.mat_mul(glam::Vec3::Z) * matrix.determinant(),
+            transform.mat_mul(glam::Vec4::new(1.0, 2.0, 3.0, 1.0)).x(),
+        ]);
+        assert!(output.contains("transpose(") && output.contains("determinant("));
+
+        assert_eq!(rotation.component_count(), Some(4));
+        assert_eq!(matrix.determinant().component_count(), Some(1));
+    }
+
+    #[test]
+    fn test_inject_shader_functions() {
+        let functions = crate::ShaderFunctions::new(
+            r#"
+            const SCALE: f32 = 2.0;
+            const OFFSET = vec3<f32>(0.5, 0.25, 0.125);
+
+            struct Wave {
+                amplitude: f32,
+                frequency: f32,
+            };
+
+            fn wave(wave: Wave, x: f32) -> f32 {
+                return wave.amplitude * sin(wave.frequency * x);
+            }
+
+            fn noise(position: vec3<f32>) -> f32 {
+                var sum = 0.0;
+                for (var i = 0; i < 3; i++) {
+                    sum += wave(Wave(1.0 / f32(i + 1), SCALE * f32(i + 1)), position[i]);
+                }
+                return sum;
+            }
+
+            fn offset(position: vec3<f32>, amount: f32) -> vec3<f32> {
+                return position + amount * OFFSET;
+            }
+            "#,
+        )
+        .unwrap();
+        let position = Expression::from(Vec3::new(1.0, 2.0, 3.0));
+        let noise = functions.call("noise", &[position.swizzle("zyx")]);
+        let offset = functions.call("offset", &[position.clone(), noise.clone()]);
+        let output = inject_vertex_fields(&[
+            offset.clone(),
+            functions.call("offset", &[Vec3::ZERO.into(), 1.0.into()]),
+            noise * 0.5,
+        ]);
+        // The snippet is imported once, however many times its functions are called.
+        assert_eq!(output.matches("fn noise(").count(), 1);
+
+        assert_eq!(offset.component_count(), Some(3));
+        assert!(matches!(
+            functions.try_call("missing", &[]),
+            Err(ShaderError::FunctionNotFound(_))
+        ));
+        assert!(functions.try_call("noise", &[]).is_err());
+        assert!(functions.try_call("noise", &[1.0.into()]).is_err());
+        assert!(crate::ShaderFunctions::new("@compute @workgroup_size(1) fn main() {}").is_err());
+    }
+
+    #[test]
+    fn test_replace_function_with_entry_point() {
+        let _ = env_logger::try_init();
+        let mut module = naga::front::wgsl::parse_str(
+            r#"
+            struct Transfer {
+                color: vec4<f32>,
+            };
+
+            fn transfer(value: f32) -> vec4<f32> {
+                return vec4<f32>(0.0);
+            }
+
+            @fragment
+            fn fs_transfer(@location(0) value: f32) -> @location(0) vec4<f32> {
+                var volume_transfer: Transfer;
+                return volume_transfer.color;
+            }
+
+            @fragment
+            fn fs_main(@location(0) value: f32) -> @location(0) vec4<f32> {
+                var sum = vec4<f32>(0.0);
+                for (var i = 0; i < 4; i++) {
+                    sum += transfer(value * f32(i));
+                }
+                return sum;
+            }
+            "#,
+        )
+        .unwrap();
+        let mut binding_builder = BindingBuilder::new(&module, "fs_transfer", 0).unwrap();
+        let value = Expression::VolumeValue;
+        inject(
+            &mut module,
+            &mut binding_builder,
+            "volume_transfer",
+            &[crate::vec4(value.clone(), value.clone(), value, 1.0)],
+        )
+        .unwrap();
+        replace_function_with_entry_point(&mut module, "fs_transfer", "transfer").unwrap();
+        assert_eq!(module.entry_points.len(), 1);
+
+        // Injected expressions are only emitted once written, so validate the written shader.
+        let info =
+            naga::valid::Validator::new(ValidationFlags::empty(), naga::valid::Capabilities::all())
+                .validate(&module)
+                .unwrap();
+        let output = naga::back::wgsl::write_string(&module, &info, WriterFlags::empty()).unwrap();
+        let written = naga::front::wgsl::parse_str(&output).unwrap();
+        naga::valid::Validator::new(ValidationFlags::all(), naga::valid::Capabilities::all())
+            .validate(&written)
+            .unwrap();
+    }
 }
@@ -53,9 +53,15 @@ macro_rules! impl_instance_for_plain {
 
 impl_instance_for_plain!(
     f32,
+    i32,
+    u32,
     [f32; 2],
     [f32; 3],
     [f32; 4],
+    [u8; 4],
+    [u16; 2],
+    [half::f16; 2],
+    [half::f16; 4],
     glam::Vec2,
     glam::Vec3,
     glam::Vec4,
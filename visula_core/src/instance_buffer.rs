@@ -8,12 +8,30 @@ use wgpu::{util::DeviceExt, Device, Queue};
 
 use crate::Instance;
 
+/// How vertex shaders read the fields of an instance buffer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InstanceBindingMode {
+    /// Each field is a per-instance vertex attribute. The struct is interleaved in one buffer
+    /// and all buffers of a pipeline share the adapter's vertex attribute limit.
+    #[default]
+    Vertex,
+    /// The buffer is bound as a read-only storage buffer and fields are read by
+    /// `instance_index`. This does not use any vertex attributes, so many separate buffers can
+    /// feed one pipeline, but requires an adapter that supports storage buffers in vertex
+    /// shaders (not WebGL). Supported by the quad-based primitives and compute pipelines.
+    Storage,
+}
+
 #[derive(Debug)]
 pub struct InstanceBufferInner {
     pub label: String,
     pub buffer: wgpu::Buffer,
+    /// The number of instances in use, which is what pipelines draw.
     pub count: usize,
+    /// The number of instances that fit in `buffer`.
+    pub capacity: usize,
     pub handle: Uuid,
+    pub binding_mode: InstanceBindingMode,
     usage: BufferUsages,
 }
 
@@ -24,11 +42,20 @@ pub struct InstanceBuffer<T: Pod> {
 
 impl<T: Instance + Pod> InstanceBuffer<T> {
     pub fn new(device: &Device) -> Self {
-        let usage = BufferUsages::VERTEX | BufferUsages::COPY_DST;
+        Self::with_capacity(device, 0)
+    }
+
+    /// Creates an empty buffer with room for `capacity` instances.
+    pub fn with_capacity(device: &Device, capacity: usize) -> Self {
+        let size = instance_size::<T>();
+        let usage = BufferUsages::VERTEX
+            | BufferUsages::STORAGE
+            | BufferUsages::COPY_DST
+            | BufferUsages::COPY_SRC;
         let label = std::any::type_name::<T>();
         let buffer = device.create_buffer(&wgpu::BufferDescriptor {
             mapped_at_creation: false,
-            size: 0,
+            size: capacity as u64 * size,
             label: Some(label),
             usage,
         });
@@ -38,6 +65,8 @@ impl<T: Instance + Pod> InstanceBuffer<T> {
                 label: label.into(),
                 buffer,
                 count: 0,
+                capacity,
+                binding_mode: InstanceBindingMode::default(),
                 usage,
                 handle: uuid::Uuid::new_v4(),
             })),
@@ -46,8 +75,13 @@ impl<T: Instance + Pod> InstanceBuffer<T> {
     }
 
     pub fn new_with_init(device: &wgpu::Device, data: &[T]) -> Self {
+        // Checks the alignment up front, rather than on the first update.
+        instance_size::<T>();
         let label = std::any::type_name::<T>();
-        let usage = BufferUsages::VERTEX | BufferUsages::COPY_DST;
+        let usage = BufferUsages::VERTEX
+            | BufferUsages::STORAGE
+            | BufferUsages::COPY_DST
+            | BufferUsages::COPY_SRC;
         let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some(label),
             contents: bytemuck::cast_slice(data),
@@ -60,25 +94,122 @@ impl<T: Instance + Pod> InstanceBuffer<T> {
                 label: label.into(),
                 buffer,
                 count: data.len(),
+                capacity: data.len(),
+                binding_mode: InstanceBindingMode::default(),
                 usage,
             })),
             phantom: PhantomData {},
         }
     }
 
+    /// Replaces the contents of the buffer with `data`.
+    ///
+    /// The buffer is only reallocated if `data` does not fit in its capacity.
     pub fn update(&self, device: &Device, queue: &Queue, data: &[T]) {
         let mut inner = self.inner.borrow_mut();
         log::trace!("Update buffer '{}' with length {}", inner.label, data.len());
-        if data.len() == inner.count {
+        if data.len() <= inner.capacity {
             queue.write_buffer(&inner.buffer, 0, bytemuck::cast_slice(data));
         } else {
             inner.buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Instance buffer"),
+                label: Some(&inner.label),
                 contents: bytemuck::cast_slice(data),
                 usage: inner.usage,
             });
-            inner.count = data.len();
+            inner.capacity = data.len();
         }
+        inner.count = data.len();
+    }
+
+    /// Overwrites the instances starting at index `offset` and leaves the rest untouched.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range extends past the current length.
+    pub fn update_range(&self, queue: &Queue, offset: usize, data: &[T]) {
+        let inner = self.inner.borrow();
+        assert!(
+            offset + data.len() <= inner.count,
+            "range {}..{} out of bounds for instance buffer '{}' of length {}",
+            offset,
+            offset + data.len(),
+            inner.label,
+            inner.count
+        );
+        log::trace!(
+            "Update buffer '{}' range {}..{}",
+            inner.label,
+            offset,
+            offset + data.len()
+        );
+        queue.write_buffer(
+            &inner.buffer,
+            offset as u64 * instance_size::<T>(),
+            bytemuck::cast_slice(data),
+        );
+    }
+
+    /// Appends `data` after the instances in use.
+    ///
+    /// When the capacity is exceeded the buffer is reallocated, see [`grown_capacity`], and
+    /// the existing instances are copied on the GPU.
+    pub fn push(&self, device: &Device, queue: &Queue, data: &[T]) {
+        let mut inner = self.inner.borrow_mut();
+        let size = instance_size::<T>();
+        let count = inner.count + data.len();
+        if let Some(capacity) = grown_capacity(inner.capacity, count) {
+            log::trace!(
+                "Grow buffer '{}' from capacity {} to {}",
+                inner.label,
+                inner.capacity,
+                capacity
+            );
+            let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                mapped_at_creation: false,
+                size: capacity as u64 * size,
+                label: Some(&inner.label),
+                usage: inner.usage,
+            });
+            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("instance buffer growth"),
+            });
+            encoder.copy_buffer_to_buffer(&inner.buffer, 0, &buffer, 0, inner.count as u64 * size);
+            queue.submit(Some(encoder.finish()));
+            inner.buffer = buffer;
+            inner.capacity = capacity;
+        }
+        queue.write_buffer(
+            &inner.buffer,
+            inner.count as u64 * size,
+            bytemuck::cast_slice(data),
+        );
+        inner.count = count;
+    }
+
+    /// Shortens the buffer to its first `len` instances while keeping its capacity. Has no
+    /// effect if `len` is not less than the current length.
+    pub fn truncate(&self, len: usize) {
+        let mut inner = self.inner.borrow_mut();
+        inner.count = inner.count.min(len);
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.borrow().count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.inner.borrow().capacity
+    }
+
+    /// Sets how vertex shaders read this buffer. Must be set before the buffer's instance
+    /// fields are used to create a pipeline.
+    pub fn with_binding_mode(self, binding_mode: InstanceBindingMode) -> Self {
+        self.inner.borrow_mut().binding_mode = binding_mode;
+        self
     }
 
     pub fn instance(&self) -> T::Type {
@@ -86,6 +217,34 @@ impl<T: Instance + Pod> InstanceBuffer<T> {
     }
 }
 
+/// The size of one instance in bytes.
+///
+/// Offsets and sizes of buffer writes and copies must be multiples of
+/// [`wgpu::COPY_BUFFER_ALIGNMENT`]. Since they are all whole numbers of instances, this holds
+/// as long as the instance size is a multiple of it.
+///
+/// # Panics
+///
+/// Panics if the size of `T` is not a multiple of [`wgpu::COPY_BUFFER_ALIGNMENT`].
+fn instance_size<T>() -> u64 {
+    let size = std::mem::size_of::<T>() as u64;
+    assert!(
+        size.is_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT),
+        "instance type '{}' is {size} bytes, which is not a multiple of {} bytes",
+        std::any::type_name::<T>(),
+        wgpu::COPY_BUFFER_ALIGNMENT
+    );
+    size
+}
+
+/// The capacity to reallocate to for `count` instances, or `None` if they fit in `capacity`.
+///
+/// Grows to at least twice the capacity, so that appending a few instances every frame only
+/// reallocates occasionally.
+fn grown_capacity(capacity: usize, count: usize) -> Option<usize> {
+    (count > capacity).then(|| count.max(2 * capacity))
+}
+
 pub trait InstanceDeviceExt {
     fn create_instance_buffer<T>(&self) -> InstanceBuffer<T>
     where
@@ -100,3 +259,90 @@ impl InstanceDeviceExt for wgpu::Device {
         InstanceBuffer::<T>::new(self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Copies the instances in use back from the GPU.
+    fn read_back(device: &Device, queue: &Queue, buffer: &InstanceBuffer<f32>) -> Vec<f32> {
+        let inner = buffer.inner.borrow();
+        let size = (inner.count * std::mem::size_of::<f32>()) as u64;
+        let staging = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size,
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&Default::default());
+        encoder.copy_buffer_to_buffer(&inner.buffer, 0, &staging, 0, size);
+        queue.submit(Some(encoder.finish()));
+        let slice = staging.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |result| result.unwrap());
+        device.poll(wgpu::PollType::wait_indefinitely()).unwrap();
+        let values = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+        values
+    }
+
+    #[test]
+    fn test_push() {
+        let (device, queue) = wgpu::Device::noop(&Default::default());
+        let buffer = InstanceBuffer::<f32>::new_with_init(&device, &[1.0, 2.0]);
+        buffer.push(&device, &queue, &[3.0]);
+        assert_eq!(buffer.capacity(), 4);
+        buffer.push(&device, &queue, &[4.0]);
+        assert_eq!(buffer.capacity(), 4);
+        buffer.push(&device, &queue, &[5.0, 6.0]);
+        assert_eq!(buffer.capacity(), 8);
+        assert_eq!(buffer.len(), 6);
+        assert_eq!(
+            read_back(&device, &queue, &buffer),
+            [1.0, 2.0, 3.0, 4.0, 5.0, 6.0]
+        );
+    }
+
+    #[test]
+    fn test_truncate_and_update_range() {
+        let (device, queue) = wgpu::Device::noop(&Default::default());
+        let buffer = InstanceBuffer::<f32>::new_with_init(&device, &[1.0, 2.0, 3.0, 4.0]);
+        buffer.truncate(2);
+        buffer.truncate(3);
+        assert_eq!(buffer.len(), 2);
+        assert_eq!(buffer.capacity(), 4);
+        buffer.push(&device, &queue, &[5.0]);
+        buffer.update_range(&queue, 1, &[6.0, 7.0]);
+        assert_eq!(read_back(&device, &queue, &buffer), [1.0, 6.0, 7.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn test_update_range_past_len() {
+        let (device, queue) = wgpu::Device::noop(&Default::default());
+        let buffer = InstanceBuffer::<f32>::with_capacity(&device, 4);
+        buffer.push(&device, &queue, &[1.0, 2.0]);
+        buffer.update_range(&queue, 1, &[3.0, 4.0]);
+    }
+
+    #[test]
+    fn test_grown_capacity() {
+        assert_eq!(grown_capacity(0, 0), None);
+        assert_eq!(grown_capacity(4, 3), None);
+        assert_eq!(grown_capacity(4, 4), None);
+        assert_eq!(grown_capacity(0, 3), Some(3));
+        assert_eq!(grown_capacity(4, 5), Some(8));
+        assert_eq!(grown_capacity(4, 20), Some(20));
+    }
+
+    #[test]
+    fn test_instance_size() {
+        assert_eq!(instance_size::<f32>(), 4);
+        assert_eq!(instance_size::<[half::f16; 2]>(), 4);
+        assert_eq!(instance_size::<glam::Vec3>(), 12);
+    }
+
+    #[test]
+    #[should_panic(expected = "not a multiple of 4 bytes")]
+    fn test_instance_size_unaligned() {
+        instance_size::<[u8; 3]>();
+    }
+}
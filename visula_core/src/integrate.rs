@@ -1,7 +1,7 @@
 use std::{cell::RefCell, rc::Rc};
 
 use crate::{
-    binding_builder::{InstanceBinding, UniformBinding, VertexBufferLayoutBuilder},
+    binding_builder::{InstanceBinding, StorageBinding, UniformBinding, VertexBufferLayoutBuilder},
     instance_buffer::InstanceBufferInner,
     uniform_buffer::UniformBufferInner,
     BindingBuilder, BufferBindingField,
@@ -40,24 +40,25 @@ pub fn integrate_instance(
         let field_type = module
             .types
             .insert(field.naga_type.clone(), naga::Span::default());
-        module.entry_points[entry_point_index]
-            .function
-            .arguments
-            .push(naga::FunctionArgument {
-                name: Some(field.name.clone()),
-                ty: field_type,
-                binding: Some(naga::Binding::Location {
-                    location: shader_location,
-                    interpolation: if binding_builder.shader_stage == naga::ShaderStage::Fragment {
-                        Some(naga::Interpolation::Flat)
-                    } else {
-                        None
-                    },
-                    sampling: None,
-                    blend_src: None,
-                    per_primitive: false,
-                }),
-            });
+        let arguments = &mut module.entry_points[entry_point_index].function.arguments;
+        // Builtin arguments may precede the instance attributes, so the argument index is
+        // not necessarily the shader location.
+        let function_argument = arguments.len() as u32;
+        arguments.push(naga::FunctionArgument {
+            name: Some(field.name.clone()),
+            ty: field_type,
+            binding: Some(naga::Binding::Location {
+                location: shader_location,
+                interpolation: if binding_builder.shader_stage == naga::ShaderStage::Fragment {
+                    Some(naga::Interpolation::Flat)
+                } else {
+                    None
+                },
+                sampling: None,
+                blend_src: None,
+                per_primitive: false,
+            }),
+        });
 
         attributes.push(wgpu::VertexAttribute {
             format: field.vertex_attr_format,
@@ -65,9 +66,7 @@ pub fn integrate_instance(
             shader_location,
         });
 
-        binding_fields.push(BufferBindingField {
-            function_argument: shader_location,
-        });
+        binding_fields.push(BufferBindingField { function_argument });
 
         offset += field.vertex_attr_format.size();
     }
@@ -92,6 +91,457 @@ pub fn integrate_instance(
     binding_builder.current_slot += 1;
 }
 
+/// Binds an instance buffer as a runtime-sized `array<u32>` in the entry point's storage bind
+/// group, so that its fields can be read by the index of the current instance.
+///
+/// In compute entry points the buffer is also writable.
+pub fn integrate_storage_instance(
+    descriptor: &Rc<InstanceDescriptor>,
+    inner: &Rc<RefCell<InstanceBufferInner>>,
+    handle: &uuid::Uuid,
+    module: &mut naga::Module,
+    binding_builder: &mut BindingBuilder,
+) {
+    let entry_point_index = binding_builder.entry_point_index;
+    let group = match binding_builder.storage_group {
+        Some(group) => group,
+        None => {
+            let group = binding_builder.current_bind_group;
+            binding_builder.storage_group = Some(group);
+            binding_builder.current_bind_group += 1;
+            group
+        }
+    };
+    let binding = binding_builder.storage_buffers.len() as u32;
+    let access = if binding_builder.shader_stage == naga::ShaderStage::Compute {
+        naga::StorageAccess::LOAD | naga::StorageAccess::STORE
+    } else {
+        naga::StorageAccess::LOAD
+    };
+
+    let word_type = module.types.insert(
+        naga::Type {
+            name: None,
+            inner: naga::TypeInner::Scalar(naga::Scalar::U32),
+        },
+        naga::Span::default(),
+    );
+    let array_type = module.types.insert(
+        naga::Type {
+            name: None,
+            inner: naga::TypeInner::Array {
+                base: word_type,
+                size: naga::ArraySize::Dynamic,
+                stride: 4,
+            },
+        },
+        naga::Span::default(),
+    );
+    let storage_variable = module.global_variables.append(
+        naga::GlobalVariable {
+            name: Some(format!("instances_{binding}")),
+            binding: Some(naga::ResourceBinding { group, binding }),
+            space: naga::AddressSpace::Storage { access },
+            ty: array_type,
+            init: None,
+            memory_decorations: naga::MemoryDecorations::empty(),
+        },
+        naga::Span::default(),
+    );
+    let expression = module.entry_points[entry_point_index]
+        .function
+        .expressions
+        .append(
+            naga::Expression::GlobalVariable(storage_variable),
+            naga::Span::default(),
+        );
+
+    binding_builder.storage_buffers.insert(
+        *handle,
+        StorageBinding {
+            binding,
+            expression,
+            descriptor: descriptor.clone(),
+            inner: inner.clone(),
+        },
+    );
+}
+
+/// The index of the instance handled by the current invocation. This is `instance_index` in
+/// vertex entry points. In compute entry points it is the linear index of
+/// `global_invocation_id` in the dispatched grid, so that dispatches can spread over more than
+/// one dimension when there are too many workgroups for one.
+///
+/// The builtin arguments are added to the entry point if the shader does not declare them.
+pub fn storage_index(
+    module: &mut naga::Module,
+    binding_builder: &mut BindingBuilder,
+) -> naga::Handle<naga::Expression> {
+    if let Some(index) = binding_builder.storage_index {
+        return index;
+    }
+    let entry_point_index = binding_builder.entry_point_index;
+    if binding_builder.shader_stage != naga::ShaderStage::Compute {
+        let index = builtin_argument(
+            module,
+            entry_point_index,
+            naga::BuiltIn::InstanceIndex,
+            "visula_instance_index",
+            naga::TypeInner::Scalar(naga::Scalar::U32),
+        );
+        binding_builder.storage_index = Some(index);
+        return index;
+    }
+
+    let vector = naga::TypeInner::Vector {
+        size: naga::VectorSize::Tri,
+        scalar: naga::Scalar::U32,
+    };
+    let global_id = builtin_argument(
+        module,
+        entry_point_index,
+        naga::BuiltIn::GlobalInvocationId,
+        "visula_global_id",
+        vector.clone(),
+    );
+    let workgroup_count = builtin_argument(
+        module,
+        entry_point_index,
+        naga::BuiltIn::NumWorkGroups,
+        "visula_workgroup_count",
+        vector,
+    );
+    let workgroup_size = module.entry_points[entry_point_index].workgroup_size;
+    let expressions = &mut module.entry_points[entry_point_index].function.expressions;
+    let mut append = |expression| expressions.append(expression, naga::Span::default());
+    let [x, y, z] = [0, 1, 2].map(|index| {
+        append(naga::Expression::AccessIndex {
+            base: global_id,
+            index,
+        })
+    });
+    // The number of invocations along x and y in the whole dispatch.
+    let [width, height] = [0, 1].map(|index| {
+        let count = append(naga::Expression::AccessIndex {
+            base: workgroup_count,
+            index,
+        });
+        let size = append(naga::Expression::Literal(naga::Literal::U32(
+            workgroup_size[index as usize],
+        )));
+        append(naga::Expression::Binary {
+            op: naga::BinaryOperator::Multiply,
+            left: count,
+            right: size,
+        })
+    });
+    // x + width * (y + height * z)
+    let plane = append(naga::Expression::Binary {
+        op: naga::BinaryOperator::Multiply,
+        left: height,
+        right: z,
+    });
+    let row = append(naga::Expression::Binary {
+        op: naga::BinaryOperator::Add,
+        left: y,
+        right: plane,
+    });
+    let offset = append(naga::Expression::Binary {
+        op: naga::BinaryOperator::Multiply,
+        left: width,
+        right: row,
+    });
+    let index = append(naga::Expression::Binary {
+        op: naga::BinaryOperator::Add,
+        left: x,
+        right: offset,
+    });
+    binding_builder.storage_index = Some(index);
+    index
+}
+
+/// An expression for the builtin argument of the entry point, which is added with `name` and
+/// type `inner` if the shader does not declare it.
+fn builtin_argument(
+    module: &mut naga::Module,
+    entry_point_index: usize,
+    builtin: naga::BuiltIn,
+    name: &str,
+    inner: naga::TypeInner,
+) -> naga::Handle<naga::Expression> {
+    let existing = module.entry_points[entry_point_index]
+        .function
+        .arguments
+        .iter()
+        .position(|argument| argument.binding == Some(naga::Binding::BuiltIn(builtin)));
+    let argument_index = match existing {
+        Some(argument_index) => argument_index,
+        None => {
+            let ty = module
+                .types
+                .insert(naga::Type { name: None, inner }, naga::Span::default());
+            let arguments = &mut module.entry_points[entry_point_index].function.arguments;
+            arguments.push(naga::FunctionArgument {
+                name: Some(name.into()),
+                ty,
+                binding: Some(naga::Binding::BuiltIn(builtin)),
+            });
+            arguments.len() - 1
+        }
+    };
+    module.entry_points[entry_point_index]
+        .function
+        .expressions
+        .append(
+            naga::Expression::FunctionArgument(argument_index as u32),
+            naga::Span::default(),
+        )
+}
+
+/// Pointers to each `u32` word of a field of the current invocation's instance.
+fn storage_field_pointers(
+    module: &mut naga::Module,
+    binding_builder: &mut BindingBuilder,
+    handle: &uuid::Uuid,
+    field_index: usize,
+) -> Vec<naga::Handle<naga::Expression>> {
+    let index = storage_index(module, binding_builder);
+    let binding = &binding_builder.storage_buffers[handle];
+    let fields = &binding.descriptor.fields;
+    let stride = (binding.descriptor.struct_size / 4) as u32;
+    let offset = (fields[..field_index]
+        .iter()
+        .map(|field| field.vertex_attr_format.size())
+        .sum::<u64>()
+        / 4) as u32;
+    let word_count = (fields[field_index].vertex_attr_format.size() / 4) as u32;
+
+    let expressions = &mut module.entry_points[binding_builder.entry_point_index]
+        .function
+        .expressions;
+    let stride_literal = expressions.append(
+        naga::Expression::Literal(naga::Literal::U32(stride)),
+        naga::Span::default(),
+    );
+    let start = expressions.append(
+        naga::Expression::Binary {
+            op: naga::BinaryOperator::Multiply,
+            left: index,
+            right: stride_literal,
+        },
+        naga::Span::default(),
+    );
+    (0..word_count)
+        .map(|word| {
+            let word_offset = expressions.append(
+                naga::Expression::Literal(naga::Literal::U32(offset + word)),
+                naga::Span::default(),
+            );
+            let word_index = expressions.append(
+                naga::Expression::Binary {
+                    op: naga::BinaryOperator::Add,
+                    left: start,
+                    right: word_offset,
+                },
+                naga::Span::default(),
+            );
+            expressions.append(
+                naga::Expression::Access {
+                    base: binding.expression,
+                    index: word_index,
+                },
+                naga::Span::default(),
+            )
+        })
+        .collect()
+}
+
+/// Loads a field of the current invocation's instance from its storage buffer.
+pub fn load_storage_field(
+    module: &mut naga::Module,
+    binding_builder: &mut BindingBuilder,
+    handle: &uuid::Uuid,
+    field_index: usize,
+) -> naga::Handle<naga::Expression> {
+    let pointers = storage_field_pointers(module, binding_builder, handle, field_index);
+    let field = &binding_builder.storage_buffers[handle].descriptor.fields[field_index];
+    let format = field.vertex_attr_format;
+    let field_type = module
+        .types
+        .insert(field.naga_type.clone(), naga::Span::default());
+    let expressions = &mut module.entry_points[binding_builder.entry_point_index]
+        .function
+        .expressions;
+    let words: Vec<_> = pointers
+        .into_iter()
+        .map(|pointer| {
+            expressions.append(naga::Expression::Load { pointer }, naga::Span::default())
+        })
+        .collect();
+    unpack_storage_words(expressions, format, field_type, words)
+}
+
+/// Statements that store `value` into a field of the current invocation's instance.
+pub fn store_storage_field(
+    module: &mut naga::Module,
+    binding_builder: &mut BindingBuilder,
+    handle: &uuid::Uuid,
+    field_index: usize,
+    value: naga::Handle<naga::Expression>,
+) -> Vec<naga::Statement> {
+    let pointers = storage_field_pointers(module, binding_builder, handle, field_index);
+    let format =
+        binding_builder.storage_buffers[handle].descriptor.fields[field_index].vertex_attr_format;
+    let expressions = &mut module.entry_points[binding_builder.entry_point_index]
+        .function
+        .expressions;
+    let words = pack_storage_words(expressions, format, value);
+    pointers
+        .into_iter()
+        .zip(words)
+        .map(|(pointer, value)| naga::Statement::Store { pointer, value })
+        .collect()
+}
+
+fn bitcast(
+    expressions: &mut naga::Arena<naga::Expression>,
+    expr: naga::Handle<naga::Expression>,
+    kind: naga::ScalarKind,
+) -> naga::Handle<naga::Expression> {
+    expressions.append(
+        naga::Expression::As {
+            expr,
+            kind,
+            convert: None,
+        },
+        naga::Span::default(),
+    )
+}
+
+fn math(
+    expressions: &mut naga::Arena<naga::Expression>,
+    fun: naga::MathFunction,
+    arg: naga::Handle<naga::Expression>,
+) -> naga::Handle<naga::Expression> {
+    expressions.append(
+        naga::Expression::Math {
+            fun,
+            arg,
+            arg1: None,
+            arg2: None,
+            arg3: None,
+        },
+        naga::Span::default(),
+    )
+}
+
+/// The function that unpacks one word of a packed format, and its inverse.
+fn packing_functions(
+    format: wgpu::VertexFormat,
+) -> Option<(naga::MathFunction, naga::MathFunction)> {
+    match format {
+        wgpu::VertexFormat::Unorm8x4 => Some((
+            naga::MathFunction::Unpack4x8unorm,
+            naga::MathFunction::Pack4x8unorm,
+        )),
+        wgpu::VertexFormat::Unorm16x2 => Some((
+            naga::MathFunction::Unpack2x16unorm,
+            naga::MathFunction::Pack2x16unorm,
+        )),
+        wgpu::VertexFormat::Float16x2 | wgpu::VertexFormat::Float16x4 => Some((
+            naga::MathFunction::Unpack2x16float,
+            naga::MathFunction::Pack2x16float,
+        )),
+        _ => None,
+    }
+}
+
+/// Converts the `u32` words loaded from a storage buffer into a value of the field's type.
+///
+/// The words hold the bits of the field as it is laid out in the instance buffer, so float
+/// and signed fields are bitcast from them and packed fields are unpacked.
+pub fn unpack_storage_words(
+    expressions: &mut naga::Arena<naga::Expression>,
+    format: wgpu::VertexFormat,
+    field_type: naga::Handle<naga::Type>,
+    words: Vec<naga::Handle<naga::Expression>>,
+) -> naga::Handle<naga::Expression> {
+    let components: Vec<_> = match format {
+        wgpu::VertexFormat::Uint32 => return words[0],
+        wgpu::VertexFormat::Sint32 => {
+            return bitcast(expressions, words[0], naga::ScalarKind::Sint)
+        }
+        _ => match packing_functions(format) {
+            Some((unpack, _)) => words
+                .into_iter()
+                .map(|word| math(expressions, unpack, word))
+                .collect(),
+            None => words
+                .into_iter()
+                .map(|word| bitcast(expressions, word, naga::ScalarKind::Float))
+                .collect(),
+        },
+    };
+    if components.len() == 1 {
+        components[0]
+    } else {
+        expressions.append(
+            naga::Expression::Compose {
+                ty: field_type,
+                components,
+            },
+            naga::Span::default(),
+        )
+    }
+}
+
+/// Converts `value` into the `u32` words that store it in a storage buffer, the inverse of
+/// [`unpack_storage_words`].
+pub fn pack_storage_words(
+    expressions: &mut naga::Arena<naga::Expression>,
+    format: wgpu::VertexFormat,
+    value: naga::Handle<naga::Expression>,
+) -> Vec<naga::Handle<naga::Expression>> {
+    let word_count = (format.size() / 4) as u32;
+    match format {
+        wgpu::VertexFormat::Uint32 => vec![value],
+        wgpu::VertexFormat::Sint32 => vec![bitcast(expressions, value, naga::ScalarKind::Uint)],
+        wgpu::VertexFormat::Float16x4 => {
+            let (_, pack) = packing_functions(format).unwrap();
+            [
+                [naga::SwizzleComponent::X, naga::SwizzleComponent::Y],
+                [naga::SwizzleComponent::Z, naga::SwizzleComponent::W],
+            ]
+            .into_iter()
+            .map(|[first, second]| {
+                let pair = expressions.append(
+                    naga::Expression::Swizzle {
+                        size: naga::VectorSize::Bi,
+                        vector: value,
+                        pattern: [first, second, first, first],
+                    },
+                    naga::Span::default(),
+                );
+                math(expressions, pack, pair)
+            })
+            .collect()
+        }
+        _ => match packing_functions(format) {
+            Some((_, pack)) => vec![math(expressions, pack, value)],
+            None if word_count == 1 => vec![bitcast(expressions, value, naga::ScalarKind::Uint)],
+            None => (0..word_count)
+                .map(|index| {
+                    let component = expressions.append(
+                        naga::Expression::AccessIndex { base: value, index },
+                        naga::Span::default(),
+                    );
+                    bitcast(expressions, component, naga::ScalarKind::Uint)
+                })
+                .collect(),
+        },
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct UniformFieldDescriptor {
     pub name: String,
@@ -176,6 +626,7 @@ pub fn integrate_uniform(
         *handle,
         UniformBinding {
             expression: settings_expression,
+            group: bind_group,
             bind_group_layout: bind_group_layout.clone(),
             inner: inner.clone(),
         },
@@ -248,4 +699,87 @@ mod tests {
         assert_eq!(attrs[1].offset, 12); // 3 * 4 bytes
         assert_eq!(attrs[1].format, wgpu::VertexFormat::Float32x4);
     }
+
+    #[test]
+    fn test_storage_words_round_trip() {
+        use crate::{naga_type::NagaType, vertex_attr_format::VertexAttrFormat};
+        use naga::back::wgsl::WriterFlags;
+        use naga::valid::ValidationFlags;
+
+        let formats = [
+            (f32::naga_type(), f32::vertex_attr_format()),
+            (<[f32; 3]>::naga_type(), <[f32; 3]>::vertex_attr_format()),
+            (u32::naga_type(), u32::vertex_attr_format()),
+            (i32::naga_type(), i32::vertex_attr_format()),
+            (<[u8; 4]>::naga_type(), <[u8; 4]>::vertex_attr_format()),
+            (<[u16; 2]>::naga_type(), <[u16; 2]>::vertex_attr_format()),
+            (
+                <[half::f16; 2]>::naga_type(),
+                <[half::f16; 2]>::vertex_attr_format(),
+            ),
+            (
+                <[half::f16; 4]>::naga_type(),
+                <[half::f16; 4]>::vertex_attr_format(),
+            ),
+        ];
+        for (naga_type, format) in formats {
+            let mut module = naga::front::wgsl::parse_str(
+                r#"
+                @group(0) @binding(0) var<storage, read_write> words: array<u32>;
+
+                @compute @workgroup_size(1)
+                fn main() {
+                }
+                "#,
+            )
+            .unwrap();
+            let words = module.global_variables.iter().next().unwrap().0;
+            let field_type = module.types.insert(naga_type, naga::Span::default());
+            let function = &mut module.entry_points[0].function;
+            let expressions = &mut function.expressions;
+            let words = expressions.append(
+                naga::Expression::GlobalVariable(words),
+                naga::Span::default(),
+            );
+            let pointers: Vec<_> = (0..(format.size() / 4) as u32)
+                .map(|index| {
+                    expressions.append(
+                        naga::Expression::AccessIndex { base: words, index },
+                        naga::Span::default(),
+                    )
+                })
+                .collect();
+            let loads = pointers
+                .iter()
+                .map(|&pointer| {
+                    expressions.append(naga::Expression::Load { pointer }, naga::Span::default())
+                })
+                .collect();
+            let value = unpack_storage_words(expressions, format, field_type, loads);
+            let packed = pack_storage_words(expressions, format, value);
+            assert_eq!(packed.len(), pointers.len());
+            for (pointer, value) in pointers.into_iter().zip(packed) {
+                function.body.push(
+                    naga::Statement::Store { pointer, value },
+                    naga::Span::default(),
+                );
+            }
+
+            let info = naga::valid::Validator::new(
+                ValidationFlags::empty(),
+                naga::valid::Capabilities::all(),
+            )
+            .validate(&module)
+            .unwrap();
+            let output =
+                naga::back::wgsl::write_string(&module, &info, WriterFlags::empty()).unwrap();
+            if format == wgpu::VertexFormat::Float32x3 {
+                assert!(output.contains("bitcast<f32>(") && output.contains("bitcast<u32>("));
+            }
+            let written = naga::front::wgsl::parse_str(&output).unwrap();
+            naga::valid::Validator::new(ValidationFlags::all(), naga::valid::Capabilities::all())
+                .validate(&written)
+                .unwrap_or_else(|error| panic!("{format:?}: {error:?}\n{output}"));
+        }
+    }
 }
@@ -6,8 +6,11 @@ pub mod instance_binding;
 pub mod instance_buffer;
 pub mod integrate;
 pub mod naga_type;
+pub mod picking;
+pub mod shader_functions;
 pub mod texture_binding;
 pub mod texture_buffer;
+pub mod transparency;
 pub mod uniform_binding;
 pub mod uniform_buffer;
 pub mod value;
@@ -26,8 +29,11 @@ pub use instance_binding::*;
 pub use instance_buffer::*;
 pub use integrate::*;
 pub use naga_type::*;
+pub use picking::*;
+pub use shader_functions::*;
 pub use texture_binding::*;
 pub use texture_buffer::*;
+pub use transparency::*;
 pub use uniform_binding::*;
 pub use uniform_buffer::*;
 pub use value::*;
@@ -35,6 +41,7 @@ pub use vertex_attr::VertexAttr;
 pub use vertex_attr_format::VertexAttrFormat;
 
 pub use glam;
+pub use half;
 pub use naga;
 pub use uuid;
 pub use wgpu;
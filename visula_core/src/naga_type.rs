@@ -53,10 +53,45 @@ add_naga_type! {
     }
 }
 
+add_naga_type! {
+   u32, naga::Type {
+        name: None,
+        inner: naga::TypeInner::Scalar(
+            naga::Scalar{
+                kind: naga::ScalarKind::Uint,
+                width: 4,
+            }
+        ),
+    }
+}
+
 add_naga_float_vector! {2, naga::VectorSize::Bi}
 add_naga_float_vector! {3, naga::VectorSize::Tri}
 add_naga_float_vector! {4, naga::VectorSize::Quad}
 
+// Normalized and half-float fields are unpacked to floats before the shader sees them.
+macro_rules! add_naga_packed_vector {
+    ($packed_type:ty, $vector_size:expr) => {
+        add_naga_type! {
+            $packed_type, naga::Type {
+                name: None,
+                inner: naga::TypeInner::Vector {
+                    scalar: naga::Scalar {
+                        kind: naga::ScalarKind::Float,
+                        width: 4,
+                    },
+                    size: $vector_size,
+                },
+            }
+        }
+    };
+}
+
+add_naga_packed_vector! {[u8; 4], naga::VectorSize::Quad}
+add_naga_packed_vector! {[u16; 2], naga::VectorSize::Bi}
+add_naga_packed_vector! {[half::f16; 2], naga::VectorSize::Bi}
+add_naga_packed_vector! {[half::f16; 4], naga::VectorSize::Quad}
+
 macro_rules! add_naga_glam_vector {
     ($glam_type:ty, $vector_size:expr) => {
         add_naga_type! {
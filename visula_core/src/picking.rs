@@ -0,0 +1,428 @@
+use naga::{
+    Binding, BuiltIn, EntryPoint, Expression, Function, FunctionArgument, FunctionResult, Handle,
+    Interpolation, Literal, Module, Scalar, ShaderStage, Span, Statement, StructMember, Type,
+    TypeInner, VectorSize,
+};
+
+use crate::error::ShaderError;
+
+/// Format of the picking target written by modules from [`picking_module`]. The red channel
+/// holds the picking id of the pipeline and the green channel the instance index.
+pub const PICKING_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rg32Uint;
+
+/// Builds a variant of `module` whose fragment shader writes `(picking_id, instance_index)` to
+/// a single [`PICKING_FORMAT`] target instead of color and normal.
+///
+/// The original vertex and fragment entry points are kept as regular functions and called from
+/// new entry points with the same names, so that geometry, discards and depth written by the
+/// original shader are preserved. The instance index is passed from the vertex to the
+/// fragment stage as an extra flat-interpolated member of the vertex output.
+pub fn picking_module(module: &Module, picking_id: u32) -> Result<Module, ShaderError> {
+    let mut module = module.clone();
+    let vertex_index = entry_point_index(&module, ShaderStage::Vertex)?;
+    let fragment_index = entry_point_index(&module, ShaderStage::Fragment)?;
+
+    let u32_type = module.types.insert(
+        Type {
+            name: None,
+            inner: TypeInner::Scalar(Scalar::U32),
+        },
+        Span::default(),
+    );
+
+    let vertex_output_type = module.entry_points[vertex_index]
+        .function
+        .result
+        .as_ref()
+        .map(|result| result.ty)
+        .ok_or_else(|| ShaderError::EntryPointNotFound("vertex output".into()))?;
+    let (vertex_output_members, vertex_output_span) = match &module.types[vertex_output_type].inner
+    {
+        TypeInner::Struct { members, span } => (members.clone(), *span),
+        _ => return Err(ShaderError::EntryPointNotFound("vertex output".into())),
+    };
+    let next_location = vertex_output_members
+        .iter()
+        .filter_map(|member| match member.binding {
+            Some(Binding::Location { location, .. }) => Some(location + 1),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0);
+    let mut picking_vertex_output_members = vertex_output_members.clone();
+    picking_vertex_output_members.push(StructMember {
+        name: Some("_visula_instance_index".into()),
+        ty: u32_type,
+        binding: Some(Binding::Location {
+            location: next_location,
+            interpolation: Some(Interpolation::Flat),
+            sampling: None,
+            blend_src: None,
+            per_primitive: false,
+        }),
+        offset: vertex_output_span,
+    });
+    let picking_vertex_output_type = module.types.insert(
+        Type {
+            name: Some("VisulaPickingVertexOutput".into()),
+            inner: TypeInner::Struct {
+                members: picking_vertex_output_members,
+                span: (vertex_output_span + 4).div_ceil(16) * 16,
+            },
+        },
+        Span::default(),
+    );
+
+    let vertex_function = move_to_function(&mut module, vertex_index, "_visula_vs_main");
+    let fragment_function = move_to_function(&mut module, fragment_index, "_visula_fs_main");
+
+    // Vertex stage: forward all arguments and append the instance index to the output.
+    {
+        let original = &module.functions[vertex_function];
+        let mut arguments = entry_arguments(&module, vertex_index, original);
+        let instance_index_argument = match arguments
+            .iter()
+            .position(|argument| argument.binding == Some(Binding::BuiltIn(BuiltIn::InstanceIndex)))
+        {
+            Some(index) => index,
+            None => {
+                arguments.push(FunctionArgument {
+                    name: Some("_visula_instance_index".into()),
+                    ty: u32_type,
+                    binding: Some(Binding::BuiltIn(BuiltIn::InstanceIndex)),
+                });
+                arguments.len() - 1
+            }
+        };
+        let original_argument_count = original.arguments.len();
+
+        let mut function = Function {
+            name: Some("vs_main".into()),
+            arguments,
+            result: Some(FunctionResult {
+                ty: picking_vertex_output_type,
+                binding: None,
+            }),
+            ..Default::default()
+        };
+        let argument_expressions: Vec<Handle<Expression>> = (0..function.arguments.len())
+            .map(|index| {
+                function
+                    .expressions
+                    .append(Expression::FunctionArgument(index as u32), Span::default())
+            })
+            .collect();
+        let call_result = function
+            .expressions
+            .append(Expression::CallResult(vertex_function), Span::default());
+        function.body.push(
+            Statement::Call {
+                function: vertex_function,
+                arguments: argument_expressions[..original_argument_count].to_vec(),
+                result: Some(call_result),
+            },
+            Span::default(),
+        );
+        let emit_start = function.expressions.len();
+        let mut components = access_members(&mut function, call_result, &vertex_output_members);
+        components.push(argument_expressions[instance_index_argument]);
+        let output = function.expressions.append(
+            Expression::Compose {
+                ty: picking_vertex_output_type,
+                components,
+            },
+            Span::default(),
+        );
+        function.body.push(
+            Statement::Emit(function.expressions.range_from(emit_start)),
+            Span::default(),
+        );
+        function.body.push(
+            Statement::Return {
+                value: Some(output),
+            },
+            Span::default(),
+        );
+        module.entry_points[vertex_index].function = function;
+    }
+
+    // Fragment stage: rebuild the original vertex output, call the original fragment shader
+    // and write the picking id along with any depth it produced.
+    {
+        let original = &module.functions[fragment_function];
+        let mut arguments = entry_arguments(&module, fragment_index, original);
+        let fragment_output_type = original.result.as_ref().map(|result| result.ty);
+        let picking_input = arguments
+            .iter()
+            .position(|argument| argument.ty == vertex_output_type)
+            .ok_or_else(|| ShaderError::EntryPointNotFound("fragment input".into()))?;
+        arguments[picking_input].ty = picking_vertex_output_type;
+
+        let depth_member = fragment_output_type.and_then(|ty| match &module.types[ty].inner {
+            TypeInner::Struct { members, .. } => members
+                .iter()
+                .position(|member| member.binding == Some(Binding::BuiltIn(BuiltIn::FragDepth))),
+            _ => None,
+        });
+
+        let id_type = module.types.insert(
+            Type {
+                name: None,
+                inner: TypeInner::Vector {
+                    size: VectorSize::Bi,
+                    scalar: Scalar::U32,
+                },
+            },
+            Span::default(),
+        );
+        let mut picking_output_members = vec![StructMember {
+            name: Some("id".into()),
+            ty: id_type,
+            binding: Some(Binding::Location {
+                location: 0,
+                interpolation: None,
+                sampling: None,
+                blend_src: None,
+                per_primitive: false,
+            }),
+            offset: 0,
+        }];
+        if depth_member.is_some() {
+            let f32_type = module.types.insert(
+                Type {
+                    name: None,
+                    inner: TypeInner::Scalar(Scalar::F32),
+                },
+                Span::default(),
+            );
+            picking_output_members.push(StructMember {
+                name: Some("depth".into()),
+                ty: f32_type,
+                binding: Some(Binding::BuiltIn(BuiltIn::FragDepth)),
+                offset: 8,
+            });
+        }
+        let picking_output_type = module.types.insert(
+            Type {
+                name: Some("VisulaPickingOutput".into()),
+                inner: TypeInner::Struct {
+                    members: picking_output_members,
+                    span: 16,
+                },
+            },
+            Span::default(),
+        );
+
+        let mut function = Function {
+            name: Some("fs_main".into()),
+            arguments,
+            result: Some(FunctionResult {
+                ty: picking_output_type,
+                binding: None,
+            }),
+            ..Default::default()
+        };
+        let mut argument_expressions: Vec<Handle<Expression>> = (0..function.arguments.len())
+            .map(|index| {
+                function
+                    .expressions
+                    .append(Expression::FunctionArgument(index as u32), Span::default())
+            })
+            .collect();
+        let picking_input_expression = argument_expressions[picking_input];
+
+        let emit_start = function.expressions.len();
+        let components = access_members(
+            &mut function,
+            picking_input_expression,
+            &vertex_output_members,
+        );
+        argument_expressions[picking_input] = function.expressions.append(
+            Expression::Compose {
+                ty: vertex_output_type,
+                components,
+            },
+            Span::default(),
+        );
+        function.body.push(
+            Statement::Emit(function.expressions.range_from(emit_start)),
+            Span::default(),
+        );
+
+        let call_result = fragment_output_type.map(|_| {
+            function
+                .expressions
+                .append(Expression::CallResult(fragment_function), Span::default())
+        });
+        function.body.push(
+            Statement::Call {
+                function: fragment_function,
+                arguments: argument_expressions,
+                result: call_result,
+            },
+            Span::default(),
+        );
+
+        let id_literal = function.expressions.append(
+            Expression::Literal(Literal::U32(picking_id)),
+            Span::default(),
+        );
+        let emit_start = function.expressions.len();
+        let instance_index = function.expressions.append(
+            Expression::AccessIndex {
+                base: picking_input_expression,
+                index: vertex_output_members.len() as u32,
+            },
+            Span::default(),
+        );
+        let id = function.expressions.append(
+            Expression::Compose {
+                ty: id_type,
+                components: vec![id_literal, instance_index],
+            },
+            Span::default(),
+        );
+        let mut components = vec![id];
+        if let (Some(depth_member), Some(call_result)) = (depth_member, call_result) {
+            components.push(function.expressions.append(
+                Expression::AccessIndex {
+                    base: call_result,
+                    index: depth_member as u32,
+                },
+                Span::default(),
+            ));
+        }
+        let output = function.expressions.append(
+            Expression::Compose {
+                ty: picking_output_type,
+                components,
+            },
+            Span::default(),
+        );
+        function.body.push(
+            Statement::Emit(function.expressions.range_from(emit_start)),
+            Span::default(),
+        );
+        function.body.push(
+            Statement::Return {
+                value: Some(output),
+            },
+            Span::default(),
+        );
+        module.entry_points[fragment_index].function = function;
+    }
+
+    Ok(module)
+}
+
+pub(crate) fn entry_point_index(module: &Module, stage: ShaderStage) -> Result<usize, ShaderError> {
+    module
+        .entry_points
+        .iter()
+        .position(|entry_point| entry_point.stage == stage)
+        .ok_or_else(|| ShaderError::EntryPointNotFound(format!("{stage:?}")))
+}
+
+/// Copies the function of an entry point into the module's regular functions, with the
+/// bindings of its arguments and result removed.
+pub(crate) fn move_to_function(
+    module: &mut Module,
+    entry_index: usize,
+    name: &str,
+) -> Handle<Function> {
+    let EntryPoint { function, .. } = &module.entry_points[entry_index];
+    let mut function = function.clone();
+    function.name = Some(name.into());
+    for argument in &mut function.arguments {
+        argument.binding = None;
+    }
+    if let Some(ref mut result) = function.result {
+        result.binding = None;
+    }
+    module.functions.append(function, Span::default())
+}
+
+/// The arguments of an entry point, with their bindings restored from the entry point.
+pub(crate) fn entry_arguments(
+    module: &Module,
+    entry_index: usize,
+    original: &Function,
+) -> Vec<FunctionArgument> {
+    original
+        .arguments
+        .iter()
+        .zip(module.entry_points[entry_index].function.arguments.iter())
+        .map(|(argument, entry_argument)| FunctionArgument {
+            binding: entry_argument.binding.clone(),
+            ..argument.clone()
+        })
+        .collect()
+}
+
+fn access_members(
+    function: &mut Function,
+    base: Handle<Expression>,
+    members: &[StructMember],
+) -> Vec<Handle<Expression>> {
+    (0..members.len())
+        .map(|index| {
+            function.expressions.append(
+                Expression::AccessIndex {
+                    base,
+                    index: index as u32,
+                },
+                Span::default(),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use naga::valid::ValidationFlags;
+
+    #[test]
+    fn test_picking_module() {
+        let module = naga::front::wgsl::parse_str(
+            r#"
+            struct VertexOutput {
+                @builtin(position) position: vec4<f32>,
+                @location(0) color: vec3<f32>,
+            };
+
+            struct FragmentOutput {
+                @location(0) color: vec4<f32>,
+                @location(1) normal: vec4<f32>,
+                @builtin(frag_depth) depth: f32,
+            };
+
+            @vertex
+            fn vs_main(@location(0) position: vec3<f32>) -> VertexOutput {
+                var output: VertexOutput;
+                output.position = vec4<f32>(position, 1.0);
+                output.color = position;
+                return output;
+            }
+
+            @fragment
+            fn fs_main(in: VertexOutput) -> FragmentOutput {
+                if (in.color.x < 0.0) {
+                    discard;
+                }
+                var output: FragmentOutput;
+                output.color = vec4<f32>(in.color, 1.0);
+                output.normal = vec4<f32>(0.0, 0.0, 1.0, 0.0);
+                output.depth = in.position.z;
+                return output;
+            }
+            "#,
+        )
+        .unwrap();
+        let picking = picking_module(&module, 7).unwrap();
+        naga::valid::Validator::new(ValidationFlags::all(), naga::valid::Capabilities::all())
+            .validate(&picking)
+            .unwrap();
+        assert_eq!(picking.functions.len(), module.functions.len() + 2);
+    }
+}
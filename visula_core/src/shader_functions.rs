@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use naga::valid::ValidationFlags;
+use naga::{Handle, Module, Span};
+
+use crate::error::ShaderError;
+use crate::value::{type_component_count, Expression};
+
+/// Functions written in WGSL that expressions can call with [`ShaderFunctions::call`].
+///
+/// This is the escape hatch for shading and math that cannot be built from expressions, such
+/// as a custom BRDF or a noise function. The snippet must define plain functions, and may also
+/// define the structs and constants they use, but no entry points, bindings or overrides. It is
+/// copied into every shader module that an expression calling one of its functions is injected
+/// into.
+#[derive(Clone)]
+pub struct ShaderFunctions {
+    pub handle: uuid::Uuid,
+    module: Rc<Module>,
+}
+
+/// The functions of the snippet by name, and the handles they got in a shader module.
+pub type ImportedFunctions = HashMap<String, Handle<naga::Function>>;
+
+#[derive(Default)]
+struct ImportMap {
+    types: HashMap<Handle<naga::Type>, Handle<naga::Type>>,
+    constants: HashMap<Handle<naga::Constant>, Handle<naga::Constant>>,
+    functions: HashMap<Handle<naga::Function>, Handle<naga::Function>>,
+}
+
+impl ShaderFunctions {
+    /// Parses and validates the functions in `source`.
+    pub fn new(source: &str) -> Result<ShaderFunctions, ShaderError> {
+        let module = naga::front::wgsl::parse_str(source)?;
+        naga::valid::Validator::new(ValidationFlags::all(), naga::valid::Capabilities::all())
+            .validate(&module)
+            .map_err(Box::new)?;
+        if !module.entry_points.is_empty() {
+            return Err(ShaderError::UnsupportedFunctions(
+                "entry points are not allowed".to_string(),
+            ));
+        }
+        if !module.global_variables.is_empty() || !module.overrides.is_empty() {
+            return Err(ShaderError::UnsupportedFunctions(
+                "global variables and overrides are not allowed".to_string(),
+            ));
+        }
+        let functions = ShaderFunctions {
+            handle: uuid::Uuid::new_v4(),
+            module: Rc::new(module),
+        };
+        // Catches constants that cannot be copied now, instead of when the functions are used.
+        functions.import(&mut Module::default())?;
+        Ok(functions)
+    }
+
+    /// A call to the function `name` with `arguments`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the call is invalid, see [`ShaderFunctions::try_call`].
+    pub fn call(&self, name: &str, arguments: &[Expression]) -> Expression {
+        self.try_call(name, arguments)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Like [`ShaderFunctions::call`], but returns an error if there is no function `name` that
+    /// returns a value, or if the arguments do not match its parameters. The arguments are
+    /// checked by count and by their number of components where that is known, while the
+    /// scalar types are checked when the shader is validated.
+    pub fn try_call(
+        &self,
+        name: &str,
+        arguments: &[Expression],
+    ) -> Result<Expression, ShaderError> {
+        let function = self
+            .function(name)
+            .ok_or_else(|| ShaderError::FunctionNotFound(name.to_string()))?;
+        if function.result.is_none() {
+            return Err(ShaderError::InvalidCall(format!(
+                "'{name}' does not return a value"
+            )));
+        }
+        if arguments.len() != function.arguments.len() {
+            return Err(ShaderError::InvalidCall(format!(
+                "'{name}' takes {} arguments, but got {}",
+                function.arguments.len(),
+                arguments.len()
+            )));
+        }
+        for (index, (argument, parameter)) in arguments.iter().zip(&function.arguments).enumerate()
+        {
+            let expected = type_component_count(&self.module.types[parameter.ty]);
+            if let (Some(expected), Some(count)) = (expected, argument.component_count()) {
+                if expected != count {
+                    return Err(ShaderError::InvalidCall(format!(
+                        "argument {index} of '{name}' has {expected} components, but got {count}"
+                    )));
+                }
+            }
+        }
+        Ok(Expression::Call {
+            functions: self.clone(),
+            name: name.to_string(),
+            arguments: arguments.iter().map(|argument| argument.into()).collect(),
+        })
+    }
+
+    fn function(&self, name: &str) -> Option<&naga::Function> {
+        self.module
+            .functions
+            .iter()
+            .map(|(_, function)| function)
+            .find(|function| function.name.as_deref() == Some(name))
+    }
+
+    /// The number of components of the result of `name`, see [`Expression::component_count`].
+    pub fn result_component_count(&self, name: &str) -> Option<usize> {
+        let result = self.function(name)?.result.as_ref()?;
+        type_component_count(&self.module.types[result.ty])
+    }
+
+    /// Copies the functions, and the types and constants they use, into `module`.
+    pub fn import(&self, module: &mut Module) -> Result<ImportedFunctions, ShaderError> {
+        let mut map = ImportMap::default();
+        let mut imported = ImportedFunctions::new();
+        for (handle, function) in self.module.functions.iter() {
+            let mut function = function.clone();
+            for argument in function.arguments.iter_mut() {
+                argument.ty = self.import_type(argument.ty, module, &mut map);
+            }
+            if let Some(result) = function.result.as_mut() {
+                result.ty = self.import_type(result.ty, module, &mut map);
+            }
+            for (_, variable) in function.local_variables.iter_mut() {
+                variable.ty = self.import_type(variable.ty, module, &mut map);
+            }
+            // The expressions of the function keep their handles, since the whole arena is
+            // copied, but those that refer to the module need new ones.
+            for (_, expression) in function.expressions.iter_mut() {
+                match expression {
+                    naga::Expression::Constant(constant) => {
+                        *constant = self.import_constant(*constant, module, &mut map)?;
+                    }
+                    naga::Expression::ZeroValue(ty)
+                    | naga::Expression::Compose { ty, .. }
+                    | naga::Expression::AtomicResult { ty, .. }
+                    | naga::Expression::WorkGroupUniformLoadResult { ty }
+                    | naga::Expression::SubgroupOperationResult { ty } => {
+                        *ty = self.import_type(*ty, module, &mut map);
+                    }
+                    naga::Expression::CallResult(callee) => {
+                        *callee = map.functions[callee];
+                    }
+                    _ => {}
+                }
+            }
+            remap_calls(&mut function.body, &map.functions);
+            function.diagnostic_filter_leaf = None;
+            let name = function.name.clone();
+            let new_handle = module.functions.append(function, Span::default());
+            map.functions.insert(handle, new_handle);
+            if let Some(name) = name {
+                imported.insert(name, new_handle);
+            }
+        }
+        Ok(imported)
+    }
+
+    fn import_type(
+        &self,
+        handle: Handle<naga::Type>,
+        module: &mut Module,
+        map: &mut ImportMap,
+    ) -> Handle<naga::Type> {
+        if let Some(&imported) = map.types.get(&handle) {
+            return imported;
+        }
+        let ty = &self.module.types[handle];
+        let inner = match &ty.inner {
+            naga::TypeInner::Pointer { base, space } => naga::TypeInner::Pointer {
+                base: self.import_type(*base, module, map),
+                space: *space,
+            },
+            naga::TypeInner::Array { base, size, stride } => naga::TypeInner::Array {
+                base: self.import_type(*base, module, map),
+                size: *size,
+                stride: *stride,
+            },
+            naga::TypeInner::BindingArray { base, size } => naga::TypeInner::BindingArray {
+                base: self.import_type(*base, module, map),
+                size: *size,
+            },
+            naga::TypeInner::Struct { members, span } => naga::TypeInner::Struct {
+                members: members
+                    .iter()
+                    .map(|member| naga::StructMember {
+                        ty: self.import_type(member.ty, module, map),
+                        ..member.clone()
+                    })
+                    .collect(),
+                span: *span,
+            },
+            inner => inner.clone(),
+        };
+        let imported = module.types.insert(
+            naga::Type {
+                name: ty.name.clone(),
+                inner,
+            },
+            Span::default(),
+        );
+        map.types.insert(handle, imported);
+        imported
+    }
+
+    fn import_constant(
+        &self,
+        handle: Handle<naga::Constant>,
+        module: &mut Module,
+        map: &mut ImportMap,
+    ) -> Result<Handle<naga::Constant>, ShaderError> {
+        if let Some(&imported) = map.constants.get(&handle) {
+            return Ok(imported);
+        }
+        let constant = &self.module.constants[handle];
+        let imported = naga::Constant {
+            name: constant.name.clone(),
+            ty: self.import_type(constant.ty, module, map),
+            init: self.import_global_expression(constant.init, module, map)?,
+        };
+        let imported = module.constants.append(imported, Span::default());
+        map.constants.insert(handle, imported);
+        Ok(imported)
+    }
+
+    /// Copies the initializer of a constant, which the WGSL front end has already evaluated.
+    fn import_global_expression(
+        &self,
+        handle: Handle<naga::Expression>,
+        module: &mut Module,
+        map: &mut ImportMap,
+    ) -> Result<Handle<naga::Expression>, ShaderError> {
+        let expression = match &self.module.global_expressions[handle] {
+            naga::Expression::Literal(literal) => naga::Expression::Literal(*literal),
+            naga::Expression::Constant(constant) => {
+                naga::Expression::Constant(self.import_constant(*constant, module, map)?)
+            }
+            naga::Expression::ZeroValue(ty) => {
+                naga::Expression::ZeroValue(self.import_type(*ty, module, map))
+            }
+            naga::Expression::Compose { ty, components } => naga::Expression::Compose {
+                ty: self.import_type(*ty, module, map),
+                components: components
+                    .iter()
+                    .map(|&component| self.import_global_expression(component, module, map))
+                    .collect::<Result<_, _>>()?,
+            },
+            naga::Expression::Splat { size, value } => naga::Expression::Splat {
+                size: *size,
+                value: self.import_global_expression(*value, module, map)?,
+            },
+            expression => {
+                return Err(ShaderError::UnsupportedFunctions(format!(
+                    "constant initializer {expression:?} is not supported"
+                )))
+            }
+        };
+        Ok(module
+            .global_expressions
+            .append(expression, Span::default()))
+    }
+}
+
+/// Points calls in `block`, including nested blocks, to the imported functions.
+fn remap_calls(
+    block: &mut naga::Block,
+    functions: &HashMap<Handle<naga::Function>, Handle<naga::Function>>,
+) {
+    for statement in block.iter_mut() {
+        match statement {
+            naga::Statement::Call { function, .. } => *function = functions[function],
+            naga::Statement::Block(inner) => remap_calls(inner, functions),
+            naga::Statement::If { accept, reject, .. } => {
+                remap_calls(accept, functions);
+                remap_calls(reject, functions);
+            }
+            naga::Statement::Loop {
+                body, continuing, ..
+            } => {
+                remap_calls(body, functions);
+                remap_calls(continuing, functions);
+            }
+            naga::Statement::Switch { cases, .. } => {
+                for case in cases.iter_mut() {
+                    remap_calls(&mut case.body, functions);
+                }
+            }
+            _ => {}
+        }
+    }
+}
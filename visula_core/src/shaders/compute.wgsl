@@ -0,0 +1,14 @@
+struct Particle {
+    position: vec3<f32>,
+    velocity: vec3<f32>,
+    time_step: f32,
+};
+
+@compute @workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    var particle: Particle;
+    if particle.time_step <= 0.0 {
+        return;
+    }
+    particle.position += particle.velocity * particle.time_step;
+}
@@ -1,49 +1,190 @@
+const VISULA_PI: f32 = 3.14159265;
+const VISULA_MAX_CASCADES: u32 = 4u;
+
 struct Light {
     direction: vec3<f32>,
     _pad0: f32,
     color: vec3<f32>,
     intensity: f32,
     light_view_proj: mat4x4<f32>,
+    cascade_view_proj: array<mat4x4<f32>, VISULA_MAX_CASCADES>,
+    cascade_count: u32,
+    shadow_map_size: f32,
+    _pad1: vec2<f32>,
 };
 
 @group(1) @binding(0)
 var<uniform> u_light: Light;
 
 @group(1) @binding(1)
-var shadow_map: texture_depth_2d;
+var shadow_map: texture_depth_2d_array;
 
 @group(1) @binding(2)
 var shadow_sampler: sampler_comparison;
 
 fn compute_shadow(world_position: vec3<f32>) -> f32 {
-    let light_clip = u_light.light_view_proj * vec4<f32>(world_position, 1.0);
-    let light_ndc = light_clip.xyz / light_clip.w;
-    let shadow_uv = light_ndc.xy * vec2<f32>(0.5, -0.5) + vec2<f32>(0.5, 0.5);
+    // Derivatives are taken before branching on the cascade, since they need uniform control flow.
+    let position_dx = dpdx(world_position);
+    let position_dy = dpdy(world_position);
 
-    if shadow_uv.x < 0.0 || shadow_uv.x > 1.0 || shadow_uv.y < 0.0 || shadow_uv.y > 1.0 {
+    // Cascades are ordered from the camera outwards, so the first one that contains the
+    // position has the highest resolution.
+    let count = min(u_light.cascade_count, VISULA_MAX_CASCADES);
+    var cascade = count;
+    var light_ndc = vec3<f32>(0.0);
+    for (var i = 0u; i < count; i++) {
+        let light_clip = u_light.cascade_view_proj[i] * vec4<f32>(world_position, 1.0);
+        let ndc = light_clip.xyz / light_clip.w;
+        if all(abs(ndc.xy) <= vec2<f32>(1.0)) && ndc.z >= 0.0 && ndc.z <= 1.0 {
+            cascade = i;
+            light_ndc = ndc;
+            break;
+        }
+    }
+    if cascade == count {
         return 1.0;
     }
 
+    let shadow_uv = light_ndc.xy * vec2<f32>(0.5, -0.5) + vec2<f32>(0.5, 0.5);
     let depth = light_ndc.z;
 
     // Slope-scaled bias: steeper surfaces need more bias to avoid acne.
-    let light_dir = normalize(-u_light.direction);
-    let dx = dpdx(depth);
-    let dy = dpdy(depth);
+    // The cascades are orthographic, so depth changes linearly with the position.
+    let view_proj = u_light.cascade_view_proj[cascade];
+    let dx = (view_proj * vec4<f32>(position_dx, 0.0)).z;
+    let dy = (view_proj * vec4<f32>(position_dy, 0.0)).z;
     let slope = sqrt(dx * dx + dy * dy);
     let bias = max(0.0005, 0.002 * slope);
 
-    let texel_size = 3.0 / 4096.0;
+    let texel_size = 3.0 / u_light.shadow_map_size;
     var shadow = 0.0;
     for (var y = -2; y <= 2; y++) {
         for (var x = -2; x <= 2; x++) {
             let offset = vec2<f32>(f32(x), f32(y)) * texel_size;
-            shadow += textureSampleCompare(shadow_map, shadow_sampler, shadow_uv + offset, depth - bias);
+            shadow += textureSampleCompareLevel(shadow_map, shadow_sampler, shadow_uv + offset, cascade, depth - bias);
         }
     }
     return shadow / 25.0;
 }
 
+const VISULA_MAX_LIGHTS: u32 = 16u;
+const VISULA_LIGHT_DIRECTIONAL: u32 = 0u;
+const VISULA_LIGHT_POINT: u32 = 1u;
+const VISULA_LIGHT_SPOT: u32 = 2u;
+
+struct LightSource {
+    position: vec3<f32>,
+    kind: u32,
+    direction: vec3<f32>,
+    range: f32,
+    color: vec3<f32>,
+    intensity: f32,
+    inner_cos: f32,
+    outer_cos: f32,
+    _pad0: vec2<f32>,
+};
+
+struct LightList {
+    fill_direction: vec3<f32>,
+    fill_intensity: f32,
+    ambient: f32,
+    count: u32,
+    environment_intensity: f32,
+    environment_mip_count: f32,
+    lights: array<LightSource, VISULA_MAX_LIGHTS>,
+};
+
+@group(1) @binding(3)
+var<uniform> u_lights: LightList;
+
+@group(1) @binding(4)
+var environment_irradiance: texture_2d<f32>;
+
+@group(1) @binding(5)
+var environment_specular: texture_2d<f32>;
+
+@group(1) @binding(6)
+var environment_sampler: sampler;
+
+fn visula_equirect_uv(direction: vec3<f32>) -> vec2<f32> {
+    return vec2<f32>(
+        atan2(direction.z, direction.x) / (2.0 * VISULA_PI) + 0.5,
+        acos(clamp(direction.y, -1.0, 1.0)) / VISULA_PI,
+    );
+}
+
+fn visula_has_environment() -> bool {
+    return u_lights.environment_mip_count > 0.0;
+}
+
+// Light arriving at a surface with the given normal, from the environment if there is one
+// and from the constant ambient term otherwise.
+fn visula_ambient(normal: vec3<f32>) -> vec3<f32> {
+    if visula_has_environment() {
+        let irradiance = textureSampleLevel(environment_irradiance, environment_sampler, visula_equirect_uv(normal), 0.0);
+        return irradiance.rgb * u_lights.environment_intensity;
+    }
+    return u_lights.ambient * u_light.color;
+}
+
+fn visula_environment_specular(direction: vec3<f32>, roughness: f32) -> vec3<f32> {
+    let lod = roughness * (u_lights.environment_mip_count - 1.0);
+    let radiance = textureSampleLevel(environment_specular, environment_sampler, visula_equirect_uv(direction), lod);
+    return radiance.rgb * u_lights.environment_intensity;
+}
+
+struct LightContribution {
+    diffuse: vec3<f32>,
+    specular: vec3<f32>,
+};
+
+// Direction towards the light in xyz and attenuation in w.
+fn visula_light_incidence(light: LightSource, world_position: vec3<f32>) -> vec4<f32> {
+    if light.kind == VISULA_LIGHT_DIRECTIONAL {
+        return vec4<f32>(normalize(-light.direction), 1.0);
+    }
+    let to_light = light.position - world_position;
+    let distance = length(to_light);
+    let light_dir = to_light / max(distance, 1e-4);
+    var attenuation = 1.0 / max(distance * distance, 1e-4);
+    if light.range > 0.0 {
+        // Smoothly window the inverse-square falloff so it reaches zero at the range.
+        let ratio = distance / light.range;
+        let window = clamp(1.0 - ratio * ratio * ratio * ratio, 0.0, 1.0);
+        attenuation *= window * window;
+    }
+    if light.kind == VISULA_LIGHT_SPOT {
+        let cos_angle = dot(-light_dir, normalize(light.direction));
+        attenuation *= smoothstep(light.outer_cos, light.inner_cos, cos_angle);
+    }
+    return vec4<f32>(light_dir, attenuation);
+}
+
+fn visula_light_list(normal: vec3<f32>, view_direction: vec3<f32>, world_position: vec3<f32>) -> LightContribution {
+    var result: LightContribution;
+    result.diffuse = vec3<f32>(0.0);
+    result.specular = vec3<f32>(0.0);
+    let count = min(u_lights.count, VISULA_MAX_LIGHTS);
+    for (var i = 0u; i < count; i++) {
+        let light = u_lights.lights[i];
+        let incidence = visula_light_incidence(light, world_position);
+        let n_dot_l = dot(normal, incidence.xyz);
+        if n_dot_l <= 0.0 {
+            continue;
+        }
+        let radiance = light.color * light.intensity * incidence.w;
+        result.diffuse += n_dot_l * radiance;
+        let half_dir = normalize(incidence.xyz + normalize(view_direction));
+        result.specular += pow(max(dot(normal, half_dir), 0.0), 32.0) * 0.3 * radiance;
+    }
+    return result;
+}
+
+fn visula_fill_diffuse(normal: vec3<f32>) -> f32 {
+    let fill_dir = normalize(-u_lights.fill_direction);
+    return max(dot(normal, fill_dir), 0.0) * u_lights.fill_intensity;
+}
+
 fn visula_lit_vec3(color: vec3<f32>, normal: vec3<f32>, view_direction: vec3<f32>, world_position: vec3<f32>) -> vec3<f32> {
     let shadow = compute_shadow(world_position);
 
@@ -53,13 +194,11 @@ fn visula_lit_vec3(color: vec3<f32>, normal: vec3<f32>, view_direction: vec3<f32
     let half_dir = normalize(light_dir + normalize(view_direction));
     let main_specular = pow(max(dot(normal, half_dir), 0.0), 32.0) * shadow;
 
-    let fill_dir = normalize(vec3<f32>(1.0, 0.4, -0.8));
-    let fill_intensity = 0.3;
-    let fill_diffuse = max(dot(normal, fill_dir), 0.0) * fill_intensity;
+    let fill_diffuse = visula_fill_diffuse(normal);
+    let lights = visula_light_list(normal, view_direction, world_position);
 
-    let ambient = 0.1;
-
-    return color * (ambient + main_diffuse + fill_diffuse) * u_light.color + main_specular * 0.3 * u_light.color;
+    let main = visula_ambient(normal) + (main_diffuse + fill_diffuse) * u_light.color;
+    return color * (main + lights.diffuse) + main_specular * 0.3 * u_light.color + lights.specular;
 }
 
 fn visula_lit_vec4(color: vec4<f32>, normal: vec3<f32>, view_direction: vec3<f32>, world_position: vec3<f32>) -> vec4<f32> {
@@ -73,13 +212,12 @@ fn visula_directional_lit_vec3(color: vec3<f32>, normal: vec3<f32>, world_positi
     let n_dot_l = max(dot(normal, light_dir), 0.0);
     let main_diffuse = n_dot_l * shadow * u_light.intensity;
 
-    let fill_dir = normalize(vec3<f32>(1.0, 0.4, -0.8));
-    let fill_intensity = 0.3;
-    let fill_diffuse = max(dot(normal, fill_dir), 0.0) * fill_intensity;
-
-    let ambient = 0.1;
+    let fill_diffuse = visula_fill_diffuse(normal);
+    // There is no view direction here, so only the diffuse part of the light list is used.
+    let lights = visula_light_list(normal, normal, world_position);
 
-    return color * (ambient + main_diffuse + fill_diffuse) * u_light.color;
+    let main = visula_ambient(normal) + (main_diffuse + fill_diffuse) * u_light.color;
+    return color * (main + lights.diffuse);
 }
 
 fn visula_directional_lit_vec4(color: vec4<f32>, normal: vec3<f32>, world_position: vec3<f32>) -> vec4<f32> {
@@ -109,3 +247,109 @@ fn visula_toon_lit_vec3(color: vec3<f32>, normal: vec3<f32>, view_direction: vec
 fn visula_toon_lit_vec4(color: vec4<f32>, normal: vec3<f32>, view_direction: vec3<f32>, world_position: vec3<f32>) -> vec4<f32> {
     return vec4<f32>(visula_toon_lit_vec3(color.xyz, normal, view_direction, world_position), color.w);
 }
+
+fn visula_distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+    let alpha = roughness * roughness;
+    let alpha2 = alpha * alpha;
+    let denominator = n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0;
+    return alpha2 / (VISULA_PI * denominator * denominator);
+}
+
+fn visula_geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+    let ggx_v = n_dot_v / (n_dot_v * (1.0 - k) + k);
+    let ggx_l = n_dot_l / (n_dot_l * (1.0 - k) + k);
+    return ggx_v * ggx_l;
+}
+
+fn visula_fresnel_schlick(cos_theta: f32, f0: vec3<f32>) -> vec3<f32> {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// Cook-Torrance GGX reflectance for light arriving from light_dir.
+// The result is scaled by pi so that a white diffuse surface facing a light of intensity 1
+// is as bright as with visula_lit_vec3.
+fn visula_pbr_brdf(albedo: vec3<f32>, metallic: f32, roughness: f32, normal: vec3<f32>, view_dir: vec3<f32>, light_dir: vec3<f32>) -> vec3<f32> {
+    let n_dot_l = max(dot(normal, light_dir), 0.0);
+    if n_dot_l <= 0.0 {
+        return vec3<f32>(0.0);
+    }
+    let n_dot_v = max(dot(normal, view_dir), 1e-4);
+    let half_dir = normalize(light_dir + view_dir);
+    let n_dot_h = max(dot(normal, half_dir), 0.0);
+    let h_dot_v = max(dot(half_dir, view_dir), 0.0);
+
+    let f0 = mix(vec3<f32>(0.04), albedo, metallic);
+    let fresnel = visula_fresnel_schlick(h_dot_v, f0);
+    let distribution = visula_distribution_ggx(n_dot_h, roughness);
+    let geometry = visula_geometry_smith(n_dot_v, n_dot_l, roughness);
+    let specular = distribution * geometry * fresnel / (4.0 * n_dot_v * n_dot_l + 1e-4);
+
+    let diffuse = (vec3<f32>(1.0) - fresnel) * (1.0 - metallic) * albedo / VISULA_PI;
+    return (diffuse + specular) * n_dot_l * VISULA_PI;
+}
+
+// Analytic fit of the split-sum environment BRDF, from Karis, "Physically Based Shading on
+// Mobile", which avoids a lookup texture.
+fn visula_env_brdf_approx(f0: vec3<f32>, roughness: f32, n_dot_v: f32) -> vec3<f32> {
+    let c0 = vec4<f32>(-1.0, -0.0275, -0.572, 0.022);
+    let c1 = vec4<f32>(1.0, 0.0425, 1.04, -0.04);
+    let r = roughness * c0 + c1;
+    let a004 = min(r.x * r.x, exp2(-9.28 * n_dot_v)) * r.x + r.y;
+    let ab = vec2<f32>(-1.04, 1.04) * a004 + r.zw;
+    return f0 * ab.x + ab.y;
+}
+
+// Perturbs the normal by a tangent-space normal map value in [0, 1], using a cotangent frame
+// derived from screen-space derivatives so that no tangents are needed.
+fn visula_perturb_normal(normal: vec3<f32>, world_position: vec3<f32>, uv: vec2<f32>, map_value: vec3<f32>) -> vec3<f32> {
+    let dp1 = dpdx(world_position);
+    let dp2 = dpdy(world_position);
+    let duv1 = dpdx(uv);
+    let duv2 = dpdy(uv);
+    let dp2_perp = cross(dp2, normal);
+    let dp1_perp = cross(normal, dp1);
+    let tangent = dp2_perp * duv1.x + dp1_perp * duv2.x;
+    let bitangent = dp2_perp * duv1.y + dp1_perp * duv2.y;
+    let scale = inverseSqrt(max(max(dot(tangent, tangent), dot(bitangent, bitangent)), 1e-12));
+    let frame = mat3x3<f32>(tangent * scale, bitangent * scale, normal);
+    return normalize(frame * (map_value * 2.0 - 1.0));
+}
+
+fn visula_pbr_vec3(color: vec3<f32>, metallic: f32, roughness: f32, emissive: vec3<f32>, normal: vec3<f32>, view_direction: vec3<f32>, world_position: vec3<f32>) -> vec3<f32> {
+    let shadow = compute_shadow(world_position);
+    let view_dir = normalize(view_direction);
+    // Very low roughness makes the highlights of the analytic lights vanish.
+    let rough = clamp(roughness, 0.04, 1.0);
+    let metal = clamp(metallic, 0.0, 1.0);
+
+    let main_dir = normalize(-u_light.direction);
+    var result = visula_pbr_brdf(color, metal, rough, normal, view_dir, main_dir) * u_light.color * u_light.intensity * shadow;
+
+    let fill_dir = normalize(-u_lights.fill_direction);
+    result += visula_pbr_brdf(color, metal, rough, normal, view_dir, fill_dir) * u_light.color * u_lights.fill_intensity;
+
+    let count = min(u_lights.count, VISULA_MAX_LIGHTS);
+    for (var i = 0u; i < count; i++) {
+        let light = u_lights.lights[i];
+        let incidence = visula_light_incidence(light, world_position);
+        result += visula_pbr_brdf(color, metal, rough, normal, view_dir, incidence.xyz) * light.color * light.intensity * incidence.w;
+    }
+
+    let f0 = mix(vec3<f32>(0.04), color, metal);
+    var ambient: vec3<f32>;
+    if visula_has_environment() {
+        let n_dot_v = max(dot(normal, view_dir), 1e-4);
+        let reflected = reflect(-view_dir, normal);
+        let specular = visula_environment_specular(reflected, rough) * visula_env_brdf_approx(f0, rough, n_dot_v);
+        ambient = visula_ambient(normal) * color * (1.0 - metal) + specular;
+    } else {
+        ambient = (color * (1.0 - metal) + f0) * visula_ambient(normal);
+    }
+
+    return result + ambient + emissive;
+}
+
+fn visula_pbr_vec4(color: vec4<f32>, metallic: f32, roughness: f32, emissive: vec3<f32>, normal: vec3<f32>, view_direction: vec3<f32>, world_position: vec3<f32>) -> vec4<f32> {
+    return vec4<f32>(visula_pbr_vec3(color.xyz, metallic, roughness, emissive, normal, view_direction, world_position), color.w);
+}
@@ -0,0 +1,297 @@
+use naga::{
+    BinaryOperator, Binding, BuiltIn, Expression, Function, FunctionArgument, FunctionResult,
+    Handle, Literal, MathFunction, Module, Scalar, ShaderStage, Span, Statement, StructMember,
+    SwizzleComponent, Type, TypeInner, VectorSize,
+};
+
+use crate::error::ShaderError;
+use crate::picking::{entry_arguments, entry_point_index, move_to_function};
+
+/// Format of the accumulation target written by modules from [`transparency_module`]. Holds
+/// the weighted sum of premultiplied colors in `rgb` and of the weighted alphas in `a`, and is
+/// blended additively.
+pub const ACCUMULATION_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Format of the revealage target written by modules from [`transparency_module`]. Holds the
+/// product of `1 - alpha` over all fragments, starting from one.
+pub const REVEALAGE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R16Float;
+
+/// Builds a variant of `module` for weighted blended order-independent transparency, see
+/// McGuire and Bavoil, "Weighted Blended Order-Independent Transparency" (2013).
+///
+/// The fragment shader writes to an [`ACCUMULATION_FORMAT`] target at location 0 and a
+/// [`REVEALAGE_FORMAT`] target at location 1 instead of color and normal. The original fragment
+/// entry point is kept as a regular function and called from a new entry point with the same
+/// name, so that discards and depth written by the original shader are preserved. The alpha
+/// of its color output is the opacity of the fragment, and the weight falls off with depth.
+pub fn transparency_module(module: &Module) -> Result<Module, ShaderError> {
+    let mut module = module.clone();
+    let fragment_index = entry_point_index(&module, ShaderStage::Fragment)?;
+    let fragment_function = move_to_function(&mut module, fragment_index, "_visula_fs_main");
+
+    let original = &module.functions[fragment_function];
+    let arguments = entry_arguments(&module, fragment_index, original);
+    let fragment_output_type = original
+        .result
+        .as_ref()
+        .map(|result| result.ty)
+        .ok_or_else(|| ShaderError::EntryPointNotFound("fragment output".into()))?;
+    let output_members = match &module.types[fragment_output_type].inner {
+        TypeInner::Struct { members, .. } => members.clone(),
+        _ => return Err(ShaderError::EntryPointNotFound("fragment output".into())),
+    };
+    let color_member = output_members
+        .iter()
+        .position(|member| matches!(member.binding, Some(Binding::Location { location: 0, .. })))
+        .ok_or_else(|| ShaderError::EntryPointNotFound("fragment color".into()))?;
+    let depth_member = output_members
+        .iter()
+        .position(|member| member.binding == Some(Binding::BuiltIn(BuiltIn::FragDepth)));
+    let position = fragment_position(&module, &arguments)
+        .ok_or_else(|| ShaderError::EntryPointNotFound("fragment position".into()))?;
+
+    let f32_type = module.types.insert(
+        Type {
+            name: None,
+            inner: TypeInner::Scalar(Scalar::F32),
+        },
+        Span::default(),
+    );
+    let vec4_type = module.types.insert(
+        Type {
+            name: None,
+            inner: TypeInner::Vector {
+                size: VectorSize::Quad,
+                scalar: Scalar::F32,
+            },
+        },
+        Span::default(),
+    );
+    let location = |location| Binding::Location {
+        location,
+        interpolation: None,
+        sampling: None,
+        blend_src: None,
+        per_primitive: false,
+    };
+    let mut transparency_output_members = vec![
+        StructMember {
+            name: Some("accumulation".into()),
+            ty: vec4_type,
+            binding: Some(location(0)),
+            offset: 0,
+        },
+        StructMember {
+            name: Some("revealage".into()),
+            ty: f32_type,
+            binding: Some(location(1)),
+            offset: 16,
+        },
+    ];
+    if depth_member.is_some() {
+        transparency_output_members.push(StructMember {
+            name: Some("depth".into()),
+            ty: f32_type,
+            binding: Some(Binding::BuiltIn(BuiltIn::FragDepth)),
+            offset: 20,
+        });
+    }
+    let transparency_output_type = module.types.insert(
+        Type {
+            name: Some("VisulaTransparencyOutput".into()),
+            inner: TypeInner::Struct {
+                members: transparency_output_members,
+                span: 32,
+            },
+        },
+        Span::default(),
+    );
+
+    let mut function = Function {
+        name: Some("fs_main".into()),
+        arguments,
+        result: Some(FunctionResult {
+            ty: transparency_output_type,
+            binding: None,
+        }),
+        ..Default::default()
+    };
+    let argument_expressions: Vec<Handle<Expression>> = (0..function.arguments.len())
+        .map(|index| {
+            function
+                .expressions
+                .append(Expression::FunctionArgument(index as u32), Span::default())
+        })
+        .collect();
+    let call_result = function
+        .expressions
+        .append(Expression::CallResult(fragment_function), Span::default());
+    function.body.push(
+        Statement::Call {
+            function: fragment_function,
+            arguments: argument_expressions.clone(),
+            result: Some(call_result),
+        },
+        Span::default(),
+    );
+
+    let mut literal = |value| {
+        function
+            .expressions
+            .append(Expression::Literal(Literal::F32(value)), Span::default())
+    };
+    let one = literal(1.0);
+    let min_weight = literal(1e-2);
+    let max_weight = literal(3e3);
+
+    let emit_start = function.expressions.len();
+    let mut append = |expression| function.expressions.append(expression, Span::default());
+    let binary = |op, left, right| Expression::Binary { op, left, right };
+    let color = append(Expression::AccessIndex {
+        base: call_result,
+        index: color_member as u32,
+    });
+    let alpha = append(Expression::AccessIndex {
+        base: color,
+        index: 3,
+    });
+    let rgb = append(Expression::Swizzle {
+        size: VectorSize::Tri,
+        vector: color,
+        pattern: [
+            SwizzleComponent::X,
+            SwizzleComponent::Y,
+            SwizzleComponent::Z,
+            SwizzleComponent::X,
+        ],
+    });
+    let depth = match (depth_member, position) {
+        (Some(depth_member), _) => append(Expression::AccessIndex {
+            base: call_result,
+            index: depth_member as u32,
+        }),
+        (None, (argument, member)) => {
+            let position = match member {
+                Some(member) => append(Expression::AccessIndex {
+                    base: argument_expressions[argument],
+                    index: member as u32,
+                }),
+                None => argument_expressions[argument],
+            };
+            append(Expression::AccessIndex {
+                base: position,
+                index: 2,
+            })
+        }
+    };
+    // w = max(1e-2, 3e3 * (1 - z)^3), the weight suggested in the paper for depth in [0, 1].
+    let distance = append(binary(BinaryOperator::Subtract, one, depth));
+    let distance_squared = append(binary(BinaryOperator::Multiply, distance, distance));
+    let distance_cubed = append(binary(BinaryOperator::Multiply, distance_squared, distance));
+    let scaled = append(binary(BinaryOperator::Multiply, max_weight, distance_cubed));
+    let weight = append(Expression::Math {
+        fun: MathFunction::Max,
+        arg: min_weight,
+        arg1: Some(scaled),
+        arg2: None,
+        arg3: None,
+    });
+    let weighted_alpha = append(binary(BinaryOperator::Multiply, alpha, weight));
+    let weighted_rgb = append(binary(BinaryOperator::Multiply, rgb, weighted_alpha));
+    let accumulation = append(Expression::Compose {
+        ty: vec4_type,
+        components: vec![weighted_rgb, weighted_alpha],
+    });
+    let mut components = vec![accumulation, alpha];
+    if depth_member.is_some() {
+        components.push(depth);
+    }
+    let output = append(Expression::Compose {
+        ty: transparency_output_type,
+        components,
+    });
+    function.body.push(
+        Statement::Emit(function.expressions.range_from(emit_start)),
+        Span::default(),
+    );
+    function.body.push(
+        Statement::Return {
+            value: Some(output),
+        },
+        Span::default(),
+    );
+    module.entry_points[fragment_index].function = function;
+
+    Ok(module)
+}
+
+/// The argument holding the fragment position, along with its member index if the position
+/// is part of a struct argument.
+fn fragment_position(
+    module: &Module,
+    arguments: &[FunctionArgument],
+) -> Option<(usize, Option<usize>)> {
+    let is_position = |binding: &Option<Binding>| {
+        matches!(binding, Some(Binding::BuiltIn(BuiltIn::Position { .. })))
+    };
+    arguments.iter().enumerate().find_map(|(index, argument)| {
+        if is_position(&argument.binding) {
+            return Some((index, None));
+        }
+        match &module.types[argument.ty].inner {
+            TypeInner::Struct { members, .. } => members
+                .iter()
+                .position(|member| is_position(&member.binding))
+                .map(|member| (index, Some(member))),
+            _ => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use naga::valid::ValidationFlags;
+
+    #[test]
+    fn test_transparency_module() {
+        let module = naga::front::wgsl::parse_str(
+            r#"
+            struct VertexOutput {
+                @builtin(position) position: vec4<f32>,
+                @location(0) color: vec3<f32>,
+            };
+
+            struct FragmentOutput {
+                @location(0) color: vec4<f32>,
+                @location(1) normal: vec4<f32>,
+            };
+
+            @vertex
+            fn vs_main(@location(0) position: vec3<f32>) -> VertexOutput {
+                var output: VertexOutput;
+                output.position = vec4<f32>(position, 1.0);
+                output.color = position;
+                return output;
+            }
+
+            @fragment
+            fn fs_main(in: VertexOutput) -> FragmentOutput {
+                if (in.color.x < 0.0) {
+                    discard;
+                }
+                var output: FragmentOutput;
+                output.color = vec4<f32>(in.color, 0.5);
+                output.normal = vec4<f32>(0.0, 0.0, 1.0, 0.0);
+                return output;
+            }
+            "#,
+        )
+        .unwrap();
+        let transparency = transparency_module(&module).unwrap();
+        naga::valid::Validator::new(ValidationFlags::all(), naga::valid::Capabilities::all())
+            .validate(&transparency)
+            .unwrap();
+        assert_eq!(transparency.functions.len(), module.functions.len() + 1);
+    }
+}
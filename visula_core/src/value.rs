@@ -1,11 +1,14 @@
 use std::{
     fmt::{Error, Formatter},
-    ops::{Add, Deref, Div, Mul, Neg, Rem, Sub},
+    ops::{Add, Deref, Div, Mul, Neg, Not, Rem, Sub},
 };
 
 use naga::{GlobalVariable, ResourceBinding, Span};
 
-use crate::{BindingBuilder, InstanceField, TextureField, UniformField};
+use crate::{
+    BindingBuilder, InstanceBindingMode, InstanceField, ShaderError, ShaderFunctions, TextureField,
+    UniformField,
+};
 
 #[derive(Clone)]
 pub struct ExpressionInner {
@@ -42,10 +45,36 @@ pub enum Expression {
         z: ExpressionInner,
         w: ExpressionInner,
     },
+    /// A square matrix of `f32` built from its columns, see [`mat3`].
+    Matrix {
+        columns: Vec<ExpressionInner>,
+    },
     Math {
         function: naga::MathFunction,
         arguments: Vec<ExpressionInner>,
     },
+    /// `accept` where `condition` is true and `reject` elsewhere, see [`select`].
+    Select {
+        condition: ExpressionInner,
+        accept: ExpressionInner,
+        reject: ExpressionInner,
+    },
+    /// Components of a vector, see [`Expression::swizzle`].
+    Swizzle {
+        value: ExpressionInner,
+        components: Vec<naga::SwizzleComponent>,
+    },
+    /// A call to a function written in WGSL, see [`crate::ShaderFunctions::call`].
+    Call {
+        functions: ShaderFunctions,
+        name: String,
+        arguments: Vec<ExpressionInner>,
+    },
+    /// The value converted to another scalar kind, see [`Expression::as_f32`].
+    Convert {
+        value: ExpressionInner,
+        kind: naga::ScalarKind,
+    },
     UV,
     Normal,
     Position,
@@ -53,7 +82,54 @@ pub enum Expression {
     DirectionalLit(ExpressionInner),
     Lit(ExpressionInner),
     ToonLit(ExpressionInner),
+    Pbr(Box<Pbr>),
     ViewDirection,
+    /// The scalar sampled from a volume, available in the transfer function of a volume.
+    VolumeValue,
+}
+
+/// Physically based shading with a GGX BRDF, evaluated for the main light, the fill light
+/// and the additional lights of the scene.
+///
+/// `color` is the base color, as a `vec3` or `vec4`, while `metallic` and `roughness` are
+/// scalars in `[0, 1]` and `emissive` is a `vec3` added to the result. Converts into an
+/// [`Expression`] that can be used wherever `.lit()` can.
+#[derive(Clone, Debug)]
+pub struct Pbr {
+    pub color: Expression,
+    pub metallic: Expression,
+    pub roughness: Expression,
+    pub emissive: Expression,
+    pub normal_map: Option<NormalMap>,
+}
+
+/// A tangent-space normal map applied to the surface normal of a [`Pbr`] material.
+///
+/// The tangent frame is derived from screen-space derivatives of the position and `uv`, so
+/// `uv` must be the coordinate that `normal` was sampled at.
+#[derive(Clone, Debug)]
+pub struct NormalMap {
+    /// Normal map value with components in `[0, 1]`, typically a `TextureField`.
+    pub normal: Expression,
+    pub uv: Expression,
+}
+
+impl Default for Pbr {
+    fn default() -> Self {
+        Pbr {
+            color: glam::Vec3::ONE.into(),
+            metallic: 0.0.into(),
+            roughness: 0.5.into(),
+            emissive: glam::Vec3::ZERO.into(),
+            normal_map: None,
+        }
+    }
+}
+
+impl From<Pbr> for Expression {
+    fn from(value: Pbr) -> Expression {
+        Expression::Pbr(Box::new(value))
+    }
 }
 
 fn math(function: naga::MathFunction, arguments: Vec<ExpressionInner>) -> Expression {
@@ -63,6 +139,18 @@ fn math(function: naga::MathFunction, arguments: Vec<ExpressionInner>) -> Expres
     }
 }
 
+fn binary(
+    left: impl Into<ExpressionInner>,
+    right: impl Into<ExpressionInner>,
+    operator: naga::BinaryOperator,
+) -> Expression {
+    Expression::BinaryOperator {
+        left: left.into(),
+        right: right.into(),
+        operator,
+    }
+}
+
 impl Expression {
     pub fn pow(&self, exponent: impl Into<ExpressionInner>) -> Expression {
         math(naga::MathFunction::Pow, vec![self.into(), exponent.into()])
@@ -167,6 +255,218 @@ impl Expression {
         math(naga::MathFunction::Step, vec![edge.into(), self.into()])
     }
 
+    /// Comparisons produce booleans, or vectors of booleans when comparing vectors, that can
+    /// be combined with [`Expression::and`], [`Expression::or`] and [`Expression::not`] and
+    /// used as the condition of [`select`].
+    pub fn lt(&self, other: impl Into<ExpressionInner>) -> Expression {
+        binary(self, other, naga::BinaryOperator::Less)
+    }
+    pub fn le(&self, other: impl Into<ExpressionInner>) -> Expression {
+        binary(self, other, naga::BinaryOperator::LessEqual)
+    }
+    pub fn gt(&self, other: impl Into<ExpressionInner>) -> Expression {
+        binary(self, other, naga::BinaryOperator::Greater)
+    }
+    pub fn ge(&self, other: impl Into<ExpressionInner>) -> Expression {
+        binary(self, other, naga::BinaryOperator::GreaterEqual)
+    }
+    pub fn eq(&self, other: impl Into<ExpressionInner>) -> Expression {
+        binary(self, other, naga::BinaryOperator::Equal)
+    }
+    pub fn ne(&self, other: impl Into<ExpressionInner>) -> Expression {
+        binary(self, other, naga::BinaryOperator::NotEqual)
+    }
+    /// Component-wise for vectors of booleans. Both sides are always evaluated, since WGSL
+    /// only has short-circuiting `&&` and `||` for scalars.
+    pub fn and(&self, other: impl Into<ExpressionInner>) -> Expression {
+        binary(self, other, naga::BinaryOperator::And)
+    }
+    pub fn or(&self, other: impl Into<ExpressionInner>) -> Expression {
+        binary(self, other, naga::BinaryOperator::InclusiveOr)
+    }
+    pub fn not(&self) -> Expression {
+        !self.clone()
+    }
+
+    /// Matrix product with a matrix or a column vector, the same as `*` but easier to tell
+    /// apart from component-wise products.
+    pub fn mat_mul(&self, other: impl Into<ExpressionInner>) -> Expression {
+        binary(self, other, naga::BinaryOperator::Multiply)
+    }
+    pub fn transpose(&self) -> Expression {
+        math(naga::MathFunction::Transpose, vec![self.into()])
+    }
+    pub fn determinant(&self) -> Expression {
+        math(naga::MathFunction::Determinant, vec![self.into()])
+    }
+    /// The inverse of a square matrix, which is undefined if the determinant is zero.
+    ///
+    /// WGSL has no built-in inverse, so this adds a helper function to the shader. Prefer
+    /// [`Expression::transpose`] for rotation matrices.
+    pub fn inverse(&self) -> Expression {
+        math(naga::MathFunction::Inverse, vec![self.into()])
+    }
+
+    /// Converts integer values to floats, such as a `u32` instance field used in arithmetic
+    /// with `f32` values. Vectors are converted component-wise.
+    pub fn as_f32(&self) -> Expression {
+        Expression::Convert {
+            value: self.into(),
+            kind: naga::ScalarKind::Float,
+        }
+    }
+    /// Converts to signed integers, truncating floats toward zero.
+    pub fn as_i32(&self) -> Expression {
+        Expression::Convert {
+            value: self.into(),
+            kind: naga::ScalarKind::Sint,
+        }
+    }
+    /// Converts to unsigned integers, truncating floats toward zero.
+    pub fn as_u32(&self) -> Expression {
+        Expression::Convert {
+            value: self.into(),
+            kind: naga::ScalarKind::Uint,
+        }
+    }
+
+    /// Unpacks a `u32` with four 8-bit channels, such as an RGBA color, into a `vec4<f32>`
+    /// with each channel normalized to `[0, 1]`. The first channel is the lowest byte.
+    pub fn unpack_unorm(&self) -> Expression {
+        math(naga::MathFunction::Unpack4x8unorm, vec![self.into()])
+    }
+
+    pub fn x(&self) -> Expression {
+        self.swizzle("x")
+    }
+    pub fn y(&self) -> Expression {
+        self.swizzle("y")
+    }
+    pub fn z(&self) -> Expression {
+        self.swizzle("z")
+    }
+    pub fn w(&self) -> Expression {
+        self.swizzle("w")
+    }
+
+    /// Components of this vector given by a pattern such as `"xz"` or `"bgr"`, as in WGSL.
+    ///
+    /// A single component gives a scalar, and two to four components give a vector.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pattern is invalid for this expression, see [`Expression::try_swizzle`].
+    pub fn swizzle(&self, pattern: &str) -> Expression {
+        self.try_swizzle(pattern)
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Like [`Expression::swizzle`], but returns an error if the pattern has other letters
+    /// than `xyzw` and `rgba`, more than four letters, or components that this expression
+    /// does not have. The components are checked against the type of instance and uniform
+    /// fields and of vectors built from them.
+    pub fn try_swizzle(&self, pattern: &str) -> Result<Expression, ShaderError> {
+        if pattern.is_empty() || pattern.len() > 4 {
+            return Err(ShaderError::InvalidSwizzle(format!(
+                "'{pattern}' must have one to four components"
+            )));
+        }
+        let components = pattern
+            .chars()
+            .map(|component| match component {
+                'x' | 'r' => Ok(naga::SwizzleComponent::X),
+                'y' | 'g' => Ok(naga::SwizzleComponent::Y),
+                'z' | 'b' => Ok(naga::SwizzleComponent::Z),
+                'w' | 'a' => Ok(naga::SwizzleComponent::W),
+                _ => Err(ShaderError::InvalidSwizzle(format!(
+                    "'{pattern}' has unknown component '{component}'"
+                ))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        match self.component_count() {
+            Some(1) => {
+                return Err(ShaderError::InvalidSwizzle(format!(
+                    "'{pattern}' of a scalar"
+                )))
+            }
+            Some(count)
+                if components
+                    .iter()
+                    .any(|&component| component as usize >= count) =>
+            {
+                return Err(ShaderError::InvalidSwizzle(format!(
+                    "'{pattern}' of a vector with {count} components"
+                )))
+            }
+            _ => {}
+        }
+        Ok(Expression::Swizzle {
+            value: self.into(),
+            components,
+        })
+    }
+
+    /// The number of components of the value, with `1` for scalars, or `None` if it cannot be
+    /// told without the shader the expression is injected into.
+    pub fn component_count(&self) -> Option<usize> {
+        let max_count = |arguments: &[&ExpressionInner]| {
+            arguments
+                .iter()
+                .map(|argument| argument.component_count())
+                .try_fold(1, |count, other| Some(count.max(other?)))
+        };
+        match self {
+            Expression::Literal(_) | Expression::VolumeValue => Some(1),
+            Expression::Vector2 { .. } => Some(2),
+            Expression::Vector3 { .. } => Some(3),
+            Expression::Vector4 { .. } | Expression::TextureField(_) => Some(4),
+            Expression::Matrix { .. } => None,
+            Expression::Normal
+            | Expression::Position
+            | Expression::ViewDirection
+            | Expression::InputColor => Some(3),
+            Expression::InstanceField(field) => {
+                type_component_count(&field.descriptor.fields[field.field_index].naga_type)
+            }
+            Expression::UniformField(field) => {
+                type_component_count(&field.descriptor.borrow().fields[field.field_index].naga_type)
+            }
+            Expression::Swizzle { components, .. } => Some(components.len()),
+            Expression::Convert { value, .. } => value.component_count(),
+            Expression::Call {
+                functions, name, ..
+            } => functions.result_component_count(name),
+            Expression::Select { accept, .. } => accept.component_count(),
+            Expression::UnaryOperator { value, .. } => value.component_count(),
+            Expression::BinaryOperator { left, right, .. } => max_count(&[left, right]),
+            Expression::Math {
+                function,
+                arguments,
+            } => match function {
+                naga::MathFunction::Length
+                | naga::MathFunction::Distance
+                | naga::MathFunction::Dot
+                | naga::MathFunction::Determinant => Some(1),
+                naga::MathFunction::Cross => Some(3),
+                naga::MathFunction::Unpack4x8unorm | naga::MathFunction::Unpack4x8snorm => Some(4),
+                naga::MathFunction::Unpack2x16unorm
+                | naga::MathFunction::Unpack2x16snorm
+                | naga::MathFunction::Unpack2x16float => Some(2),
+                naga::MathFunction::Pack4x8unorm
+                | naga::MathFunction::Pack4x8snorm
+                | naga::MathFunction::Pack2x16unorm
+                | naga::MathFunction::Pack2x16snorm
+                | naga::MathFunction::Pack2x16float => Some(1),
+                _ => max_count(&arguments.iter().collect::<Vec<_>>()),
+            },
+            Expression::UV
+            | Expression::DirectionalLit(_)
+            | Expression::Lit(_)
+            | Expression::ToonLit(_)
+            | Expression::Pbr(_) => None,
+        }
+    }
+
     pub fn directional_lit(&self) -> Expression {
         Expression::DirectionalLit(self.into())
     }
@@ -178,6 +478,26 @@ impl Expression {
     pub fn toon_lit(&self) -> Expression {
         Expression::ToonLit(self.into())
     }
+
+    /// Shade this color with [`Pbr`], without emission or normal map.
+    pub fn pbr(
+        &self,
+        metallic: impl Into<Expression>,
+        roughness: impl Into<Expression>,
+    ) -> Expression {
+        Pbr {
+            color: self.clone(),
+            metallic: metallic.into(),
+            roughness: roughness.into(),
+            ..Default::default()
+        }
+        .into()
+    }
+
+    /// Whether this is the literal `1.0`, which is the alpha of opaque materials.
+    pub fn is_one(&self) -> bool {
+        matches!(self, Expression::Literal(naga::Literal::F32(value)) if *value == 1.0)
+    }
 }
 
 impl ExpressionInner {
@@ -298,6 +618,14 @@ fn is_expression_vec4(
     }
 }
 
+pub(crate) fn type_component_count(ty: &naga::Type) -> Option<usize> {
+    match ty.inner {
+        naga::TypeInner::Scalar(_) => Some(1),
+        naga::TypeInner::Vector { size, .. } => Some(size as usize),
+        _ => None,
+    }
+}
+
 fn find_function(module: &naga::Module, name: &str) -> naga::Handle<naga::Function> {
     module
         .functions
@@ -401,6 +729,39 @@ impl Expression {
                         ::naga::Span::default(),
                     )
             }
+            Expression::Matrix { columns } => {
+                let size = match columns.len() {
+                    2 => naga::VectorSize::Bi,
+                    3 => naga::VectorSize::Tri,
+                    _ => naga::VectorSize::Quad,
+                };
+                let naga_type = ::naga::Type {
+                    name: None,
+                    inner: ::naga::TypeInner::Matrix {
+                        columns: size,
+                        rows: size,
+                        scalar: ::naga::Scalar {
+                            kind: ::naga::ScalarKind::Float,
+                            width: 4,
+                        },
+                    },
+                };
+                let field_type = module.types.insert(naga_type, ::naga::Span::default());
+                let components_setup = columns
+                    .iter()
+                    .map(|column| column.setup(module, binding_builder))
+                    .collect();
+                module.entry_points[binding_builder.entry_point_index]
+                    .function
+                    .expressions
+                    .append(
+                        ::naga::Expression::Compose {
+                            ty: field_type,
+                            components: components_setup,
+                        },
+                        ::naga::Span::default(),
+                    )
+            }
             Expression::BinaryOperator {
                 left,
                 right,
@@ -463,6 +824,121 @@ impl Expression {
                         naga::Span::default(),
                     )
             }
+            Expression::Select {
+                condition,
+                accept,
+                reject,
+            } => {
+                let condition = condition.setup(module, binding_builder);
+                let accept = accept.setup(module, binding_builder);
+                let reject = reject.setup(module, binding_builder);
+                module.entry_points[binding_builder.entry_point_index]
+                    .function
+                    .expressions
+                    .append(
+                        naga::Expression::Select {
+                            condition,
+                            accept,
+                            reject,
+                        },
+                        naga::Span::default(),
+                    )
+            }
+            Expression::Swizzle { value, components } => {
+                let vector = value.setup(module, binding_builder);
+                let expression = match components[..] {
+                    [component] => naga::Expression::AccessIndex {
+                        base: vector,
+                        index: component as u32,
+                    },
+                    _ => {
+                        let size = match components.len() {
+                            2 => naga::VectorSize::Bi,
+                            3 => naga::VectorSize::Tri,
+                            _ => naga::VectorSize::Quad,
+                        };
+                        let mut pattern = [naga::SwizzleComponent::X; 4];
+                        pattern[..components.len()].copy_from_slice(&components);
+                        naga::Expression::Swizzle {
+                            size,
+                            vector,
+                            pattern,
+                        }
+                    }
+                };
+                module.entry_points[binding_builder.entry_point_index]
+                    .function
+                    .expressions
+                    .append(expression, naga::Span::default())
+            }
+            Expression::Call {
+                functions,
+                name,
+                arguments,
+            } => {
+                let function = binding_builder
+                    .functions
+                    .entry(functions.handle)
+                    .or_insert_with(|| {
+                        functions
+                            .import(module)
+                            .expect("functions are checked when they are created")
+                    })[&name];
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| argument.setup(module, binding_builder))
+                    .collect();
+                let result = module.entry_points[binding_builder.entry_point_index]
+                    .function
+                    .expressions
+                    .append(naga::Expression::CallResult(function), Span::default());
+                binding_builder
+                    .pending_statements
+                    .push(naga::Statement::Call {
+                        function,
+                        arguments,
+                        result: Some(result),
+                    });
+                result
+            }
+            Expression::Convert { value, kind } => {
+                let expr = value.setup(module, binding_builder);
+                module.entry_points[binding_builder.entry_point_index]
+                    .function
+                    .expressions
+                    .append(
+                        naga::Expression::As {
+                            expr,
+                            kind,
+                            convert: Some(4),
+                        },
+                        naga::Span::default(),
+                    )
+            }
+            Expression::InstanceField(field)
+                if binding_builder.shader_stage == naga::ShaderStage::Compute
+                    || (binding_builder.shader_stage == naga::ShaderStage::Vertex
+                        && field.inner.borrow().binding_mode == InstanceBindingMode::Storage) =>
+            {
+                if !binding_builder
+                    .storage_buffers
+                    .contains_key(&field.buffer_handle)
+                {
+                    crate::integrate::integrate_storage_instance(
+                        &field.descriptor,
+                        &field.inner,
+                        &field.buffer_handle,
+                        module,
+                        binding_builder,
+                    );
+                }
+                crate::integrate::load_storage_field(
+                    module,
+                    binding_builder,
+                    &field.buffer_handle,
+                    field.field_index,
+                )
+            }
             Expression::InstanceField(field) => {
                 if !binding_builder.instances.contains_key(&field.buffer_handle) {
                     crate::integrate::integrate_instance(
@@ -486,12 +962,6 @@ impl Expression {
                     )
             }
             Expression::TextureField(field) => {
-                binding_builder
-                    .textures
-                    .entry(field.handle)
-                    .or_insert(crate::TextureBinding {
-                        inner: field.inner.clone(),
-                    });
                 let sampler_type = module.types.insert(
                     naga::Type {
                         name: None,
@@ -501,6 +971,13 @@ impl Expression {
                 );
                 let texture_group = binding_builder.current_bind_group;
                 binding_builder.current_bind_group += 1;
+                binding_builder
+                    .textures
+                    .entry(field.handle)
+                    .or_insert(crate::TextureBinding {
+                        group: texture_group,
+                        inner: field.inner.clone(),
+                    });
                 let sampler_variable = module.global_variables.append(
                     GlobalVariable {
                         name: Some("u_sampler".to_string()),
@@ -621,6 +1098,10 @@ impl Expression {
                         Span::default(),
                     )
             }
+            Expression::VolumeValue => module.entry_points[binding_builder.entry_point_index]
+                .function
+                .expressions
+                .append(naga::Expression::FunctionArgument(0), Span::default()),
             Expression::Normal => load_local_variable("_visula_normal", module, binding_builder),
             Expression::Position => {
                 load_local_variable("_visula_position", module, binding_builder)
@@ -691,6 +1172,66 @@ impl Expression {
                     });
                 result
             }
+            Expression::Pbr(pbr) => {
+                let color_handle = pbr.color.setup(module, binding_builder);
+                let is_vec4 =
+                    is_expression_vec4(color_handle, module, binding_builder.entry_point_index);
+                let metallic_handle = pbr.metallic.setup(module, binding_builder);
+                let roughness_handle = pbr.roughness.setup(module, binding_builder);
+                let emissive_handle = pbr.emissive.setup(module, binding_builder);
+                let mut normal_handle =
+                    load_local_variable("_visula_normal", module, binding_builder);
+                let view_handle =
+                    load_local_variable("_visula_view_direction", module, binding_builder);
+                let position_handle =
+                    load_local_variable("_visula_position", module, binding_builder);
+                let ep = binding_builder.entry_point_index;
+
+                if let Some(normal_map) = &pbr.normal_map {
+                    let map_handle = normal_map.normal.setup(module, binding_builder);
+                    let uv_handle = normal_map.uv.setup(module, binding_builder);
+                    let function = find_function(module, "visula_perturb_normal");
+                    let result = module.entry_points[ep]
+                        .function
+                        .expressions
+                        .append(naga::Expression::CallResult(function), Span::default());
+                    binding_builder
+                        .pending_statements
+                        .push(naga::Statement::Call {
+                            function,
+                            arguments: vec![normal_handle, position_handle, uv_handle, map_handle],
+                            result: Some(result),
+                        });
+                    normal_handle = result;
+                }
+
+                let fname = if is_vec4 {
+                    "visula_pbr_vec4"
+                } else {
+                    "visula_pbr_vec3"
+                };
+                let function = find_function(module, fname);
+                let result = module.entry_points[ep]
+                    .function
+                    .expressions
+                    .append(naga::Expression::CallResult(function), Span::default());
+                binding_builder
+                    .pending_statements
+                    .push(naga::Statement::Call {
+                        function,
+                        arguments: vec![
+                            color_handle,
+                            metallic_handle,
+                            roughness_handle,
+                            emissive_handle,
+                            normal_handle,
+                            view_handle,
+                            position_handle,
+                        ],
+                        result: Some(result),
+                    });
+                result
+            }
             Expression::DirectionalLit(color) => {
                 let color_handle = color.setup(module, binding_builder);
                 let is_vec4 =
@@ -760,9 +1301,24 @@ impl std::fmt::Debug for Expression {
             Expression::Vector4 { .. } => {
                 write!(fmt, "Vector4")?;
             }
+            Expression::Matrix { columns } => {
+                write!(fmt, "Matrix{}", columns.len())?;
+            }
             Expression::Math { function, .. } => {
                 write!(fmt, "{function:?}")?;
             }
+            Expression::Select { .. } => {
+                write!(fmt, "Select")?;
+            }
+            Expression::Swizzle { components, .. } => {
+                write!(fmt, "Swizzle({components:?})")?;
+            }
+            Expression::Convert { kind, .. } => {
+                write!(fmt, "Convert({kind:?})")?;
+            }
+            Expression::Call { name, .. } => {
+                write!(fmt, "Call({name})")?;
+            }
             Expression::UV => {
                 write!(fmt, "UV")?;
             }
@@ -784,9 +1340,15 @@ impl std::fmt::Debug for Expression {
             Expression::ToonLit(_) => {
                 write!(fmt, "ToonLit")?;
             }
+            Expression::Pbr(_) => {
+                write!(fmt, "Pbr")?;
+            }
             Expression::ViewDirection => {
                 write!(fmt, "ViewDirection")?;
             }
+            Expression::VolumeValue => {
+                write!(fmt, "VolumeValue")?;
+            }
         }
         Ok(())
     }
@@ -1052,6 +1614,25 @@ impl Neg for &Expression {
     }
 }
 
+impl Not for Expression {
+    type Output = Expression;
+
+    fn not(self) -> Expression {
+        Expression::UnaryOperator {
+            value: ExpressionInner::new(self),
+            operator: naga::UnaryOperator::LogicalNot,
+        }
+    }
+}
+
+impl Not for &Expression {
+    type Output = Expression;
+
+    fn not(self) -> Expression {
+        !self.clone()
+    }
+}
+
 impl<T> Mul<T> for Expression
 where
     T: Into<Expression>,
@@ -1148,6 +1729,18 @@ impl From<i32> for Expression {
     }
 }
 
+impl From<u32> for Expression {
+    fn from(value: u32) -> Expression {
+        Expression::Literal(naga::Literal::U32(value))
+    }
+}
+
+impl From<bool> for Expression {
+    fn from(value: bool) -> Expression {
+        Expression::Literal(naga::Literal::Bool(value))
+    }
+}
+
 impl From<glam::Vec2> for Expression {
     fn from(value: glam::Vec2) -> Expression {
         Expression::Vector2 {
@@ -1207,6 +1800,24 @@ impl From<[f32; 4]> for Expression {
     }
 }
 
+impl From<glam::Mat2> for Expression {
+    fn from(value: glam::Mat2) -> Expression {
+        mat2(value.x_axis, value.y_axis)
+    }
+}
+
+impl From<glam::Mat3> for Expression {
+    fn from(value: glam::Mat3) -> Expression {
+        mat3(value.x_axis, value.y_axis, value.z_axis)
+    }
+}
+
+impl From<glam::Mat4> for Expression {
+    fn from(value: glam::Mat4) -> Expression {
+        mat4(value.x_axis, value.y_axis, value.z_axis, value.w_axis)
+    }
+}
+
 pub fn vec2(x: impl Into<ExpressionInner>, y: impl Into<ExpressionInner>) -> Expression {
     Expression::Vector2 {
         x: x.into(),
@@ -1240,6 +1851,36 @@ pub fn vec4(
     }
 }
 
+/// A 2x2 matrix with the given `vec2` columns.
+pub fn mat2(x_axis: impl Into<ExpressionInner>, y_axis: impl Into<ExpressionInner>) -> Expression {
+    Expression::Matrix {
+        columns: vec![x_axis.into(), y_axis.into()],
+    }
+}
+
+/// A 3x3 matrix with the given `vec3` columns.
+pub fn mat3(
+    x_axis: impl Into<ExpressionInner>,
+    y_axis: impl Into<ExpressionInner>,
+    z_axis: impl Into<ExpressionInner>,
+) -> Expression {
+    Expression::Matrix {
+        columns: vec![x_axis.into(), y_axis.into(), z_axis.into()],
+    }
+}
+
+/// A 4x4 matrix with the given `vec4` columns.
+pub fn mat4(
+    x_axis: impl Into<ExpressionInner>,
+    y_axis: impl Into<ExpressionInner>,
+    z_axis: impl Into<ExpressionInner>,
+    w_axis: impl Into<ExpressionInner>,
+) -> Expression {
+    Expression::Matrix {
+        columns: vec![x_axis.into(), y_axis.into(), z_axis.into(), w_axis.into()],
+    }
+}
+
 pub fn min(a: impl Into<ExpressionInner>, b: impl Into<ExpressionInner>) -> Expression {
     math(naga::MathFunction::Min, vec![a.into(), b.into()])
 }
@@ -1284,3 +1925,78 @@ pub fn smoothstep(
 pub fn step(edge: impl Into<ExpressionInner>, value: impl Into<ExpressionInner>) -> Expression {
     math(naga::MathFunction::Step, vec![edge.into(), value.into()])
 }
+
+/// `accept` where `condition` is true and `reject` elsewhere.
+///
+/// The condition is a boolean, or a vector of booleans of the same size as `accept` and
+/// `reject` to choose each component separately. Both `accept` and `reject` are evaluated.
+pub fn select(
+    condition: impl Into<ExpressionInner>,
+    accept: impl Into<ExpressionInner>,
+    reject: impl Into<ExpressionInner>,
+) -> Expression {
+    Expression::Select {
+        condition: condition.into(),
+        accept: accept.into(),
+        reject: reject.into(),
+    }
+}
+
+// Quaternions are `vec4` values with the vector part in `xyz` and the scalar part in `w`, the
+// same layout as `glam::Quat`.
+
+/// The `vec4` quaternion `(xyz, w)`.
+fn quat(xyz: &Expression, w: impl Into<ExpressionInner>) -> Expression {
+    vec4(xyz.x(), xyz.y(), xyz.z(), w)
+}
+
+/// Rotates the vector `v` by the unit quaternion `q`.
+pub fn quat_rotate(q: impl Into<Expression>, v: impl Into<Expression>) -> Expression {
+    let (q, v) = (q.into(), v.into());
+    let axis = q.swizzle("xyz");
+    let t = 2.0 * axis.cross(&v);
+    &v + q.w() * &t + axis.cross(t)
+}
+
+/// The product `a * b` of two quaternions, which rotates by `b` and then by `a`.
+pub fn quat_mul(a: impl Into<Expression>, b: impl Into<Expression>) -> Expression {
+    let (a, b) = (a.into(), b.into());
+    let (a_xyz, b_xyz) = (a.swizzle("xyz"), b.swizzle("xyz"));
+    let xyz = a.w() * &b_xyz + b.w() * &a_xyz + a_xyz.cross(&b_xyz);
+    quat(&xyz, a.w() * b.w() - a_xyz.dot(b_xyz))
+}
+
+/// The quaternion that rotates by `angle` radians around `axis`, which need not be normalized.
+pub fn quat_from_axis_angle(
+    axis: impl Into<Expression>,
+    angle: impl Into<Expression>,
+) -> Expression {
+    let half_angle = angle.into() * 0.5;
+    quat(
+        &(axis.into().normalize() * half_angle.sin()),
+        half_angle.cos(),
+    )
+}
+
+/// The shortest rotation that turns the direction `from` into the direction `to`.
+///
+/// Useful to orient glyphs along directions from data, such as
+/// `quat_from_to(Vec3::Z, velocity)`. Opposite directions rotate half a turn around an axis
+/// perpendicular to `from`.
+pub fn quat_from_to(from: impl Into<Expression>, to: impl Into<Expression>) -> Expression {
+    let (from, to) = (from.into().normalize(), to.into().normalize());
+    let cosine = from.dot(&to);
+    let rotation = quat(&from.cross(&to), 1.0 + &cosine).normalize();
+    let other = select(from.x().abs().lt(0.9), glam::Vec3::X, glam::Vec3::Y);
+    let half_turn = quat(&from.cross(other).normalize(), 0.0);
+    select(cosine.lt(-0.9999), half_turn, rotation)
+}
+
+/// The rotation matrix that turns the z axis towards `direction` and the y axis as close to
+/// `up` as possible, which must not be parallel to `direction`.
+pub fn look_at(direction: impl Into<Expression>, up: impl Into<Expression>) -> Expression {
+    let forward = direction.into().normalize();
+    let right = up.into().cross(&forward).normalize();
+    let up = forward.cross(&right);
+    mat3(right, up, forward)
+}
@@ -1,4 +1,5 @@
 use glam::{Quat, Vec2, Vec3, Vec4};
+use half::f16;
 
 pub trait VertexAttrFormat {
     fn vertex_attr_format() -> wgpu::VertexFormat;
@@ -10,6 +11,18 @@ impl VertexAttrFormat for f32 {
     }
 }
 
+impl VertexAttrFormat for u32 {
+    fn vertex_attr_format() -> wgpu::VertexFormat {
+        wgpu::VertexFormat::Uint32
+    }
+}
+
+impl VertexAttrFormat for i32 {
+    fn vertex_attr_format() -> wgpu::VertexFormat {
+        wgpu::VertexFormat::Sint32
+    }
+}
+
 impl VertexAttrFormat for [f32; 2] {
     fn vertex_attr_format() -> wgpu::VertexFormat {
         wgpu::VertexFormat::Float32x2
@@ -51,3 +64,93 @@ impl VertexAttrFormat for Quat {
         wgpu::VertexFormat::Float32x4
     }
 }
+
+/// Each channel is normalized to `[0, 1]`, such as an 8-bit RGBA color.
+impl VertexAttrFormat for [u8; 4] {
+    fn vertex_attr_format() -> wgpu::VertexFormat {
+        wgpu::VertexFormat::Unorm8x4
+    }
+}
+
+/// Each channel is normalized to `[0, 1]`.
+impl VertexAttrFormat for [u16; 2] {
+    fn vertex_attr_format() -> wgpu::VertexFormat {
+        wgpu::VertexFormat::Unorm16x2
+    }
+}
+
+// A single `f16` is not supported, since every field must fill whole 4-byte words.
+impl VertexAttrFormat for [f16; 2] {
+    fn vertex_attr_format() -> wgpu::VertexFormat {
+        wgpu::VertexFormat::Float16x2
+    }
+}
+
+impl VertexAttrFormat for [f16; 4] {
+    fn vertex_attr_format() -> wgpu::VertexFormat {
+        wgpu::VertexFormat::Float16x4
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::naga_type::NagaType;
+
+    fn entry<T: VertexAttrFormat + NagaType>() -> (usize, wgpu::VertexFormat, naga::TypeInner) {
+        (
+            std::mem::size_of::<T>(),
+            T::vertex_attr_format(),
+            T::naga_type().inner,
+        )
+    }
+
+    fn vector(size: naga::VectorSize, scalar: naga::Scalar) -> naga::TypeInner {
+        naga::TypeInner::Vector { size, scalar }
+    }
+
+    #[test]
+    fn test_formats_and_naga_types() {
+        use naga::{Scalar, TypeInner, VectorSize};
+        use wgpu::VertexFormat;
+
+        let table = [
+            (
+                entry::<u32>(),
+                VertexFormat::Uint32,
+                TypeInner::Scalar(Scalar::U32),
+            ),
+            (
+                entry::<i32>(),
+                VertexFormat::Sint32,
+                TypeInner::Scalar(Scalar::I32),
+            ),
+            (
+                entry::<[u8; 4]>(),
+                VertexFormat::Unorm8x4,
+                vector(VectorSize::Quad, Scalar::F32),
+            ),
+            (
+                entry::<[u16; 2]>(),
+                VertexFormat::Unorm16x2,
+                vector(VectorSize::Bi, Scalar::F32),
+            ),
+            (
+                entry::<[f16; 2]>(),
+                VertexFormat::Float16x2,
+                vector(VectorSize::Bi, Scalar::F32),
+            ),
+            (
+                entry::<[f16; 4]>(),
+                VertexFormat::Float16x4,
+                vector(VectorSize::Quad, Scalar::F32),
+            ),
+        ];
+        for ((size, format, inner), expected_format, expected_inner) in table {
+            assert_eq!(format, expected_format);
+            assert_eq!(inner, expected_inner, "{format:?}");
+            // The attribute covers the whole field, so the fields that follow line up.
+            assert_eq!(format.size(), size as u64, "{format:?}");
+        }
+    }
+}
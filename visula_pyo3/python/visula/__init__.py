@@ -18,12 +18,18 @@ from .math import (
     fract,
     length,
     log,
+    look_at,
     max,
     min,
     mix,
     normalize,
     pow,
+    quat_from_axis_angle,
+    quat_from_to,
+    quat_mul,
+    quat_rotate,
     round,
+    select,
     sign,
     sin,
     smoothstep,
@@ -32,6 +38,7 @@ from .math import (
     tan,
 )
 from .uniform import Uniform
+from .shader_functions import ShaderFunctions
 from .gui import Slider
 
 __all__ = [
@@ -42,6 +49,7 @@ __all__ = [
     "ExpressionLike",
     "ColormapName",
     "InstanceBuffer",
+    "ShaderFunctions",
     "Uniform",
     "Slider",
     "abs",
@@ -58,12 +66,18 @@ __all__ = [
     "fract",
     "length",
     "log",
+    "look_at",
     "max",
     "min",
     "mix",
     "normalize",
     "pow",
+    "quat_from_axis_angle",
+    "quat_from_to",
+    "quat_mul",
+    "quat_rotate",
     "round",
+    "select",
     "sign",
     "sin",
     "smoothstep",
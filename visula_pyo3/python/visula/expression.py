@@ -1,5 +1,6 @@
 from __future__ import annotations
 
+import re
 from typing import Union
 
 import numpy.typing as npt
@@ -14,6 +15,9 @@ from ._visula_pyo3 import (
 )
 
 
+_SWIZZLE = re.compile("[xyzw]{1,4}|[rgba]{1,4}")
+
+
 def _ensure_expression(other: ExpressionLike) -> _Expression:
     if isinstance(other, Expression):
         return other.inner
@@ -75,6 +79,101 @@ class Expression:
     def __neg__(self) -> Expression:
         return Expression(self.inner.neg())
 
+    # Comparisons build boolean expressions for the shader instead of comparing the
+    # expressions themselves, like NumPy arrays do.
+    def __lt__(self, other: ExpressionLike) -> Expression:
+        return Expression(self.inner.lt(_ensure_expression(other)))
+
+    def __le__(self, other: ExpressionLike) -> Expression:
+        return Expression(self.inner.le(_ensure_expression(other)))
+
+    def __gt__(self, other: ExpressionLike) -> Expression:
+        return Expression(self.inner.gt(_ensure_expression(other)))
+
+    def __ge__(self, other: ExpressionLike) -> Expression:
+        return Expression(self.inner.ge(_ensure_expression(other)))
+
+    def __eq__(self, other: ExpressionLike) -> Expression:  # type: ignore[override]
+        return Expression(self.inner.eq(_ensure_expression(other)))
+
+    def __ne__(self, other: ExpressionLike) -> Expression:  # type: ignore[override]
+        return Expression(self.inner.ne(_ensure_expression(other)))
+
+    __hash__ = object.__hash__
+
+    def __and__(self, other: ExpressionLike) -> Expression:
+        return Expression(self.inner.logical_and(_ensure_expression(other)))
+
+    def __rand__(self, other: ExpressionLike) -> Expression:
+        return Expression(other) & self
+
+    def __or__(self, other: ExpressionLike) -> Expression:
+        return Expression(self.inner.logical_or(_ensure_expression(other)))
+
+    def __ror__(self, other: ExpressionLike) -> Expression:
+        return Expression(other) | self
+
+    def __invert__(self) -> Expression:
+        return Expression(self.inner.logical_not())
+
+    def __matmul__(self, other: ExpressionLike) -> Expression:
+        return Expression(self.inner.mat_mul(_ensure_expression(other)))
+
+    def __rmatmul__(self, other: ExpressionLike) -> Expression:
+        return Expression(other) @ self
+
+    def transpose(self) -> Expression:
+        return Expression(self.inner.transpose())
+
+    def determinant(self) -> Expression:
+        return Expression(self.inner.determinant())
+
+    def inverse(self) -> Expression:
+        return Expression(self.inner.inverse())
+
+    def as_f32(self) -> Expression:
+        return Expression(self.inner.as_f32())
+
+    def as_i32(self) -> Expression:
+        return Expression(self.inner.as_i32())
+
+    def as_u32(self) -> Expression:
+        return Expression(self.inner.as_u32())
+
+    # Unpacks a u32 with four 8-bit channels, such as an RGBA color, into normalized floats.
+    def unpack_unorm(self) -> Expression:
+        return Expression(self.inner.unpack_unorm())
+
+    @property
+    def x(self) -> Expression:
+        return Expression(self.inner.swizzle("x"))
+
+    @property
+    def y(self) -> Expression:
+        return Expression(self.inner.swizzle("y"))
+
+    @property
+    def z(self) -> Expression:
+        return Expression(self.inner.swizzle("z"))
+
+    @property
+    def w(self) -> Expression:
+        return Expression(self.inner.swizzle("w"))
+
+    # Other swizzles such as `xz` or `rgb` are looked up on demand.
+    def __getattr__(self, name: str) -> Expression:
+        if _SWIZZLE.fullmatch(name):
+            return Expression(self.inner.swizzle(name))
+        raise AttributeError(
+            f"'{type(self).__name__}' object has no attribute '{name}'"
+        )
+
+    def __bool__(self) -> bool:
+        raise TypeError(
+            "Expressions are evaluated in the shader and have no truth value, "
+            "use select() instead of if, and & and | instead of and and or"
+        )
+
 
 ExpressionLike = Union[Expression, _Expression, npt.ArrayLike]
 
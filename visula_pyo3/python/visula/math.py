@@ -1,6 +1,12 @@
 from typing import Literal
 
 from ._visula_pyo3 import colormap as _colormap
+from ._visula_pyo3 import look_at as _look_at
+from ._visula_pyo3 import quat_from_axis_angle as _quat_from_axis_angle
+from ._visula_pyo3 import quat_from_to as _quat_from_to
+from ._visula_pyo3 import quat_mul as _quat_mul
+from ._visula_pyo3 import quat_rotate as _quat_rotate
+from ._visula_pyo3 import select as _select
 from .expression import Expression, ExpressionLike, _ensure_expression
 
 ColormapName = Literal["viridis", "plasma", "magma", "inferno"]
@@ -118,3 +124,38 @@ def smoothstep(
 
 def step(edge: ExpressionLike, value: ExpressionLike) -> Expression:
     return Expression(_ensure_expression(value).step(_ensure_expression(edge)))
+
+
+def select(
+    condition: ExpressionLike, accept: ExpressionLike, reject: ExpressionLike
+) -> Expression:
+    return Expression(
+        _select(
+            _ensure_expression(condition),
+            _ensure_expression(accept),
+            _ensure_expression(reject),
+        )
+    )
+
+
+# Quaternions are vec4 values with the vector part in xyz and the scalar part in w.
+def quat_rotate(q: ExpressionLike, v: ExpressionLike) -> Expression:
+    return Expression(_quat_rotate(_ensure_expression(q), _ensure_expression(v)))
+
+
+def quat_mul(a: ExpressionLike, b: ExpressionLike) -> Expression:
+    return Expression(_quat_mul(_ensure_expression(a), _ensure_expression(b)))
+
+
+def quat_from_axis_angle(axis: ExpressionLike, angle: ExpressionLike) -> Expression:
+    return Expression(
+        _quat_from_axis_angle(_ensure_expression(axis), _ensure_expression(angle))
+    )
+
+
+def quat_from_to(from_: ExpressionLike, to: ExpressionLike) -> Expression:
+    return Expression(_quat_from_to(_ensure_expression(from_), _ensure_expression(to)))
+
+
+def look_at(direction: ExpressionLike, up: ExpressionLike) -> Expression:
+    return Expression(_look_at(_ensure_expression(direction), _ensure_expression(up)))
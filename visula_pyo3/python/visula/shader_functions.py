@@ -0,0 +1,17 @@
+from ._visula_pyo3 import ShaderFunctions as _ShaderFunctions
+from .expression import Expression, ExpressionLike, _ensure_expression
+
+
+# Functions written in WGSL that expressions can call. The source must define plain
+# functions, and may define the structs and constants they use, but no entry points or
+# bindings.
+class ShaderFunctions:
+    def __init__(self, source: str):
+        self._inner = _ShaderFunctions(source)
+
+    def call(self, name: str, *arguments: ExpressionLike) -> Expression:
+        return Expression(
+            self._inner.call(
+                name, [_ensure_expression(argument) for argument in arguments]
+            )
+        )
@@ -22,7 +22,7 @@ use visula::{
 };
 use visula_core::glam::{Vec3, Vec4};
 use visula_core::uuid::Uuid;
-use visula_core::{UniformBufferInner, UniformField};
+use visula_core::{ShaderFunctions, UniformBufferInner, UniformField};
 use visula_derive::Instance;
 use wgpu::util::DeviceExt;
 use wgpu::BufferUsages;
@@ -270,6 +270,99 @@ impl PyExpression {
             inner: self.inner.smoothstep(&edge_low.inner, &edge_high.inner),
         }
     }
+    fn lt(&self, other: &PyExpression) -> PyExpression {
+        Self {
+            inner: self.inner.lt(&other.inner),
+        }
+    }
+    fn le(&self, other: &PyExpression) -> PyExpression {
+        Self {
+            inner: self.inner.le(&other.inner),
+        }
+    }
+    fn gt(&self, other: &PyExpression) -> PyExpression {
+        Self {
+            inner: self.inner.gt(&other.inner),
+        }
+    }
+    fn ge(&self, other: &PyExpression) -> PyExpression {
+        Self {
+            inner: self.inner.ge(&other.inner),
+        }
+    }
+    fn eq(&self, other: &PyExpression) -> PyExpression {
+        Self {
+            inner: self.inner.eq(&other.inner),
+        }
+    }
+    fn ne(&self, other: &PyExpression) -> PyExpression {
+        Self {
+            inner: self.inner.ne(&other.inner),
+        }
+    }
+    fn logical_and(&self, other: &PyExpression) -> PyExpression {
+        Self {
+            inner: self.inner.and(&other.inner),
+        }
+    }
+    fn logical_or(&self, other: &PyExpression) -> PyExpression {
+        Self {
+            inner: self.inner.or(&other.inner),
+        }
+    }
+    fn logical_not(&self) -> PyExpression {
+        Self {
+            inner: self.inner.not(),
+        }
+    }
+    fn mat_mul(&self, other: &PyExpression) -> PyExpression {
+        Self {
+            inner: self.inner.mat_mul(&other.inner),
+        }
+    }
+    fn transpose(&self) -> PyExpression {
+        Self {
+            inner: self.inner.transpose(),
+        }
+    }
+    fn determinant(&self) -> PyExpression {
+        Self {
+            inner: self.inner.determinant(),
+        }
+    }
+    fn inverse(&self) -> PyExpression {
+        Self {
+            inner: self.inner.inverse(),
+        }
+    }
+    fn as_f32(&self) -> PyExpression {
+        Self {
+            inner: self.inner.as_f32(),
+        }
+    }
+    fn as_i32(&self) -> PyExpression {
+        Self {
+            inner: self.inner.as_i32(),
+        }
+    }
+    fn as_u32(&self) -> PyExpression {
+        Self {
+            inner: self.inner.as_u32(),
+        }
+    }
+    fn unpack_unorm(&self) -> PyExpression {
+        Self {
+            inner: self.inner.unpack_unorm(),
+        }
+    }
+    fn swizzle(&self, pattern: &str) -> PyResult<PyExpression> {
+        Ok(Self {
+            inner: self
+                .inner
+                .try_swizzle(pattern)
+                .map_err(|error| PyRuntimeError::new_err(error.to_string()))?,
+        })
+    }
 }
 
 pub struct SliderBank {
@@ -411,6 +504,35 @@ impl SliderBank {
     }
 }
 
+#[pyclass(name = "ShaderFunctions", unsendable)]
+struct PyShaderFunctions {
+    inner: ShaderFunctions,
+}
+
+#[pymethods]
+impl PyShaderFunctions {
+    #[new]
+    fn new(source: &str) -> PyResult<Self> {
+        Ok(Self {
+            inner: ShaderFunctions::new(source)
+                .map_err(|error| PyRuntimeError::new_err(error.to_string()))?,
+        })
+    }
+
+    fn call(&self, name: &str, arguments: Vec<PyExpression>) -> PyResult<PyExpression> {
+        let arguments: Vec<Expression> = arguments
+            .into_iter()
+            .map(|argument| argument.inner)
+            .collect();
+        Ok(PyExpression {
+            inner: self
+                .inner
+                .try_call(name, &arguments)
+                .map_err(|error| PyRuntimeError::new_err(error.to_string()))?,
+        })
+    }
+}
+
 #[pyclass(unsendable)]
 pub struct PySlider {
     #[pyo3(get, set)]
@@ -752,6 +874,48 @@ fn vec4(x: &PyExpression, y: &PyExpression, z: &PyExpression, w: &PyExpression)
     }
 }
 
+#[pyfunction]
+fn select(condition: &PyExpression, accept: &PyExpression, reject: &PyExpression) -> PyExpression {
+    PyExpression {
+        inner: visula_core::select(&condition.inner, &accept.inner, &reject.inner),
+    }
+}
+
+#[pyfunction]
+fn quat_rotate(q: &PyExpression, v: &PyExpression) -> PyExpression {
+    PyExpression {
+        inner: visula_core::quat_rotate(&q.inner, &v.inner),
+    }
+}
+
+#[pyfunction]
+fn quat_mul(a: &PyExpression, b: &PyExpression) -> PyExpression {
+    PyExpression {
+        inner: visula_core::quat_mul(&a.inner, &b.inner),
+    }
+}
+
+#[pyfunction]
+fn quat_from_axis_angle(axis: &PyExpression, angle: &PyExpression) -> PyExpression {
+    PyExpression {
+        inner: visula_core::quat_from_axis_angle(&axis.inner, &angle.inner),
+    }
+}
+
+#[pyfunction]
+fn quat_from_to(from: &PyExpression, to: &PyExpression) -> PyExpression {
+    PyExpression {
+        inner: visula_core::quat_from_to(&from.inner, &to.inner),
+    }
+}
+
+#[pyfunction]
+fn look_at(direction: &PyExpression, up: &PyExpression) -> PyExpression {
+    PyExpression {
+        inner: visula_core::look_at(&direction.inner, &up.inner),
+    }
+}
+
 #[pyfunction]
 fn convert(py: Python, pyapplication: &PyApplication, obj: Py<PyAny>) -> PyResult<PyExpression> {
     let PyApplication { application, .. } = pyapplication;
@@ -833,6 +997,10 @@ fn convert(py: Python, pyapplication: &PyApplication, obj: Py<PyAny>) -> PyResul
             inner: instance.position,
         });
     }
+    // Python booleans are also integers, so they are checked before numbers.
+    if let Ok(x) = obj.extract::<bool>(py) {
+        return Ok(PyExpression { inner: x.into() });
+    }
     if let Ok(x) = obj.extract::<f32>(py) {
         return Ok(PyExpression { inner: x.into() });
     }
@@ -964,6 +1132,12 @@ fn visula_pyo3(_py: Python, m: &Bound<PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(vec2, m)?)?;
     m.add_function(wrap_pyfunction!(vec3, m)?)?;
     m.add_function(wrap_pyfunction!(vec4, m)?)?;
+    m.add_function(wrap_pyfunction!(select, m)?)?;
+    m.add_function(wrap_pyfunction!(quat_rotate, m)?)?;
+    m.add_function(wrap_pyfunction!(quat_mul, m)?)?;
+    m.add_function(wrap_pyfunction!(quat_from_axis_angle, m)?)?;
+    m.add_function(wrap_pyfunction!(quat_from_to, m)?)?;
+    m.add_function(wrap_pyfunction!(look_at, m)?)?;
     m.add_class::<PySpheres>()?;
     m.add_class::<PyLines>()?;
     m.add_class::<PyExpression>()?;
@@ -973,5 +1147,6 @@ fn visula_pyo3(_py: Python, m: &Bound<PyModule>) -> PyResult<()> {
     m.add_class::<PyUniformBuffer>()?;
     m.add_class::<PyUniformField>()?;
     m.add_class::<PySlider>()?;
+    m.add_class::<PyShaderFunctions>()?;
     Ok(())
 }
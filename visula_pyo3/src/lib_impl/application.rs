@@ -58,6 +58,12 @@ impl Simulation for PySimulation<'_> {
         }
     }
 
+    fn render_transparent(&mut self, data: &mut visula::TransparentRenderData) {
+        for renderable in self.renderables {
+            renderable.render_transparent(data);
+        }
+    }
+
     fn gui(&mut self, _application: &Application, context: &egui::Context) {
         if self.controls.is_empty() {
             return;